@@ -0,0 +1,125 @@
+use chrono::{Duration, Local};
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+use note::config::NoteOrder;
+use note::index::{self, SearchIndex};
+use note::manager::{AppendOptions, NoteFilter, NoteManager};
+use note::note::Note;
+use note::parser::NoteParser;
+use note::search::Matcher;
+use note::time::SystemClock;
+
+// Roughly the size at which the soft note limit starts warning
+const NOTE_COUNT: usize = 10_000;
+
+fn sample_store_text() -> String {
+    let now = Local::now();
+    let mut text = String::new();
+    for i in 0..NOTE_COUNT {
+        let timestamp = now - Duration::minutes(i as i64);
+        text.push_str(&format!("#{:04x} {} tags=bench,n{}\n", i, timestamp.to_rfc3339(), i % 50));
+        text.push_str(&format!("Note number {} about deploying service {}\n", i, i % 97));
+        text.push_str("# a line that needs escaping\nand some trailing content\n\n");
+    }
+    text.replace("\n# a line", "\n\\# a line")
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let text = sample_store_text();
+    c.bench_function("parse 10k notes", |b| {
+        b.iter(|| NoteParser::parse_notes_from_text(black_box(&text), Local::now()).unwrap())
+    });
+}
+
+fn bench_save(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("note-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("notes.txt"), sample_store_text()).unwrap();
+    let mut manager = NoteManager::open(dir.clone()).unwrap();
+    
+    c.bench_function("save 10k notes", |b| b.iter(|| manager.save_notes().unwrap()));
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// What `note "..."` reads before adding to a 10k store: everything, as other
+// commands do, or just the IDs on the quick path
+fn bench_add_startup(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("note-bench-startup-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("notes.txt"), sample_store_text()).unwrap();
+    
+    c.bench_function("open 10k notes", |b| b.iter(|| NoteManager::open(black_box(dir.clone())).unwrap()));
+    c.bench_function("scan ids of 10k notes", |b| {
+        b.iter(|| NoteParser::scan_ids(&std::fs::read_to_string(black_box(dir.join("notes.txt"))).unwrap()))
+    });
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// The whole add, including the synced write both paths share
+fn bench_add(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("note-bench-add-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let text = sample_store_text();
+    let reset = || std::fs::write(dir.join("notes.txt"), &text).unwrap();
+    
+    c.bench_function("add to 10k notes", |b| {
+        b.iter_batched(reset, |_| {
+            let mut manager = NoteManager::open(dir.clone()).unwrap();
+            manager.add_note(black_box("a fresh note".to_string()), &[]).unwrap()
+        }, BatchSize::PerIteration)
+    });
+    let options = AppendOptions { order: NoteOrder::Preserve, audit_command: None, lock_timeout: None, tidy: false, clock: &SystemClock, inline_limit: 0 };
+    c.bench_function("quick add to 10k notes", |b| {
+        b.iter_batched(reset, |_| {
+            NoteManager::append_note(&dir, black_box("a fresh note".to_string()), &[], BTreeMap::new(), &options).unwrap()
+        }, BatchSize::PerIteration)
+    });
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn bench_search(c: &mut Criterion) {
+    let notes = NoteParser::parse_notes_from_text(&sample_store_text(), Local::now()).unwrap();
+    let filter = NoteFilter {
+        query: Some(Matcher::new("service 42", false).unwrap()),
+        ..NoteFilter::default()
+    };
+    
+    c.bench_function("search 10k notes", |b| {
+        b.iter(|| notes.iter().filter(|note| filter.matches(black_box(note))).count())
+    });
+}
+
+fn bench_indexed_search(c: &mut Criterion) {
+    let text = sample_store_text();
+    let notes = NoteParser::parse_notes_from_text(&text, Local::now()).unwrap();
+    let mut index = SearchIndex::load(std::env::temp_dir().join("note-bench-index.txt"));
+    index.update(&notes, &index::content_hash(&text));
+    let filter = NoteFilter {
+        query: Some(Matcher::new("service 42", false).unwrap()),
+        ..NoteFilter::default()
+    };
+    
+    c.bench_function("indexed search 10k notes", |b| {
+        b.iter(|| {
+            let candidates = index.candidates(black_box("service 42")).unwrap();
+            notes.iter()
+                .filter(|note| candidates.contains(note.id.as_str()) && filter.matches(note))
+                .count()
+        })
+    });
+}
+
+fn bench_new_id(c: &mut Criterion) {
+    let existing_ids: Vec<String> = (0..NOTE_COUNT).map(|i| format!("{:04x}", i)).collect();
+    c.bench_function("new note id among 10k", |b| {
+        b.iter(|| Note::new(black_box("a fresh note".to_string()), &existing_ids, Local::now()))
+    });
+}
+
+criterion_group!(benches, bench_parse, bench_save, bench_add_startup, bench_add, bench_search, bench_indexed_search, bench_new_id);
+criterion_main!(benches);
\ No newline at end of file
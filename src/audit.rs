@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::index;
+use crate::note::Note;
+use crate::resurface;
+
+// Append-only record of every change to the store, one JSON object per line
+// in audit.jsonl next to notes.txt. Entries hold content hashes, never the
+// content itself, so the log can be kept or shared without leaking notes.
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub time: DateTime<Local>,
+    // The command that made the change, e.g. "add" or "tag rename"
+    pub command: String,
+    // "add", "rm", "edit" (content changed) or "meta" (tags or metadata changed)
+    pub op: String,
+    pub id: String,
+    // Content hash after the change; absent for "rm"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    // Content hash before the change; absent for "add"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+}
+
+// What a note looked like at the last save: its content hash and metadata
+// without the view time, which changes on every `note show`
+struct NoteState {
+    hash: String,
+    meta: Vec<(String, String)>,
+}
+
+impl NoteState {
+    fn of(note: &Note) -> Self {
+        Self {
+            hash: index::content_hash(&note.content),
+            meta: note.meta.iter()
+                .filter(|(key, _)| key.as_str() != resurface::VIEWED_KEY)
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        }
+    }
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    command: String,
+    snapshot: HashMap<String, NoteState>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf, command: &str, notes: &[Note]) -> Self {
+        let mut log = Self { path, command: command.to_string(), snapshot: HashMap::new() };
+        log.reset(notes);
+        log
+    }
+    
+    // Takes `notes` as the new baseline without logging anything, e.g. after
+    // the file was reloaded with changes another process already logged
+    pub fn reset(&mut self, notes: &[Note]) {
+        self.snapshot = notes.iter().map(|note| (note.id.clone(), NoteState::of(note))).collect();
+    }
+    
+    // Logs how `notes` differ from the last baseline, as of `time`, and makes
+    // them the new one
+    pub fn record(&mut self, notes: &[Note], time: DateTime<Local>) -> Result<()> {
+        let entry = |op: &str, id: &str, hash: Option<&String>, previous: Option<&String>| Entry {
+            time,
+            command: self.command.clone(),
+            op: op.to_string(),
+            id: id.to_string(),
+            hash: hash.cloned(),
+            previous: previous.cloned(),
+        };
+        
+        let mut entries = Vec::new();
+        let mut current = HashMap::new();
+        for note in notes {
+            let state = NoteState::of(note);
+            match self.snapshot.get(&note.id) {
+                None => entries.push(entry("add", &note.id, Some(&state.hash), None)),
+                Some(old) if old.hash != state.hash => entries.push(entry("edit", &note.id, Some(&state.hash), Some(&old.hash))),
+                Some(old) if old.meta != state.meta => entries.push(entry("meta", &note.id, Some(&state.hash), Some(&old.hash))),
+                Some(_) => {}
+            }
+            current.insert(note.id.clone(), state);
+        }
+        let mut removed: Vec<(&String, &NoteState)> = self.snapshot.iter()
+            .filter(|(id, _)| !current.contains_key(*id))
+            .collect();
+        removed.sort_by_key(|(id, _)| id.as_str());
+        entries.extend(removed.into_iter().map(|(id, old)| entry("rm", id, None, Some(&old.hash))));
+        
+        append(&self.path, &entries)?;
+        self.snapshot = current;
+        Ok(())
+    }
+}
+
+// Logs a note added without loading the store, as `note "..."` does
+pub fn record_added(path: &Path, command: &str, note: &Note) -> Result<()> {
+    append(path, &[Entry {
+        time: note.timestamp,
+        command: command.to_string(),
+        op: "add".to_string(),
+        id: note.id.clone(),
+        hash: Some(index::content_hash(&note.content)),
+        previous: None,
+    }])
+}
+
+fn append(path: &Path, entries: &[Entry]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut lines = String::new();
+    for entry in entries {
+        lines.push_str(&serde_json::to_string(entry).context("Failed to encode audit entry")?);
+        lines.push('\n');
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)
+        .context("Failed to open audit log")?;
+    file.write_all(lines.as_bytes()).context("Failed to write audit log")
+}
+
+// Every readable entry, oldest first. Lines that don't parse, such as one cut
+// short by a crash, are skipped.
+pub fn read(path: &Path) -> Result<Vec<Entry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(path).context("Failed to read audit log")?;
+    Ok(content.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
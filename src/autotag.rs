@@ -0,0 +1,55 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+use crate::note::Note;
+
+// With [autotag] enabled, #hashtags written in a note's text become tags when
+// the note is added. A hashtag starts a word, contains at least one letter and
+// is made of letters, digits, '_', '-' and '/', so issue numbers (#12),
+// shebangs (#!/bin/sh), headings and URL fragments are left alone, as is
+// anything inside ``` fences.
+pub struct AutoTagger {
+    // Hashtags these match in full are never extracted
+    ignore: Vec<Regex>,
+}
+
+impl AutoTagger {
+    pub fn new(ignore: &[String]) -> Result<Self> {
+        let ignore = ignore.iter()
+            .map(|pattern| Regex::new(&format!("^(?:{})$", pattern)).with_context(|| format!("Invalid autotag ignore pattern: {}", pattern)))
+            .collect::<Result<_>>()?;
+        Ok(Self { ignore })
+    }
+    
+    // Tags in the order they first appear, without the '#'
+    pub fn extract(&self, content: &str) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        let mut fenced = false;
+        for line in content.lines() {
+            if line.trim_start().starts_with("```") {
+                fenced = !fenced;
+                continue;
+            }
+            if fenced {
+                continue;
+            }
+            
+            let mut previous = ' ';
+            for (index, c) in line.char_indices() {
+                if c == '#' && (previous.is_whitespace() || previous == '(') {
+                    let word: String = line[index + 1..].chars()
+                        .take_while(|&c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+                        .collect();
+                    let tag = Note::normalize_tag(word.trim_end_matches(['-', '/']));
+                    if tag.chars().any(char::is_alphabetic)
+                        && !self.ignore.iter().any(|pattern| pattern.is_match(&tag))
+                        && !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+                previous = c;
+            }
+        }
+        tags
+    }
+}
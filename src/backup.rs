@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BACKUP_PREFIX: &str = "notes-";
+const BACKUP_SUFFIX: &str = ".txt.zst";
+
+pub struct BackupInfo {
+    pub name: String,
+    pub created: DateTime<Local>,
+    pub size: u64,
+}
+
+// Timestamped, zstd-compressed snapshots of the notes file
+pub struct BackupStore {
+    dir: PathBuf,
+}
+
+impl BackupStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+    
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    
+    // Named after `now`
+    pub fn create(&self, content: &str, now: DateTime<Local>) -> Result<String> {
+        fs::create_dir_all(&self.dir)
+            .context("Failed to create backups directory")?;
+        
+        let mut name = format!("{}{}{}", BACKUP_PREFIX, now.format("%Y%m%d-%H%M%S"), BACKUP_SUFFIX);
+        // Several snapshots within one second (e.g. before chained bulk operations)
+        let mut counter = 1;
+        while self.dir.join(&name).exists() {
+            name = format!("{}{}-{}{}", BACKUP_PREFIX, now.format("%Y%m%d-%H%M%S"), counter, BACKUP_SUFFIX);
+            counter += 1;
+        }
+        
+        let compressed = zstd::encode_all(content.as_bytes(), 0)
+            .context("Failed to compress backup")?;
+        fs::write(self.dir.join(&name), compressed)
+            .context("Failed to write backup")?;
+        
+        Ok(name)
+    }
+    
+    // Newest first
+    pub fn list(&self) -> Result<Vec<BackupInfo>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        
+        let mut backups = Vec::new();
+        for entry in fs::read_dir(&self.dir).context("Failed to read backups directory")? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(BACKUP_PREFIX) || !name.ends_with(BACKUP_SUFFIX) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            backups.push(BackupInfo {
+                created: Self::created_at(&name)
+                    .or_else(|| metadata.modified().ok().map(DateTime::<Local>::from))
+                    .unwrap_or_default(),
+                name,
+                size: metadata.len(),
+            });
+        }
+        
+        backups.sort_by_key(|backup| std::cmp::Reverse(Self::sort_key(&backup.name)));
+        Ok(backups)
+    }
+    
+    // The time in the name, which came from the store's clock
+    fn created_at(name: &str) -> Option<DateTime<Local>> {
+        let stamp = name.trim_start_matches(BACKUP_PREFIX).get(..15)?;
+        NaiveDateTime::parse_from_str(stamp, "%Y%m%d-%H%M%S").ok()?
+            .and_local_timezone(Local).earliest()
+    }
+    
+    // Names embed the creation time plus an optional same-second counter
+    fn sort_key(name: &str) -> (String, u32) {
+        let stamp = name.trim_start_matches(BACKUP_PREFIX).trim_end_matches(BACKUP_SUFFIX);
+        let mut parts = stamp.splitn(3, '-');
+        let date_time = format!("{}-{}", parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        let counter = parts.next().and_then(|c| c.parse().ok()).unwrap_or(0);
+        (date_time, counter)
+    }
+    
+    // Accepts a full backup name or any unique part of it (e.g. "20250709")
+    pub fn find(&self, name: &str) -> Result<Vec<String>> {
+        let names: Vec<String> = self.list()?.into_iter().map(|backup| backup.name).collect();
+        if names.iter().any(|backup| backup == name) {
+            return Ok(vec![name.to_string()]);
+        }
+        Ok(names.into_iter().filter(|backup| backup.contains(name)).collect())
+    }
+    
+    // The newest snapshot taken on or before the day, which holds the notes
+    // as they stood at its end
+    pub fn latest_by(&self, day: NaiveDate) -> Result<Option<String>> {
+        let day = day.format("%Y%m%d").to_string();
+        Ok(self.list()?.into_iter()
+            .map(|backup| backup.name)
+            .find(|name| Self::sort_key(name).0.get(..8).is_some_and(|taken| taken <= day.as_str())))
+    }
+    
+    pub fn read(&self, name: &str) -> Result<String> {
+        let compressed = fs::read(self.dir.join(name))
+            .context(format!("Failed to read backup: {}", name))?;
+        let bytes = zstd::decode_all(compressed.as_slice())
+            .context(format!("Failed to decompress backup: {}", name))?;
+        String::from_utf8(bytes).context("Backup is not valid UTF-8")
+    }
+    
+    // Removes the oldest snapshots beyond `keep`. Returns how many were removed.
+    // The newest always stays, since it was usually just taken.
+    pub fn prune(&self, keep: usize) -> Result<usize> {
+        let backups = self.list()?;
+        let mut removed = 0;
+        for backup in backups.iter().skip(keep.max(1)) {
+            fs::remove_file(self.dir.join(&backup.name))
+                .context(format!("Failed to remove backup: {}", backup.name))?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,69 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Content-addressed storage for attachments: each file is stored once under
+// the blake3 hash of its bytes, no matter how many notes reference it
+pub struct BlobStore {
+    dir: PathBuf,
+}
+
+impl BlobStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+    
+    pub fn path_for(&self, hash: &str) -> PathBuf {
+        self.dir.join(hash)
+    }
+    
+    // Returns the hash of the stored blob and whether it was already present
+    pub fn store(&self, source: &Path) -> Result<(String, bool)> {
+        let bytes = fs::read(source)
+            .context(format!("Failed to read file: {}", source.display()))?;
+        self.store_bytes(&bytes)
+    }
+    
+    pub fn store_bytes(&self, bytes: &[u8]) -> Result<(String, bool)> {
+        let hash = blake3::hash(bytes).to_hex().to_string();
+        let blob_path = self.path_for(&hash);
+        
+        if blob_path.exists() {
+            return Ok((hash, true));
+        }
+        
+        fs::create_dir_all(&self.dir)
+            .context("Failed to create attachments directory")?;
+        
+        // Write to a temporary file first so a partial copy never looks like a valid blob
+        let tmp_path = self.dir.join(format!("{}.tmp", hash));
+        fs::write(&tmp_path, bytes)
+            .context("Failed to write attachment")?;
+        fs::rename(&tmp_path, &blob_path)
+            .context("Failed to store attachment")?;
+        
+        Ok((hash, false))
+    }
+    
+    // Reference counts are derived from the notes themselves, so any blob
+    // that no note points at can be removed
+    pub fn gc(&self, referenced: &HashSet<String>) -> Result<usize> {
+        if !self.dir.exists() {
+            return Ok(0);
+        }
+        
+        let mut removed = 0;
+        for entry in fs::read_dir(&self.dir).context("Failed to read attachments directory")? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !referenced.contains(&name) {
+                fs::remove_file(entry.path())
+                    .context(format!("Failed to remove blob: {}", name))?;
+                removed += 1;
+            }
+        }
+        
+        Ok(removed)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use colored::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::blobs::BlobStore;
+use crate::glyphs::glyphs;
+use crate::note::Note;
+
+// Notes longer than `inline_limit_kb` under `[storage]` keep their text in
+// the blob store next to attachments, so notes.txt stays quick to read
+// however large the occasional paste. In the file such a note is its header
+// with `body=<hash>` and its first line as a stand-in. Loaded, it is whole
+// again without the field, so only saving and loading ever see it.
+pub const BODY_KEY: &str = "body";
+
+// Longest stand-in line kept in the file
+const PREVIEW_CHARS: usize = 80;
+// What the file holds, whatever --ascii says, so the same note always saves
+// the same way
+const ELLIPSIS: &str = "…";
+
+// The note as it is saved: itself, or a stand-in pointing at its stored text.
+// A `limit` of 0 keeps every note inline.
+pub fn stow<'a>(note: &'a Note, limit: usize, blobs: &BlobStore) -> Result<Cow<'a, Note>> {
+    if limit == 0 || note.content.len() <= limit {
+        return Ok(Cow::Borrowed(note));
+    }
+    let (hash, _) = blobs.store_bytes(note.content.as_bytes())?;
+    let first_line = note.content.lines().find(|line| !line.trim().is_empty()).unwrap_or(ELLIPSIS);
+    let mut preview: String = first_line.chars().take(PREVIEW_CHARS).collect();
+    if preview.len() < first_line.len() {
+        preview.push_str(ELLIPSIS);
+    }
+    let mut stowed = note.clone();
+    stowed.content = preview;
+    stowed.meta.insert(BODY_KEY.to_string(), hash);
+    Ok(Cow::Owned(stowed))
+}
+
+// Puts the stored text back into notes read from the file, and returns the
+// blob each one's text came from by note ID. A note whose blob is missing
+// keeps its stand-in and the field, so saving doesn't lose the reference.
+pub fn restore(notes: &mut [Note], blobs: &BlobStore) -> HashMap<String, String> {
+    let mut stored = HashMap::new();
+    for note in notes.iter_mut() {
+        let Some(hash) = note.meta.get(BODY_KEY).cloned() else {
+            continue;
+        };
+        if let Ok(content) = fs::read_to_string(blobs.path_for(&hash)) {
+            note.content = content;
+            note.meta.remove(BODY_KEY);
+        }
+        stored.insert(note.id.clone(), hash);
+    }
+    stored
+}
+
+// For the notes `restore` couldn't find the text of
+pub fn warn_missing(notes: &[Note], blobs: &BlobStore) {
+    for note in notes {
+        if let Some(hash) = note.meta.get(BODY_KEY) {
+            eprintln!("{} The text of note {} is missing from {}",
+                glyphs().warning.yellow(),
+                format!("[{}]", note.id).yellow(),
+                blobs.path_for(hash).display()
+            );
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use anyhow::{bail, Context, Result};
+
+use crate::note::Note;
+
+// Calculation notes hold one line, `<expression> = <result>`, and carry this
+// tag so they can be re-evaluated later
+pub const CALC_TAG: &str = "calc";
+
+// Results are rounded to this many decimal places
+const PRECISION: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f64),
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Power,
+    Percent,
+    Open,
+    Close,
+}
+
+// A value and whether it was written as a percentage, so `a + 18%` can mean
+// "a plus 18% of a"
+#[derive(Debug, Clone, Copy)]
+struct Value {
+    number: f64,
+    percent: bool,
+}
+
+impl Value {
+    fn plain(number: f64) -> Self {
+        Self { number, percent: false }
+    }
+    
+    // The value as a plain number, turning 18% into 0.18
+    fn resolve(self) -> f64 {
+        if self.percent { self.number / 100.0 } else { self.number }
+    }
+}
+
+// Evaluates arithmetic with + - * / ^, parentheses and percentages. Words are
+// labels and are skipped, so "12*99 + tip 18%" adds 18% to 1188.
+pub fn evaluate(expression: &str) -> Result<f64> {
+    let tokens = tokenize(expression)?;
+    if tokens.is_empty() {
+        bail!("Nothing to calculate");
+    }
+    
+    let mut parser = Parser { tokens: &tokens, pos: 0 };
+    let value = parser.expression()?;
+    if let Some(token) = parser.peek() {
+        bail!("Unexpected {} in expression", describe(token));
+    }
+    
+    let result = value.resolve();
+    if !result.is_finite() {
+        bail!("Result is not a finite number");
+    }
+    Ok(result)
+}
+
+pub fn format_number(value: f64) -> String {
+    // Rounding would overflow; such numbers have no fractional digits anyway
+    if value.abs() >= 1e15 {
+        return value.to_string();
+    }
+    let scale = 10f64.powi(PRECISION);
+    let rounded = (value * scale).round() / scale;
+    let text = format!("{:.*}", PRECISION as usize, rounded);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" { "0".to_string() } else { text.to_string() }
+}
+
+pub fn note_content(expression: &str, result: f64) -> String {
+    format!("{} = {}", expression.trim(), format_number(result))
+}
+
+// The expression part of a calculation note: its first line without the
+// stored result
+pub fn expression(note: &Note) -> &str {
+    let line = note.content.lines().next().unwrap_or("");
+    match line.rsplit_once(" = ") {
+        Some((expression, _)) => expression,
+        None => line,
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+    
+    while let Some(&c) = chars.peek() {
+        if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_digit() || c == '.' {
+                    number.push(c);
+                } else if c != ',' && c != '_' {
+                    // Commas and underscores group digits: 1,000 or 1_000
+                    break;
+                }
+                chars.next();
+            }
+            let value = number.parse()
+                .with_context(|| format!("Invalid number '{}'", number))?;
+            tokens.push(Token::Number(value));
+            continue;
+        }
+        
+        chars.next();
+        let token = match c {
+            '+' => Token::Plus,
+            '-' | '−' => Token::Minus,
+            '*' | '×' => Token::Times,
+            '/' | '÷' => Token::Divide,
+            '^' => Token::Power,
+            '%' => Token::Percent,
+            '(' => Token::Open,
+            ')' => Token::Close,
+            // Labels and currency signs don't affect the result
+            c if c.is_whitespace() || c.is_alphabetic() || "$€£:".contains(c) => continue,
+            c => bail!("Unexpected character '{}' in expression", c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn describe(token: Token) -> String {
+    match token {
+        Token::Number(value) => format!("number {}", format_number(value)),
+        Token::Plus => "'+'".to_string(),
+        Token::Minus => "'-'".to_string(),
+        Token::Times => "'*'".to_string(),
+        Token::Divide => "'/'".to_string(),
+        Token::Power => "'^'".to_string(),
+        Token::Percent => "'%'".to_string(),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+    }
+}
+
+// Recursive descent over the usual precedence levels:
+// expression := term (('+' | '-') term)*
+// term       := unary (('*' | '/') unary)*
+// unary      := '-' unary | power
+// power      := primary ('^' unary)?
+// primary    := (number | '(' expression ')') '%'?
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
+    }
+    
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+    
+    fn expression(&mut self) -> Result<Value> {
+        let mut left = self.term()?;
+        while let Some(op @ (Token::Plus | Token::Minus)) = self.peek() {
+            self.next();
+            let right = self.term()?;
+            let base = left.resolve();
+            // A percentage added to or taken from a value is relative to it
+            let amount = if right.percent { base * right.number / 100.0 } else { right.number };
+            left = Value::plain(if op == Token::Plus { base + amount } else { base - amount });
+        }
+        Ok(left)
+    }
+    
+    fn term(&mut self) -> Result<Value> {
+        let mut left = self.unary()?;
+        while let Some(op @ (Token::Times | Token::Divide)) = self.peek() {
+            self.next();
+            let right = self.unary()?.resolve();
+            left = Value::plain(if op == Token::Times {
+                left.resolve() * right
+            } else {
+                if right == 0.0 {
+                    bail!("Division by zero");
+                }
+                left.resolve() / right
+            });
+        }
+        Ok(left)
+    }
+    
+    fn unary(&mut self) -> Result<Value> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.next();
+                let value = self.unary()?;
+                Ok(Value { number: -value.number, ..value })
+            }
+            Some(Token::Plus) => {
+                self.next();
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+    
+    fn power(&mut self) -> Result<Value> {
+        let base = self.primary()?;
+        if self.peek() == Some(Token::Power) {
+            self.next();
+            // Right-associative, and binds tighter than a leading minus: -2^2 = -4
+            let exponent = self.unary()?.resolve();
+            return Ok(Value::plain(base.resolve().powf(exponent)));
+        }
+        Ok(base)
+    }
+    
+    fn primary(&mut self) -> Result<Value> {
+        let value = match self.next() {
+            Some(Token::Number(value)) => Value::plain(value),
+            Some(Token::Open) => {
+                let value = self.expression()?;
+                if self.next() != Some(Token::Close) {
+                    bail!("Missing closing parenthesis");
+                }
+                Value::plain(value.resolve())
+            }
+            Some(token) => bail!("Unexpected {} in expression", describe(token)),
+            None => bail!("Expression ends unexpectedly"),
+        };
+        
+        if self.peek() == Some(Token::Percent) {
+            self.next();
+            return Ok(Value { percent: true, ..value });
+        }
+        Ok(value)
+    }
+}
\ No newline at end of file
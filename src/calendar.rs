@@ -0,0 +1,96 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use colored::*;
+use std::collections::BTreeMap;
+
+use crate::config::CalendarConfig;
+
+// Month grid for `note cal`, weeks starting on the configured day. Each day is
+// shaded by how many notes were written on it, relative to the busiest day of
+// the month.
+const WEEKDAYS: [&str; 7] = ["Mo", "Tu", "We", "Th", "Fr", "Sa", "Su"];
+
+// The day the week containing `day` started on
+pub fn week_start(day: NaiveDate, first_day: Weekday) -> NaiveDate {
+    day - Days::new(u64::from(day.weekday().days_since(first_day)))
+}
+
+// The ISO week most of the seven days from `start` fall in, which is the one
+// holding their Thursday; for weeks starting on Monday, simply theirs
+fn iso_week(start: NaiveDate) -> u32 {
+    let thursday = (0..7).map(|offset| start + Days::new(offset)).find(|day| day.weekday() == Weekday::Thu);
+    thursday.unwrap_or(start).iso_week().week()
+}
+
+// "2024-07", or a month number or name ("7", "jul", "July") in `this_year`
+pub fn parse_month(text: &str, this_year: i32) -> Option<NaiveDate> {
+    let text = text.trim().to_lowercase();
+    if let Some((year, month)) = text.split_once('-') {
+        return NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1);
+    }
+    let month = match text.parse::<u32>() {
+        Ok(month) => month,
+        Err(_) => (1..=12).find(|&month| {
+            let name = NaiveDate::from_ymd_opt(this_year, month, 1)
+                .map(|date| date.format("%B").to_string().to_lowercase())
+                .unwrap_or_default();
+            text.len() >= 3 && name.starts_with(&text)
+        })?,
+    };
+    NaiveDate::from_ymd_opt(this_year, month, 1)
+}
+
+// The month containing `first` (its first day), with `counts` notes per day
+pub fn render(first: NaiveDate, counts: &BTreeMap<NaiveDate, usize>, today: NaiveDate, calendar_config: &CalendarConfig) -> String {
+    let last = first + Months::new(1) - Days::new(1);
+    let busiest = (1..=last.day())
+        .filter_map(|day| counts.get(&first.with_day(day)?))
+        .copied()
+        .max()
+        .unwrap_or(0);
+    
+    let first_day = calendar_config.week_start;
+    let header: Vec<&str> = (0..7).map(|offset| WEEKDAYS[(first_day.num_days_from_monday() as usize + offset) % 7]).collect();
+    // A column of ISO week numbers goes in front when enabled
+    let (margin, numbers) = if calendar_config.week_numbers { ("   ", "Wk ") } else { ("", "") };
+    let title = format!("{}{:^20}", margin, first.format("%B %Y").to_string());
+    let mut lines = vec![title.trim_end().bold().to_string(), format!("{}{}", numbers, header.join(" ")).bright_black().to_string()];
+    let number = |start: NaiveDate| if calendar_config.week_numbers {
+        format!("{:>2} ", iso_week(start)).bright_black().to_string()
+    } else {
+        String::new()
+    };
+    
+    let mut row_start = week_start(first, first_day);
+    let mut week = vec!["  ".to_string(); first.weekday().days_since(first_day) as usize];
+    for day in 1..=last.day() {
+        let Some(date) = first.with_day(day) else { continue };
+        let count = counts.get(&date).copied().unwrap_or(0);
+        let mut cell = shade(&format!("{:>2}", day), count, busiest);
+        if date == today {
+            cell = cell.underline();
+        }
+        week.push(cell.to_string());
+        if week.len() == 7 {
+            lines.push(format!("{}{}", number(row_start), week.join(" ")));
+            week.clear();
+            row_start = row_start + Days::new(7);
+        }
+    }
+    if !week.is_empty() {
+        lines.push(format!("{}{}", number(row_start), week.join(" ")));
+    }
+    lines.join("\n")
+}
+
+// Four shades like a contribution graph; days without notes are dimmed
+fn shade(text: &str, count: usize, busiest: usize) -> ColoredString {
+    if count == 0 {
+        return text.bright_black();
+    }
+    match (count * 4).div_ceil(busiest.max(1)) {
+        1 => text.green(),
+        2 => text.bright_green(),
+        3 => text.bright_green().bold(),
+        _ => text.black().on_bright_green(),
+    }
+}
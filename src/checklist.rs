@@ -0,0 +1,37 @@
+use chrono::NaiveDate;
+
+use crate::inbox::{self, TriageAction};
+use crate::note::Note;
+
+// A note with checkbox lines (`- [ ] ...`, `* [x] ...`) is a checklist. With
+// `archive_done_checklists` set under `[inbox]`, ticking its last open box
+// archives it, so finished lists drop out of `note list` like triaged captures.
+
+// `- [ ] text` and `* [x] text` lines; returns (done, text)
+pub fn parse_item(line: &str) -> Option<(bool, &str)> {
+    let rest = line.trim_start().strip_prefix("- ").or_else(|| line.trim_start().strip_prefix("* "))?;
+    let (done, text) = if let Some(text) = rest.strip_prefix("[ ]") {
+        (false, text)
+    } else if let Some(text) = rest.strip_prefix("[x]").or_else(|| rest.strip_prefix("[X]")) {
+        (true, text)
+    } else {
+        return None;
+    };
+    let text = text.trim();
+    if text.is_empty() { None } else { Some((done, text)) }
+}
+
+// Has checkboxes, and every one is ticked
+pub fn is_done(note: &Note) -> bool {
+    let mut items = note.content.lines().filter_map(parse_item).peekable();
+    items.peek().is_some() && items.all(|(done, _)| done)
+}
+
+// Archives a finished checklist that isn't archived yet; returns whether it did
+pub fn archive_if_done(note: &mut Note, today: NaiveDate) -> bool {
+    if inbox::is_archived(note) || !is_done(note) {
+        return false;
+    }
+    inbox::apply(note, &TriageAction::Archive, today);
+    true
+}
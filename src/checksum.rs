@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::note::Note;
+use crate::parser::{FileFormat, NoteParser};
+
+// checksums.txt, written by `note checksum`, holds a hash of every note and
+// one of the whole store. Once it exists, saves made through note keep it up
+// to date for the notes they change, so `note verify` reports only what was
+// changed some other way: edits by hand, a sync tool mangling the file, or
+// disk corruption.
+pub const MANIFEST_FILE: &str = "checksums.txt";
+const HEADER: &str = "%note-checksums 1";
+
+pub struct Manifest {
+    pub hashes: BTreeMap<String, String>,
+    // False when the store hash doesn't match the note hashes, i.e. the
+    // manifest itself was altered or damaged
+    pub intact: bool,
+}
+
+#[derive(Default)]
+pub struct Differences {
+    pub changed: Vec<String>,
+    pub missing: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl Differences {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.missing.is_empty() && self.added.is_empty()
+    }
+}
+
+// Everything stored for the note, however the file happens to lay it out,
+// and as it reads back: parsing trims the content of a note just added
+pub fn note_hash(note: &Note) -> String {
+    let trimmed;
+    let note = if note.content.trim() == note.content {
+        note
+    } else {
+        trimmed = Note { content: note.content.trim().to_string(), ..note.clone() };
+        &trimmed
+    };
+    blake3::hash(NoteParser::format_note(note, FileFormat::V2).as_bytes()).to_hex().to_string()
+}
+
+pub fn hashes(notes: &[Note]) -> BTreeMap<String, String> {
+    notes.iter().map(|note| (note.id.clone(), note_hash(note))).collect()
+}
+
+pub fn store_hash(hashes: &BTreeMap<String, String>) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for (id, hash) in hashes {
+        hasher.update(format!("{} {}\n", id, hash).as_bytes());
+    }
+    hasher.finalize().to_hex().to_string()
+}
+
+pub fn load(path: &Path) -> Result<Option<Manifest>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).context("Failed to read checksums")?;
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        return Ok(Some(Manifest { hashes: BTreeMap::new(), intact: false }));
+    }
+    let stored = lines.next().and_then(|line| line.strip_prefix("store ")).unwrap_or_default();
+    let hashes: BTreeMap<String, String> = lines
+        .filter_map(|line| line.split_once(' '))
+        .map(|(id, hash)| (id.to_string(), hash.to_string()))
+        .collect();
+    let intact = stored == store_hash(&hashes);
+    Ok(Some(Manifest { hashes, intact }))
+}
+
+// Returns the store hash
+pub fn save(path: &Path, hashes: &BTreeMap<String, String>) -> Result<String> {
+    let store = store_hash(hashes);
+    let mut content = format!("{}\nstore {}\n", HEADER, store);
+    for (id, hash) in hashes {
+        content.push_str(&format!("{} {}\n", id, hash));
+    }
+    fs::write(path, content).context("Failed to write checksums")?;
+    Ok(store)
+}
+
+// After a save, records the new hash of each note that still matched the
+// manifest when it was loaded (`loaded`), and adds new notes. A note that was
+// already changed some other way keeps its old hash, so it is still reported.
+// Returns the hashes of `notes`.
+pub fn update(path: &Path, loaded: &BTreeMap<String, String>, notes: &[Note]) -> Result<BTreeMap<String, String>> {
+    let current = hashes(notes);
+    let Some(manifest) = load(path)? else {
+        return Ok(current);
+    };
+    let mut updated = manifest.hashes.clone();
+    for (id, before) in loaded {
+        if manifest.hashes.get(id) != Some(before) {
+            continue;
+        }
+        match current.get(id) {
+            Some(hash) => updated.insert(id.clone(), hash.clone()),
+            None => updated.remove(id),
+        };
+    }
+    for (id, hash) in &current {
+        if !loaded.contains_key(id) && !manifest.hashes.contains_key(id) {
+            updated.insert(id.clone(), hash.clone());
+        }
+    }
+    // A manifest that was already damaged stays that way, for verify to report
+    if updated != manifest.hashes && manifest.intact {
+        save(path, &updated)?;
+    }
+    Ok(current)
+}
+
+// The quick add path's version of update(), for a note just appended
+pub fn record_added(path: &Path, note: &Note) -> Result<()> {
+    let Some(mut manifest) = load(path)? else {
+        return Ok(());
+    };
+    if manifest.intact && !manifest.hashes.contains_key(&note.id) {
+        manifest.hashes.insert(note.id.clone(), note_hash(note));
+        save(path, &manifest.hashes)?;
+    }
+    Ok(())
+}
+
+pub fn compare(manifest: &BTreeMap<String, String>, current: &BTreeMap<String, String>) -> Differences {
+    let mut differences = Differences::default();
+    for (id, hash) in manifest {
+        match current.get(id) {
+            Some(current) if current != hash => differences.changed.push(id.clone()),
+            Some(_) => {}
+            None => differences.missing.push(id.clone()),
+        }
+    }
+    differences.added = current.keys()
+        .filter(|id| !manifest.contains_key(*id))
+        .cloned()
+        .collect();
+    differences
+}
@@ -1,16 +1,73 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Weekday};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use colored::*;
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::manager::NoteManager;
-use crate::note::RemoveResult;
+use crate::audit;
+use crate::autotag::AutoTagger;
+use crate::calc;
+use crate::calendar;
+use crate::clock::{self, ClockInResult};
+use crate::config::{AutoTagConfig, BackupConfig, CalendarConfig, Config, DiagramConfig, InboxConfig, LimitsConfig, RecapConfig, RemoteConfig, SayConfig, SimilarConfig, SnapConfig, SummaryConfig};
+use crate::diagram;
+use crate::diary;
+use crate::diff;
+use crate::digest::{self, DigestFormat};
+use crate::editor;
+use crate::encoding;
+use crate::format::{self, Format, ReadOptions};
+use crate::git;
+use crate::glyphs::{self, glyphs};
+use crate::inbox::{self, TriageAction};
+use crate::kind::{self, NoteKind, KIND_KEY};
+use crate::language;
+use crate::layout::Layout;
+use crate::location;
+use crate::manager::{AppendOptions, DisplayOptions, ImportReport, NoteFilter, NoteManager};
+use crate::note::{LookupResult, Note, RemoveResult, RESERVED_META_KEYS};
+use crate::parser::{ExportFormat, FileFormat, NoteParser, SplitBy};
+use crate::prefix::PrefixTrie;
+use crate::qr;
+use crate::reading;
+use crate::recap;
+use crate::record;
+use crate::redact::Redactor;
+use crate::remote;
+use crate::seal;
+use crate::search::Matcher;
+use crate::selection;
+use crate::shell::{self, Shell};
+use crate::similar;
+use crate::slug;
+use crate::snap;
+use crate::snippets;
+use crate::speech;
+use crate::summary;
+use crate::table;
+use crate::taskwarrior;
+use crate::time::{Clock, SystemClock};
+use crate::timezone::{self, DisplayZone};
+use crate::watch::{self, RateLimit, RecentText, Tail};
+
+// Whether an ambiguous note ID may be settled by asking which note was meant
+static PICK_AMBIGUOUS: AtomicBool = AtomicBool::new(false);
 
 fn get_storage_help() -> String {
-    let home_dir = dirs::home_dir().unwrap_or_else(|| PathBuf::from("~"));
-    let storage_path = home_dir.join(".local").join("share").join("note").join("notes.txt");
+    let storage_path = NoteManager::default_dir()
+        .map(|dir| dir.join("notes.txt"))
+        .unwrap_or_else(|_| PathBuf::from("~/.local/share/note/notes.txt"));
+    
+    let config_path = Config::path().unwrap_or_else(|| PathBuf::from("~/.config/note/config.toml"));
     
-    format!("STORAGE:\n  Notes are stored in: {}", storage_path.display())
+    format!("STORAGE:\n  Notes are stored in: {}\n  Configuration is read from: {}", 
+        storage_path.display(),
+        config_path.display()
+    )
 }
 
 #[derive(Parser)]
@@ -22,127 +79,3613 @@ pub struct Cli {
     #[command(subcommand)]
     pub command: Option<Commands>,
     
+    /// Print only created note IDs on stdout, status messages go to stderr
+    #[arg(long, global = true)]
+    pub porcelain: bool,
+    
+    /// Use plain text instead of symbols (screen-reader friendly)
+    #[arg(long, global = true)]
+    pub ascii: bool,
+    
+    /// Show and export notes without masking the configured redaction patterns
+    #[arg(long, global = true)]
+    pub no_redact: bool,
+    
+    /// Show dates in this timezone, e.g. UTC, Europe/Berlin or +05:30
+    #[arg(long, global = true, value_name = "ZONE")]
+    pub tz: Option<String>,
+    
+    /// Never ask questions; an ambiguous note ID lists the matches and stops
+    #[arg(long, global = true)]
+    pub no_interactive: bool,
+    
+    /// Use the settings of a [profiles.NAME] section from the config file
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+    
+    /// Tag to add to a new note (can be repeated)
+    #[arg(short, long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+    
+    /// Kind of the new note, which picks its icon and template
+    #[arg(short, long, value_enum)]
+    pub kind: Option<NoteKind>,
+    
+    /// Where the new note was written, e.g. "Berlin" or "52.52,13.40"
+    #[arg(long, value_name = "PLACE", conflicts_with = "here", allow_hyphen_values = true)]
+    pub location: Option<String>,
+    
+    /// Stamp the new note with the location from the configured command
+    #[arg(long)]
+    pub here: bool,
+    
+    /// Store pasted CSV or tab-separated rows as an aligned table
+    #[arg(long)]
+    pub table: bool,
+    
+    /// Add the new note even if a similar one exists
+    #[arg(short, long)]
+    pub force: bool,
+    
+    /// Keep :name: snippets in the new note as typed
+    #[arg(long)]
+    pub no_snippets: bool,
+    
     /// Text content for a new note (when no subcommand is used)
     pub text: Vec<String>,
+    
+    // Subcommand path such as "tag rename", recorded in the audit log
+    #[arg(skip)]
+    pub command_name: String,
+}
+
+impl Cli {
+    pub fn parse_args() -> Self {
+        let matches = Self::command().get_matches();
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+        let mut names = Vec::new();
+        let mut current = &matches;
+        while let Some((name, sub_matches)) = current.subcommand() {
+            names.push(name);
+            current = sub_matches;
+        }
+        // Without a subcommand the only change possible is adding a note
+        cli.command_name = if names.is_empty() { "add".to_string() } else { names.join(" ") };
+        cli
+    }
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Add a note; without text, read a multi-line note from stdin
+    #[command(name = "add")]
+    Add {
+        /// Tag to add to the note (can be repeated)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Kind of note, which picks its icon and template
+        #[arg(short, long, value_enum)]
+        kind: Option<NoteKind>,
+        /// Where the note was written, e.g. "Berlin" or "52.52,13.40"
+        #[arg(long, value_name = "PLACE", conflicts_with = "here", allow_hyphen_values = true)]
+        location: Option<String>,
+        /// Stamp the note with the location from the configured command
+        #[arg(long)]
+        here: bool,
+        /// Store pasted CSV or tab-separated rows as an aligned table
+        #[arg(long)]
+        table: bool,
+        /// Add the note even if a similar one exists
+        #[arg(short, long)]
+        force: bool,
+        /// Keep :name: snippets as typed
+        #[arg(long)]
+        no_snippets: bool,
+        /// Note text (omit to type several lines, ending with a lone '.' or Ctrl-D)
+        text: Vec<String>,
+    },
+    /// List notes
+    #[command(name = "list", visible_alias = "ls")]
+    List {
+        /// Show every line of every note
+        #[arg(long)]
+        full: bool,
+        /// Collapse notes longer than N lines
+        #[arg(long, value_name = "N", conflicts_with = "full")]
+        truncate: Option<usize>,
+        /// Only show notes with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only show notes of this kind
+        #[arg(long, value_enum)]
+        kind: Option<NoteKind>,
+        /// Only show notes whose location contains PLACE
+        #[arg(long, value_name = "PLACE")]
+        location: Option<String>,
+        /// Only show notes written near LAT,LON
+        #[arg(long, value_name = "LAT,LON", value_parser = parse_coordinates, allow_hyphen_values = true)]
+        near: Option<(f64, f64)>,
+        /// Radius for --near, in kilometers
+        #[arg(long, value_name = "KM", default_value_t = 25.0, requires = "near")]
+        within: f64,
+        /// Only show notes whose metadata matches KEY=VALUE (or has KEY)
+        #[arg(long = "where", value_name = "KEY=VALUE")]
+        where_clauses: Vec<String>,
+        /// Only show notes detected as written in this language, e.g. de or german
+        #[arg(long, value_name = "LANG", value_parser = parse_language)]
+        lang: Option<String>,
+        /// Only show untriaged captures
+        #[arg(long, conflicts_with = "all")]
+        inbox: bool,
+        /// Include archived notes and untriaged captures
+        #[arg(long)]
+        all: bool,
+        /// Show word count and reading time for each note
+        #[arg(long)]
+        stats: bool,
+        /// Print one line per note from a layout, e.g. '{id}\t{date:%Y-%m-%d}\t{first_line}'
+        #[arg(long, value_name = "LAYOUT", conflicts_with_all = ["full", "truncate", "stats"])]
+        format: Option<String>,
+    },
+    /// Show one note in full
+    #[command(name = "show")]
+    Show {
+        /// Note ID to show
+        id: String,
+        /// Include word count and reading time
+        #[arg(long)]
+        stats: bool,
+        /// Render mermaid and dot diagrams in the note to images
+        #[arg(long)]
+        render: bool,
+    },
+    /// Edit a note in $VISUAL or $EDITOR
+    #[command(name = "edit")]
+    Edit {
+        /// Note ID to edit
+        id: String,
+        /// Keep newly typed :name: snippets as typed
+        #[arg(long)]
+        no_snippets: bool,
+    },
+    /// Show a note as a QR code, to scan it with a phone
+    #[command(name = "qr")]
+    Qr {
+        /// Note ID to encode
+        id: String,
+    },
+    /// Search note contents, highlighting every match
+    #[command(name = "search")]
+    Search {
+        /// Text to search for (case-insensitive)
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Treat the query as a regular expression
+        #[arg(short, long, conflicts_with = "stem")]
+        regex: bool,
+        /// Match inflected word forms (language set in the config file)
+        #[arg(long, conflicts_with = "fuzzy_typos")]
+        stem: bool,
+        /// Also match words with a typo or two, e.g. "kubernets" for "Kubernetes"
+        #[arg(long, conflicts_with = "regex")]
+        fuzzy_typos: bool,
+        /// Most edits allowed per word with --fuzzy-typos (words get one per four letters up to this)
+        #[arg(long, value_name = "N", default_value_t = 2, requires = "fuzzy_typos")]
+        max_typos: usize,
+        /// Only search notes with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Only search notes detected as written in this language
+        #[arg(long, value_name = "LANG", value_parser = parse_language)]
+        lang: Option<String>,
+        /// Show every line of every matching note
+        #[arg(long)]
+        full: bool,
+        /// Lines of context shown around matches in long notes
+        #[arg(short = 'C', long, value_name = "N", default_value_t = 2, conflicts_with = "full")]
+        context: usize,
+        /// Search these note files instead, such as backups or `note output` copies ('-' reads stdin)
+        #[arg(long, value_name = "FILE", num_args = 1..)]
+        external: Vec<String>,
+    },
+    /// Save the notes matching a search as @sel (or --as NAME), for commands
+    /// that take IDs; lists saved selections without a query
+    #[command(name = "select")]
+    Select {
+        /// Text to search for (case-insensitive)
+        query: Vec<String>,
+        /// Treat the query as a regular expression
+        #[arg(short, long)]
+        regex: bool,
+        /// Only select notes with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Name to save the selection under
+        #[arg(long = "as", value_name = "NAME", default_value = selection::DEFAULT_NAME)]
+        name: String,
+    },
+    /// Go through untriaged captures one at a time
+    #[command(name = "triage")]
+    Triage,
+    /// Show notes you haven't looked at in the longest time
+    #[command(name = "resurface")]
+    Resurface {
+        /// Number of notes to show
+        #[arg(short = 'n', long, default_value_t = 3)]
+        count: usize,
+        /// Pick notes at random instead
+        #[arg(long)]
+        random: bool,
+    },
+    /// Short digest of pinned, due and new notes, e.g. for your shell startup file
+    #[command(name = "summary")]
+    Summary,
+    /// Count notes, words and tags
+    #[command(name = "stats")]
+    Stats {
+        /// Chart the moods recorded with note journal --mood instead
+        #[arg(long)]
+        mood: bool,
+        /// Days the mood chart goes back
+        #[arg(long, value_name = "N", default_value_t = 30, requires = "mood")]
+        days: u32,
+    },
+    /// Sum up the notes captured today, e.g. from a timer at the end of the day
+    #[command(name = "recap")]
+    Recap {
+        /// Show the recap as a desktop notification
+        #[arg(long)]
+        notify: bool,
+        /// Save it as a note tagged recap, replacing the day's earlier one
+        #[arg(long)]
+        save: bool,
+        /// Recap another day instead (YYYY-MM-DD)
+        #[arg(long)]
+        day: Option<String>,
+    },
+    /// Print the notes of recent days as one document, e.g. for a status email
+    #[command(name = "digest")]
+    Digest {
+        /// First day to include: a date, today, yesterday, this-week,
+        /// last-week, this-month, last-month, or a count such as 3d or 2w
+        #[arg(long, value_name = "WHEN", default_value = "last-week")]
+        since: String,
+        /// Only include notes with this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+        /// Markdown, or plain text for mail clients that don't render it
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: DigestFormat,
+    },
+    /// Add a timestamped entry to today's journal note, or show the journal
+    #[command(name = "journal")]
+    Journal {
+        /// Show another day's journal instead (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE", conflicts_with_all = ["text", "mood"])]
+        day: Option<String>,
+        /// How you feel: 1-5, awful, bad, okay, good, great, or a face emoji
+        #[arg(long, value_name = "MOOD", value_parser = parse_mood)]
+        mood: Option<u8>,
+        /// Keep :name: snippets as typed
+        #[arg(long)]
+        no_snippets: bool,
+        /// Entry text (omit to show the journal)
+        text: Vec<String>,
+    },
+    /// Show a month calendar shaded by how many notes each day has
+    #[command(name = "cal")]
+    Cal {
+        /// Month to show: 2024-07, a month number or a name (defaults to this month)
+        month: Option<String>,
+        /// List the notes written on this day instead (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE", conflicts_with = "month")]
+        day: Option<String>,
+    },
+    /// List all tags with their note counts
+    #[command(name = "tags")]
+    Tags {
+        /// Show nested tags such as work/infra as a tree, counting notes under each
+        #[arg(long)]
+        tree: bool,
+    },
+    /// Count notes by detected language, or detect it for notes without one
+    #[command(name = "lang")]
+    Lang {
+        /// Detect and store the language of notes that don't have one yet
+        #[arg(long)]
+        detect: bool,
+        /// Only show the languages --detect would store
+        #[arg(long, requires = "detect")]
+        dry_run: bool,
+    },
+    /// Manage tags across all notes
+    #[command(name = "tag", subcommand)]
+    Tag(TagCommands),
+    /// Review notes as flashcards with spaced repetition
+    #[command(name = "srs", subcommand)]
+    Srs(SrsCommands),
+    /// Save a link to the reading list, fetching the page title
+    #[command(name = "read")]
+    Read {
+        /// Address of the page (http or https)
+        url: String,
+        /// Extra tag to add besides "reading" (can be repeated)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+    /// Show unread links from the reading list
+    #[command(name = "reading")]
+    Reading {
+        #[command(subcommand)]
+        command: Option<ReadingCommands>,
+    },
+    /// Save a git commit's message, SHA, author and files as a note
+    #[command(name = "from-git")]
+    FromGit {
+        /// Commit to capture
+        #[arg(default_value = "HEAD")]
+        rev: String,
+        /// Extra tag for the note (can be repeated)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+    },
+    /// Read a note aloud with a text-to-speech command
+    #[command(name = "say")]
+    Say {
+        /// Note ID to read
+        id: String,
+    },
+    /// Record a shell session and save its transcript as a note when it exits
+    #[command(name = "record")]
+    Record {
+        /// Extra tag for the note (can be repeated)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Title for the session (defaults to the date and time)
+        text: Vec<String>,
+    },
+    /// Take a screenshot and save it attached to a new note
+    #[command(name = "snap")]
+    Snap {
+        /// Extra tag for the note (can be repeated)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Caption for the screenshot
+        text: Vec<String>,
+    },
+    /// Print shell integration to evaluate in your shell's rc file
+    #[command(name = "shell-init")]
+    ShellInit {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Evaluate arithmetic and save it as a note, e.g. "12*99 + tip 18%"
+    #[command(name = "calc")]
+    Calc {
+        /// Expression to evaluate; words are treated as labels
+        #[arg(required_unless_present = "redo", allow_hyphen_values = true)]
+        expression: Vec<String>,
+        /// Re-evaluate an existing calculation note and update its result
+        #[arg(long, value_name = "ID", conflicts_with = "expression")]
+        redo: Option<String>,
+    },
+    /// Track time with paired clock-in and clock-out notes
+    #[command(name = "clock", subcommand)]
+    Clock(ClockCommands),
+    /// Create, list and restore compressed snapshots of your notes
+    #[command(name = "backup", subcommand)]
+    Backup(BackupCommands),
+    /// Look through your notes as a backup held them, without restoring it
+    #[command(name = "at")]
+    At {
+        /// Backup name, a unique part of it, or a date (YYYY-MM-DD) for the
+        /// last backup taken by then
+        snapshot: String,
+        #[command(subcommand)]
+        command: AtCommands,
+    },
+    /// Manage custom key-value metadata on a note
+    #[command(name = "meta", subcommand)]
+    Meta(MetaCommands),
     /// Remove a note by ID
     #[command(name = "rm")]
     Remove {
-        /// Note ID to delete
+        /// Note ID to delete, or @NAME for every note in a selection
         id: String,
+        /// Also overwrite the note's bytes in the old notes file and search index, with no backup first
+        #[arg(long)]
+        shred: bool,
     },
-    /// Output raw content of notes file
+    /// Output raw content of notes file, or every note in another format
     #[command(name = "output")]
     Output {
         /// Optional file path to write output to (defaults to stdout)
         file: Option<String>,
+        /// Write the notes in this format instead of copying the file as it is
+        #[arg(long, value_parser = format::writers())]
+        format: Option<&'static dyn Format>,
+    },
+    /// Export one note to a Markdown file, or tasks for taskwarrior
+    #[command(name = "export")]
+    Export {
+        /// Note ID or @NAME selection to export (optional for taskwarrior: all tasks by default; for html, the directory)
+        id: Option<String>,
+        /// File or directory to write to (defaults to <slug>.md here, or stdout for taskwarrior)
+        path: Option<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: ExportFormat,
+        /// Only export notes with this tag (can be repeated, for notes with any of them)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Only export notes written on or after this day (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE", value_parser = parse_date)]
+        since: Option<NaiveDate>,
+        /// Only export notes containing this text (case-insensitive)
+        #[arg(long, value_name = "TEXT")]
+        matching: Option<String>,
+        /// Replace the file if it already exists
+        #[arg(short, long)]
+        force: bool,
+        /// Also write a manifest of the notes and files exported, with their hashes
+        #[arg(long)]
+        manifest: bool,
     },
     /// Import notes from a text file
     #[command(name = "import")]
     Import {
         /// Path to the text file to import
         file: String,
+        /// Format of the file being imported
+        #[arg(long, value_parser = format::readers(), default_value = "native")]
+        format: &'static dyn Format,
+        /// How plain text is split into notes
+        #[arg(long, value_enum, default_value = "blank-line")]
+        split_by: SplitBy,
+        /// Write "old-id<TAB>new-id" lines for notes whose ID had to change
+        #[arg(long, value_name = "FILE")]
+        id_map: Option<String>,
+        /// Write a JSON report of every note created, renamed or skipped
+        #[arg(long, value_name = "FILE")]
+        report: Option<String>,
+        /// Skip notes already in the store with the same text and time
+        #[arg(long)]
+        skip_duplicates: bool,
+        /// Seconds two times may differ by and still count as the same, besides whole timezone offsets
+        #[arg(long, value_name = "SECONDS", default_value_t = 1, requires = "skip_duplicates")]
+        tolerance: u32,
+        /// Only import when the file matches this manifest from note export --manifest
+        #[arg(long, value_name = "MANIFEST")]
+        verify_manifest: Option<PathBuf>,
+    },
+    /// Attach a file to a note, or list its attachments
+    #[command(name = "attach")]
+    Attach {
+        /// Note ID to attach to
+        id: String,
+        /// File to attach (lists existing attachments when omitted)
+        file: Option<String>,
+    },
+    /// Show the log of changes to your notes, newest first
+    #[command(name = "log")]
+    Log {
+        /// Only changes to this note (partial IDs work)
+        id: Option<String>,
+        /// Number of entries to show
+        #[arg(short = 'n', long, default_value_t = 20)]
+        count: usize,
+    },
+    /// Show how a note changed, word by word, between backups
+    #[command(name = "diff")]
+    Diff {
+        /// Note ID (partial IDs work)
+        id: String,
+        /// Up to two backups (any unique part of the name) or "current";
+        /// with none, the latest backup where the note differs
+        #[arg(num_args = 0..=2)]
+        revisions: Vec<String>,
+        /// Compare the note with this file instead
+        #[arg(long, conflicts_with = "revisions")]
+        file: Option<PathBuf>,
+    },
+    /// Add a note to the store on another machine over SSH, or list its notes
+    #[command(name = "remote")]
+    Remote {
+        /// Host as given to ssh, e.g. user@workstation or an alias from ~/.ssh/config
+        host: String,
+        /// Tag for the new note, or the tag to list (can be repeated)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Note text, or "list" to list the remote notes
+        #[arg(required = true)]
+        text: Vec<String>,
+    },
+    /// Follow a file or named pipe and save each line written to it as a note
+    #[command(name = "watch")]
+    Watch {
+        /// File to follow from its end, or a pipe made with mkfifo
+        path: PathBuf,
+        /// Save each block of lines ending with a blank line as one note
+        #[arg(long)]
+        blocks: bool,
+        /// Tag for the new notes (can be repeated)
+        #[arg(short, long = "tag", value_name = "TAG")]
+        tags: Vec<String>,
+        /// Most notes saved in any minute; more are dropped (0 for no limit)
+        #[arg(long, value_name = "N", default_value_t = 30)]
+        max_per_minute: usize,
+    },
+    /// Record a checksum of every note for `note verify`
+    #[command(name = "checksum")]
+    Checksum,
+    /// Report notes changed outside note since `note checksum`
+    #[command(name = "verify")]
+    Verify,
+    /// Convert notes.txt to the current file format
+    #[command(name = "migrate")]
+    Migrate,
+    /// Remove attachments and long note text no longer referenced by any note or backup
+    #[command(name = "gc")]
+    Gc,
+    /// Two-way sync of your notes with files elsewhere
+    #[command(name = "sync", subcommand)]
+    Sync(SyncCommands),
+    /// Write every note, and which were deleted, to a bundle to carry to another machine
+    #[command(name = "pack")]
+    Pack {
+        /// Bundle file to write, e.g. on a USB stick
+        file: PathBuf,
+    },
+    /// Merge a bundle from `note pack` on another machine into your notes
+    #[command(name = "unpack")]
+    Unpack {
+        file: PathBuf,
+    },
+    /// Mount notes as editable text files until unmounted
+    #[cfg(all(unix, feature = "fuse"))]
+    #[command(name = "mount")]
+    Mount {
+        /// Empty directory to mount the notes on
+        dir: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagCommands {
+    /// Add a tag to the given notes, or to every note matching a regex
+    #[command(name = "add")]
+    Add {
+        tag: String,
+        /// Note IDs to tag (partial IDs and @NAME selections work)
+        #[arg(required_unless_present = "matching")]
+        ids: Vec<String>,
+        /// Tag every note whose content matches this regex instead
+        #[arg(long, value_name = "REGEX", conflicts_with = "ids")]
+        matching: Option<String>,
+    },
+    /// Rename a tag on every note that has it
+    #[command(name = "rename")]
+    Rename {
+        old: String,
+        new: String,
+    },
+    /// Remove a tag from all notes, or only from the given or matching ones
+    #[command(name = "rm")]
+    Remove {
+        tag: String,
+        /// Note IDs to untag (partial IDs and @NAME selections work)
+        ids: Vec<String>,
+        /// Untag only notes whose content matches this regex
+        #[arg(long, value_name = "REGEX", conflicts_with = "ids")]
+        matching: Option<String>,
+    },
+    /// Merge the first tag into the second
+    #[command(name = "merge")]
+    Merge {
+        from: String,
+        into: String,
+    },
+    /// Tag existing notes with the #hashtags in their text
+    #[command(name = "scan")]
+    Scan {
+        /// Only show the tags that would be added
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MetaCommands {
+    /// Set a metadata value on a note
+    #[command(name = "set")]
+    Set {
+        id: String,
+        key: String,
+        /// Value (multiple words are joined with spaces)
+        #[arg(required = true)]
+        value: Vec<String>,
+    },
+    /// Remove a metadata key from a note
+    #[command(name = "rm")]
+    Remove {
+        id: String,
+        key: String,
+    },
+    /// Show a note's metadata
+    #[command(name = "show")]
+    Show {
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SrsCommands {
+    /// Enroll a note in the review queue
+    #[command(name = "add")]
+    Add {
+        id: String,
+    },
+    /// Take a note out of the review queue
+    #[command(name = "rm")]
+    Remove {
+        id: String,
+    },
+    /// Review the notes that are due today
+    #[command(name = "review")]
+    Review,
+}
+
+#[derive(Subcommand)]
+pub enum ReadingCommands {
+    /// Mark a link as read, taking it off the queue
+    #[command(name = "done")]
+    Done {
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ClockCommands {
+    /// Start tracking time on a task
+    #[command(name = "in")]
+    In {
+        /// What you are working on
+        #[arg(required = true)]
+        label: Vec<String>,
+    },
+    /// Stop the running session and record its duration
+    #[command(name = "out")]
+    Out,
+    /// Summarize tracked time per label
+    #[command(name = "report")]
+    Report {
+        /// Only include sessions from this week
+        #[arg(long)]
+        week: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommands {
+    /// Write a new snapshot
+    #[command(name = "create")]
+    Create,
+    /// List snapshots, newest first
+    #[command(name = "list", visible_alias = "ls")]
+    List,
+    /// Replace the current notes with a snapshot
+    #[command(name = "restore")]
+    Restore {
+        /// Snapshot name, or a unique part of it such as the date
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AtCommands {
+    /// List the notes in the backup
+    #[command(name = "list", visible_alias = "ls")]
+    List {
+        /// Only list notes with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Include archived notes
+        #[arg(long)]
+        all: bool,
+        /// Show every line of every note
+        #[arg(long)]
+        full: bool,
+    },
+    /// Search the notes in the backup
+    #[command(name = "search")]
+    Search {
+        /// Text to search for (case-insensitive)
+        query: Vec<String>,
+        /// Treat the query as a regular expression
+        #[arg(short, long)]
+        regex: bool,
+        /// Only search notes with this tag
+        #[arg(long)]
+        tag: Option<String>,
+        /// Show every line of every matching note
+        #[arg(long)]
+        full: bool,
+    },
+    /// Show a note as the backup held it
+    #[command(name = "show")]
+    Show {
+        /// Note ID (any unique prefix)
+        id: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SyncCommands {
+    /// Mirror each note as a text file in a folder, e.g. one synced by Dropbox or Drive
+    #[command(name = "folder")]
+    Folder {
+        /// Folder to keep the note files in (created if missing)
+        path: PathBuf,
+        /// Show what each note would go through without changing anything
+        #[arg(long)]
+        dry_run: bool,
     },
 }
 
 pub fn run(cli: Cli) -> Result<()> {
-    let mut note_manager = NoteManager::new()?;
+    let config = Config::load(cli.profile.as_deref())?;
+    glyphs::set_ascii(cli.ascii || config.display.ascii);
+    PICK_AMBIGUOUS.store(
+        !cli.no_interactive && !cli.porcelain && std::io::stdin().is_terminal() && std::io::stdout().is_terminal(),
+        Ordering::Relaxed
+    );
+    if let Some(zone) = cli.tz.as_ref().or(config.display.timezone.as_ref()) {
+        timezone::set_display_zone(DisplayZone::parse(zone)?);
+    }
+    let redactor = if cli.no_redact || config.redact.patterns.is_empty() {
+        None
+    } else {
+        Some(Redactor::new(&config.redact.patterns, &config.redact.mask)?)
+    };
+    // Every command takes the time from here, through the manager where it
+    // opens one
+    let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+    if quick_add(&cli, &config, clock.as_ref())? {
+        return Ok(());
+    }
+    // The store is on the other machine; none is needed here
+    if let Some(Commands::Remote { host, tags, text }) = &cli.command {
+        return handle_remote_command(host, tags, text, &config.remote, cli.porcelain);
+    }
+    // Each note is appended to the file as it arrives, so changes made
+    // elsewhere while watching are kept
+    if let Some(Commands::Watch { path, blocks, tags, max_per_minute }) = &cli.command {
+        return handle_watch_command(&cli, path, *blocks, tags, *max_per_minute, &config, clock.as_ref());
+    }
+    let dir = match config.data_dir() {
+        Some(dir) => dir,
+        None => NoteManager::default_dir()?,
+    };
+    let note_manager = NoteManager::open_with_clock(dir, clock)?;
+    let mut note_manager = note_manager
+        .with_order(config.storage.order)
+        .with_aging(config.aging)
+        .with_tidy(config.storage.tidy)
+        .with_id_prefix(config.display.id_prefix)
+        .with_redactor(redactor)
+        .with_archive_done_checklists(config.inbox.archive_done_checklists)
+        .with_inline_limit(config.storage.inline_limit_kb * 1024)
+        .with_audit_log(config.storage.audit_log, &cli.command_name)
+        .with_sharing(config.storage.shared.then(|| std::time::Duration::from_secs(config.storage.lock_timeout_secs)))
+        .with_search_index(config.search.index)?;
     
     match &cli.command {
-        Some(Commands::Remove { id }) => {
-            handle_remove_command(&mut note_manager, id)?;
+        Some(Commands::Add { tags, kind, location, here, table, force, no_snippets, text }) => {
+            let text = if text.is_empty() { read_multiline_note()? } else { text.clone() };
+            if text.iter().all(|line| line.trim().is_empty()) {
+                eprintln!("{} Empty note discarded", glyphs().error.red());
+                return Ok(());
+            }
+            if !*force && !check_similar(&note_manager, &text, &config.similar) {
+                return Ok(());
+            }
+            let tags = [config.default_tags.as_slice(), tags].concat();
+            let location = capture_location(location.as_deref(), *here, &config)?;
+            let text = if *no_snippets { text } else { expand_snippets(text, &config.snippets, note_manager.now()) };
+            let text = if *table { format_pasted_table(text) } else { text };
+            handle_add_command(&mut note_manager, text, &tags, *kind, location, &config, cli.porcelain)?;
+            warn_if_over_limits(note_manager.get_notes().len(), note_manager.file_size(), &config.limits);
+        }
+        Some(Commands::List { full, truncate, tag, kind, location, near, within, where_clauses, lang, inbox, all, stats, format }) => {
+            let mut meta: Vec<(String, Option<String>)> = where_clauses.iter().map(|clause| parse_where_clause(clause)).collect();
+            meta.extend(lang.iter().map(|lang| (language::LANG_KEY.to_string(), Some(lang.clone()))));
+            let filter = NoteFilter {
+                tag: tag.as_deref().map(Note::normalize_tag),
+                kind: *kind,
+                location: location.clone(),
+                near: near.map(|center| (center, *within)),
+                meta,
+                include_archived: *all,
+                untriaged: if *inbox { Some(true) } else if *all { None } else { default_untriaged_filter(&config.inbox) },
+                ..NoteFilter::default()
+            };
+            if let Some(format) = format {
+                let layout = Layout::parse(format)?;
+                for mut note in note_manager.filtered_notes(&filter) {
+                    note_manager.redact_note(&mut note);
+                    println!("{}", layout.render(&note));
+                }
+            } else {
+                let options = DisplayOptions {
+                    max_lines: if *full { None } else { truncate.or(DisplayOptions::default().max_lines) },
+                    stats: *stats || config.display.stats,
+                    ..DisplayOptions::default()
+                };
+                note_manager.list_notes(&filter, &options);
+            }
+        }
+        Some(Commands::Show { id, stats, render }) => {
+            handle_show_command(&mut note_manager, id, *stats || config.display.stats, render.then_some(&config.diagram))?;
+        }
+        Some(Commands::Edit { id, no_snippets }) => {
+            let languages = config.language.detect.then_some(config.language.languages.as_slice());
+            handle_edit_command(&mut note_manager, id, (!*no_snippets).then_some(&config.snippets), languages)?;
         }
-        Some(Commands::Output { file }) => {
-            handle_output_command(&note_manager, file.as_deref())?;
+        Some(Commands::Qr { id }) => {
+            handle_qr_command(&note_manager, id, cli.ascii || config.display.ascii)?;
         }
-        Some(Commands::Import { file }) => {
-            handle_import_command(&mut note_manager, file)?;
+        Some(Commands::Search { query, regex, stem, fuzzy_typos, max_typos, tag, lang, full, context, external }) => {
+            let query = query.join(" ");
+            let matcher = if *stem {
+                Matcher::stemmed(&query, &config.search.language)?
+            } else if *fuzzy_typos {
+                Matcher::fuzzy(&query, *max_typos)
+            } else {
+                Matcher::new(&query, *regex)?
+            };
+            let filter = NoteFilter {
+                tag: tag.as_deref().map(Note::normalize_tag),
+                query: Some(matcher.clone()),
+                meta: lang.iter().map(|lang| (language::LANG_KEY.to_string(), Some(lang.clone()))).collect(),
+                include_archived: true,
+                ..NoteFilter::default()
+            };
+            let options = DisplayOptions {
+                max_lines: if *full { None } else { DisplayOptions::default().max_lines },
+                highlight: Some(matcher),
+                context: if *full { None } else { Some(*context) },
+                stats: config.display.stats,
+            };
+            if external.is_empty() {
+                note_manager.list_notes(&filter, &options);
+            } else {
+                handle_external_search(&note_manager, external, &filter, &options)?;
+            }
+        }
+        Some(Commands::Select { query, regex, tag, name }) => {
+            handle_select_command(&note_manager, &query.join(" "), *regex, tag.as_deref(), name)?;
+        }
+        Some(Commands::Triage) => {
+            note_manager.start_autosave(std::time::Duration::from_secs(config.storage.autosave_secs));
+            handle_triage_command(&mut note_manager)?;
+            note_manager.flush()?;
+        }
+        Some(Commands::Resurface { count, random }) => {
+            let notes = note_manager.resurface_notes(*count, *random)?;
+            if notes.is_empty() {
+                println!("  {}", "No notes to resurface".bright_black());
+            } else {
+                note_manager.display_notes(&notes, &DisplayOptions::default());
+            }
+        }
+        Some(Commands::Summary) => {
+            handle_summary_command(&note_manager, &config.summary)?;
+        }
+        Some(Commands::Stats { mood, days }) => {
+            if *mood {
+                handle_mood_stats_command(&note_manager, *days);
+            } else {
+                handle_stats_command(&note_manager);
+            }
+        }
+        Some(Commands::Journal { day, mood, no_snippets, text }) => {
+            let text = if *no_snippets { text.clone() } else { expand_snippets(text.clone(), &config.snippets, note_manager.now()) };
+            handle_journal_command(&mut note_manager, &text.join(" "), *mood, day.as_deref(), cli.porcelain)?;
+        }
+        Some(Commands::Digest { since, tag, format }) => {
+            handle_digest_command(&note_manager, since, tag.as_deref(), *format, config.calendar.week_start);
+        }
+        Some(Commands::Recap { notify, save, day }) => {
+            handle_recap_command(&mut note_manager, *notify, *save, day.as_deref(), &config.recap)?;
+        }
+        Some(Commands::Cal { month, day }) => {
+            handle_cal_command(&note_manager, month.as_deref(), day.as_deref(), &config.calendar);
+        }
+        Some(Commands::Tags { tree }) => {
+            handle_tags_command(&note_manager, *tree);
+        }
+        Some(Commands::Lang { detect, dry_run }) => {
+            handle_lang_command(&mut note_manager, *detect, *dry_run, &config.language.languages)?;
+        }
+        Some(Commands::Tag(tag_command)) => {
+            if !matches!(tag_command, TagCommands::Scan { dry_run: true }) {
+                auto_backup(&note_manager, &config.backup)?;
+            }
+            handle_tag_command(&mut note_manager, tag_command, &config.autotag)?;
+        }
+        Some(Commands::Srs(srs_command)) => {
+            if matches!(srs_command, SrsCommands::Review) {
+                note_manager.start_autosave(std::time::Duration::from_secs(config.storage.autosave_secs));
+            }
+            handle_srs_command(&mut note_manager, srs_command)?;
+            note_manager.flush()?;
+        }
+        Some(Commands::Read { url, tags }) => {
+            if !reading::is_url(url) {
+                println!("{} {} is not an http or https link", glyphs().error.red(), url.bright_cyan());
+                return Ok(());
+            }
+            handle_read_command(&mut note_manager, url, tags, cli.porcelain)?;
+        }
+        Some(Commands::Reading { command }) => {
+            handle_reading_command(&mut note_manager, command.as_ref())?;
+        }
+        Some(Commands::FromGit { rev, tags }) => {
+            handle_from_git_command(&mut note_manager, rev, tags, cli.porcelain)?;
+        }
+        Some(Commands::Say { id }) => {
+            handle_say_command(&mut note_manager, id, &config.say)?;
+        }
+        Some(Commands::Record { tags, text }) => {
+            handle_record_command(&mut note_manager, &text.join(" "), tags, &config, cli.porcelain)?;
+        }
+        Some(Commands::Snap { tags, text }) => {
+            handle_snap_command(&mut note_manager, &text.join(" "), tags, &config.snap, cli.porcelain)?;
+        }
+        Some(Commands::ShellInit { shell }) => {
+            print!("{}", shell::init_script(*shell));
+        }
+        Some(Commands::Calc { expression, redo }) => {
+            match redo {
+                Some(id) => handle_calc_redo(&mut note_manager, id)?,
+                None => handle_calc_command(&mut note_manager, &expression.join(" "), cli.porcelain)?,
+            }
+        }
+        Some(Commands::Clock(clock_command)) => {
+            handle_clock_command(&mut note_manager, clock_command, config.calendar.week_start)?;
+        }
+        Some(Commands::At { snapshot, command }) => {
+            handle_at_command(&note_manager, snapshot, command, config.display.stats)?;
+        }
+        Some(Commands::Backup(backup_command)) => {
+            handle_backup_command(&mut note_manager, backup_command, &config.backup)?;
+        }
+        Some(Commands::Meta(meta_command)) => {
+            handle_meta_command(&mut note_manager, meta_command)?;
+        }
+        Some(Commands::Remove { id, shred: true }) => handle_shred_command(&mut note_manager, id)?,
+        Some(Commands::Remove { id, shred: false }) => match selection::reference(id) {
+            Some(name) => {
+                auto_backup(&note_manager, &config.backup)?;
+                handle_remove_selection_command(&mut note_manager, name)?;
+            }
+            None => handle_remove_command(&mut note_manager, id)?,
+        },
+        Some(Commands::Output { file, format }) => {
+            handle_output_command(&note_manager, file.as_deref(), *format)?;
+        }
+        Some(Commands::Export { id, path, format, tags, since, matching, force, manifest }) => match format {
+            // With a filter, every matching note is exported to a directory,
+            // which may then come first
+            ExportFormat::Markdown if !tags.is_empty() || since.is_some() || matching.is_some() => {
+                if path.is_some() {
+                    println!("{} Filters export every matching note; leave out the note ID", glyphs().error.red());
+                    return Ok(());
+                }
+                let dir = path.as_deref().or(id.as_deref()).unwrap_or(".");
+                let filter = export_filter(since, matching.as_deref())?;
+                let tags: Vec<String> = tags.iter().map(|t| Note::normalize_tag(t)).collect();
+                let ids = note_manager.export_selection(&tags, &filter);
+                if ids.is_empty() {
+                    println!("{} No notes match", glyphs().error.red());
+                    return Ok(());
+                }
+                let files = note_manager.export_notes(&ids, Path::new(dir), *force)?;
+                println!("{} {} {} exported to {}, listed in {}", 
+                    glyphs().ok.green(),
+                    ids.len(),
+                    if ids.len() == 1 { "note" } else { "notes" },
+                    dir.bright_cyan(),
+                    slug::MANIFEST_FILE.bright_cyan()
+                );
+                if *manifest {
+                    seal_export(&note_manager, &ids, &files, Path::new(dir))?;
+                }
+            }
+            ExportFormat::Markdown => {
+                let Some(id) = id else {
+                    println!("{} Which note? Usage: {}", glyphs().error.red(), "note export <id> [path]".bright_cyan());
+                    return Ok(());
+                };
+                match selection::reference(id) {
+                    Some(name) => handle_export_selection_command(&note_manager, name, path.as_deref(), *force, *manifest)?,
+                    None => handle_export_command(&note_manager, id, path.as_deref(), *force, *manifest)?,
+                }
+            }
+            ExportFormat::Taskwarrior => {
+                if *manifest && path.is_none() {
+                    println!("{} A manifest needs the export in a file: {}", glyphs().error.red(), "note export <id> --format taskwarrior <file> --manifest".bright_cyan());
+                    return Ok(());
+                }
+                handle_task_export_command(&note_manager, id.as_deref(), path.as_deref(), *force, *manifest)?;
+            }
+            ExportFormat::Html => {
+                // The site takes no note ID, so its directory may come first
+                let Some(dir) = path.as_ref().or(id.as_ref()) else {
+                    println!("{} Which directory? Usage: {}", glyphs().error.red(), "note export --format html <dir>".bright_cyan());
+                    return Ok(());
+                };
+                let filter = export_filter(since, matching.as_deref())?;
+                let tags: Vec<String> = tags.iter().map(|t| Note::normalize_tag(t)).collect();
+                let ids = note_manager.export_selection(&tags, &filter);
+                let count = note_manager.export_site(Path::new(dir), &ids, *force)?;
+                println!("{} Exported {} {} to {}", 
+                    glyphs().ok.green(),
+                    count,
+                    if count == 1 { "note" } else { "notes" },
+                    Path::new(dir).join("index.html").display().to_string().bright_cyan()
+                );
+                if *manifest {
+                    // The site is the whole directory
+                    seal_export(&note_manager, &ids, &seal::files_in(Path::new(dir))?, Path::new(dir))?;
+                }
+            }
+        },
+        Some(Commands::Import { file, format, split_by, id_map, report, skip_duplicates, tolerance, verify_manifest }) => {
+            if let Some(manifest) = verify_manifest
+                && !verify_export(manifest, Path::new(file), cli.porcelain)? {
+                bail!("{} doesn't match {}; nothing was imported", file, manifest.display());
+            }
+            auto_backup(&note_manager, &config.backup)?;
+            let import = ImportOptions {
+                format: *format,
+                split_by: *split_by,
+                duplicates: skip_duplicates.then(|| Duration::seconds(i64::from(*tolerance))),
+            };
+            handle_import_command(&mut note_manager, file, &import, id_map.as_deref(), report.as_deref(), cli.porcelain)?;
+            warn_if_over_limits(note_manager.get_notes().len(), note_manager.file_size(), &config.limits);
+        }
+        Some(Commands::Attach { id, file }) => {
+            handle_attach_command(&mut note_manager, id, file.as_deref())?;
+        }
+        Some(Commands::Log { id, count }) => {
+            handle_log_command(&note_manager, id.as_deref(), *count, config.storage.audit_log)?;
+        }
+        // Run before the store was opened
+        Some(Commands::Remote { .. } | Commands::Watch { .. }) => {}
+        Some(Commands::Checksum) => {
+            let store = note_manager.save_checksums()?;
+            println!("{} Checksums of {} notes saved (store {})",
+                glyphs().ok.green(),
+                note_manager.get_notes().len(),
+                store[..16].bright_black()
+            );
+        }
+        Some(Commands::Verify) => {
+            handle_verify_command(&note_manager)?;
+        }
+        Some(Commands::Diff { id, revisions, file }) => {
+            handle_diff_command(&note_manager, id, revisions, file.as_deref())?;
+        }
+        Some(Commands::Migrate) => {
+            if note_manager.file_format() == FileFormat::V2 {
+                println!("{} Notes are already in the current format", glyphs().ok.green());
+            } else {
+                auto_backup(&note_manager, &config.backup)?;
+                note_manager.migrate_format()?;
+                println!("{} Converted {} {} to the current format", 
+                    glyphs().ok.green(),
+                    note_manager.get_notes().len(),
+                    if note_manager.get_notes().len() == 1 { "note" } else { "notes" }
+                );
+            }
+        }
+        Some(Commands::Gc) => {
+            handle_gc_command(&note_manager)?;
+        }
+        Some(Commands::Sync(SyncCommands::Folder { path, dry_run: true })) => {
+            handle_sync_folder_dry_run(&mut note_manager, path)?;
+        }
+        Some(Commands::Sync(SyncCommands::Folder { path, dry_run: false })) => {
+            auto_backup(&note_manager, &config.backup)?;
+            handle_sync_folder_command(&mut note_manager, path)?;
+        }
+        Some(Commands::Pack { file }) => {
+            handle_pack_command(&note_manager, file)?;
+        }
+        Some(Commands::Unpack { file }) => {
+            auto_backup(&note_manager, &config.backup)?;
+            handle_unpack_command(&mut note_manager, file)?;
+        }
+        #[cfg(all(unix, feature = "fuse"))]
+        Some(Commands::Mount { dir }) => {
+            // The mount owns the manager until it is unmounted
+            return handle_mount_command(note_manager, dir);
         }
         None => {
             if !cli.text.is_empty() {
-                handle_add_command(&mut note_manager, cli.text)?;
+                if !cli.force && !check_similar(&note_manager, &cli.text, &config.similar) {
+                    return Ok(());
+                }
+                let tags = [config.default_tags.as_slice(), &cli.tags].concat();
+                let location = capture_location(cli.location.as_deref(), cli.here, &config)?;
+                let text = if cli.no_snippets { cli.text } else { expand_snippets(cli.text, &config.snippets, note_manager.now()) };
+                let text = if cli.table { format_pasted_table(text) } else { text };
+                handle_add_command(&mut note_manager, text, &tags, cli.kind, location, &config, cli.porcelain)?;
+                warn_if_over_limits(note_manager.get_notes().len(), note_manager.file_size(), &config.limits);
             } else {
                 // List all notes
-                note_manager.list_notes();
+                let filter = NoteFilter {
+                    untriaged: default_untriaged_filter(&config.inbox),
+                    ..NoteFilter::default()
+                };
+                let options = DisplayOptions {
+                    stats: config.display.stats,
+                    ..DisplayOptions::default()
+                };
+                note_manager.list_notes(&filter, &options);
             }
         }
     }
+    report_archived_checklists(&note_manager);
     
     Ok(())
 }
 
-fn handle_remove_command(note_manager: &mut NoteManager, id: &str) -> Result<()> {
-    match note_manager.remove_note_by_id(id)? {
-        RemoveResult::Removed(note_id) => {
-            println!("{} Note {} removed", 
-                "✓".green(), 
-                format!("[{}]", note_id).yellow()
-            );
-        }
-        RemoveResult::NotFound => {
-            println!("{} No notes found matching {}", 
-                "✗".red(), 
-                format!("[{}]", id).yellow()
-            );
-        }
-        RemoveResult::Ambiguous(matching_ids) => {
-            println!("{} Multiple notes match {}:", 
-                "⚠".yellow(), 
-                format!("[{}]", id).yellow()
-            );
-            println!("  Please be more specific. Matching notes:");
-            for matching_id in matching_ids {
-                if let Some(note) = note_manager.get_notes().iter().find(|n| n.id == matching_id) {
-                    let formatted_time = note.timestamp.format("%b %d");
-                    println!("    {} {} {}", 
-                        format!("{:>6}", formatted_time).cyan(),
-                        format!("[{}]", note.id).yellow(),
-                        note.content.chars().take(50).collect::<String>()
-                            + if note.content.len() > 50 { "..." } else { "" }
-                    );
-                }
-            }
-        }
+// Status lines go to stderr, as commands with --porcelain print IDs
+fn report_archived_checklists(note_manager: &NoteManager) {
+    for id in note_manager.archived_checklists() {
+        eprintln!("{} Every box in {} is ticked, so it was archived (see it with `note list --all`)",
+            glyphs().ok.green(),
+            format!("[{}]", id).yellow()
+        );
     }
-    Ok(())
 }
 
-fn handle_add_command(note_manager: &mut NoteManager, text: Vec<String>) -> Result<()> {
-    // Join all text arguments with spaces to form the note content
+fn parse_where_clause(clause: &str) -> (String, Option<String>) {
+    match clause.split_once('=') {
+        Some((key, value)) => (key.trim().to_string(), Some(value.to_string())),
+        None => (clause.trim().to_string(), None),
+    }
+}
+
+// Rows that don't form a table are kept as they are
+fn format_pasted_table(text: Vec<String>) -> Vec<String> {
     let content = text.join(" ");
-    let note_id = note_manager.add_note(content)?;
-    println!("{} Note saved {}", 
-        "✓".green(), 
-        format!("[{}]", note_id).yellow()
-    );
-    Ok(())
+    match table::parse(&content) {
+        Some(rows) => vec![table::format_markdown(&rows)],
+        None => {
+            eprintln!("{} Not a table (needs two or more rows with the same number of columns); saved as typed", glyphs().warning.yellow());
+            vec![content]
+        }
+    }
 }
 
-fn handle_output_command(note_manager: &NoteManager, file_path: Option<&str>) -> Result<()> {
-    match file_path {
-        Some(path) => {
-            note_manager.output_raw_content_to_file(path)?;
-            println!("{} Notes exported to {}", 
-                "✓".green(),
-                path.bright_cyan()
-            );
+// A manual place, or the provider's answer for --here
+fn capture_location(place: Option<&str>, here: bool, config: &Config) -> Result<Option<String>> {
+    if here {
+        return location::from_provider(&config.location.command).map(Some);
+    }
+    Ok(place.map(|place| place.trim().to_string()).filter(|place| !place.is_empty()))
+}
+
+fn parse_mood(value: &str) -> std::result::Result<u8, String> {
+    diary::parse_mood(value).ok_or_else(|| format!("expected 1-5, one of {} or a face emoji", diary::MOODS.join(", ")))
+}
+
+fn parse_date(value: &str) -> std::result::Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| "expected a date as YYYY-MM-DD".to_string())
+}
+
+fn parse_language(value: &str) -> std::result::Result<String, String> {
+    language::parse(value)
+        .map(str::to_string)
+        .ok_or_else(|| "expected a language code such as de or a name such as german".to_string())
+}
+
+fn parse_coordinates(value: &str) -> std::result::Result<(f64, f64), String> {
+    location::coordinates(value).ok_or_else(|| "expected latitude and longitude as LAT,LON".to_string())
+}
+
+fn default_untriaged_filter(inbox_config: &InboxConfig) -> Option<bool> {
+    if inbox_config.hide_untriaged { Some(false) } else { None }
+}
+
+// Reads lines from stdin until a line containing only '.' or end of input
+fn read_multiline_note() -> Result<Vec<String>> {
+    let stdin = std::io::stdin();
+    let interactive = stdin.is_terminal();
+    if interactive {
+        eprintln!("{}", "Enter your note. Finish with a line containing only '.' or Ctrl-D".bright_black());
+    }
+    
+    let mut lines = Vec::new();
+    let mut input = stdin.lock();
+    loop {
+        if interactive {
+            eprint!("{} ", glyphs().prompt.bright_black());
+            std::io::stderr().flush()?;
         }
-        None => {
-            note_manager.output_raw_content()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            break;
         }
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line == "." {
+            break;
+        }
+        lines.push(line.to_string());
+    }
+    
+    // The add handler joins its arguments with spaces, so pass the text as one piece
+    Ok(vec![lines.join("\n")])
+}
+
+// Snapshot before operations that rewrite many notes at once
+fn auto_backup(note_manager: &NoteManager, backup_config: &BackupConfig) -> Result<()> {
+    if !backup_config.auto || note_manager.get_notes().is_empty() {
+        return Ok(());
     }
+    note_manager.create_backup()?;
+    note_manager.prune_backups(backup_config.keep)?;
     Ok(())
 }
 
-fn handle_import_command(note_manager: &mut NoteManager, file_path: &str) -> Result<()> {
-    let imported_count = note_manager.import_from_file(file_path)?;
-    println!("{} {} imported from {}", 
-        "✓".green(),
-        if imported_count == 1 { "note" } else { "notes" },
-        file_path.bright_cyan()
+// Soft limits never block an operation, they only suggest trimming the store.
+// Printed on stderr so porcelain output stays clean.
+fn warn_if_over_limits(note_count: usize, file_size: u64, limits: &LimitsConfig) {
+    let file_size_mb = file_size / (1024 * 1024);
+    
+    let mut exceeded = Vec::new();
+    if note_count > limits.max_notes {
+        exceeded.push(format!("{} notes (limit {})", note_count, limits.max_notes));
+    }
+    if file_size_mb > limits.max_file_size_mb {
+        exceeded.push(format!("{}MB (limit {}MB)", file_size_mb, limits.max_file_size_mb));
+    }
+    if exceeded.is_empty() {
+        return;
+    }
+    
+    eprintln!("{} Your notes have grown to {}", 
+        glyphs().warning.yellow(),
+        exceeded.join(" and ")
     );
+    eprintln!("  Consider archiving old notes: {} then remove what you no longer need", 
+        "note output archive.txt".bright_cyan()
+    );
+}
+
+fn append_options<'a>(cli: &'a Cli, config: &Config, clock: &'a dyn Clock) -> AppendOptions<'a> {
+    AppendOptions {
+        order: config.storage.order,
+        audit_command: config.storage.audit_log.then_some(cli.command_name.as_str()),
+        lock_timeout: config.storage.shared.then(|| std::time::Duration::from_secs(config.storage.lock_timeout_secs)),
+        tidy: config.storage.tidy,
+        clock,
+        inline_limit: config.storage.inline_limit_kb * 1024,
+    }
+}
+
+// Runs until interrupted. Text saved in the last few minutes is skipped, as
+// writers such as log shippers often repeat themselves.
+fn handle_watch_command(cli: &Cli, path: &Path, blocks: bool, tags: &[String], max_per_minute: usize, config: &Config, clock: &dyn Clock) -> Result<()> {
+    let dir = match config.data_dir() {
+        Some(dir) => dir,
+        None => NoteManager::default_dir()?,
+    };
+    let options = append_options(cli, config, clock);
+    let tags = [config.default_tags.as_slice(), tags].concat();
+    let mut seen = RecentText::within(watch::REPEAT_WINDOW);
+    let mut limit = RateLimit::per_minute(max_per_minute);
+    let mut dropped = 0;
+    
+    eprintln!("{} Watching {} for notes; stop with Ctrl-C", 
+        glyphs().ok.green(),
+        path.display().to_string().bright_cyan()
+    );
+    let mut tail = Tail::open(path)?;
+    let mut block: Vec<String> = Vec::new();
+    loop {
+        let text = match tail.next_line()? {
+            Some(line) if blocks && !line.trim().is_empty() => {
+                block.push(line);
+                continue;
+            }
+            Some(line) if !blocks => line,
+            // A blank line, or a writer closing the pipe, ends a block
+            _ => std::mem::take(&mut block).join("\n"),
+        };
+        let text = text.trim();
+        if text.is_empty() || seen.contains(text) {
+            continue;
+        }
+        if !limit.allow() {
+            dropped += 1;
+            eprintln!("{} Over {} notes a minute; dropped {} so far", 
+                glyphs().warning.yellow(),
+                max_per_minute,
+                dropped
+            );
+            continue;
+        }
+        let (content, tags, meta) = prepare_note(text.to_string(), &tags, None, None, config)?;
+        let added = NoteManager::append_note(&dir, content, &tags, meta, &options)?;
+        seen.insert(text);
+        print_note_saved(&added.id, cli.porcelain);
+    }
+}
+
+// Only when the note just added is the one that crowds `id_prefix`
+// character prefixes, so it is said once
+fn warn_if_prefixes_crowded(ids: &[String], new_id: &str, id_prefix: usize) {
+    if id_prefix == 0 {
+        return;
+    }
+    let before = PrefixTrie::new(ids.iter().map(String::as_str).filter(|id| *id != new_id));
+    let after = PrefixTrie::new(ids.iter().map(String::as_str));
+    if before.crowded(id_prefix) || !after.crowded(id_prefix) {
+        return;
+    }
+    eprintln!("{} {} of your {} notes now share their first {} ID {} with another", 
+        glyphs().warning.yellow(),
+        after.shared(id_prefix),
+        ids.len(),
+        id_prefix,
+        if id_prefix == 1 { "character" } else { "characters" }
+    );
+    eprintln!("  Type at least {} to pick a note; listings show the part needed in bold", id_prefix + 1);
+}
+
+fn handle_show_command(note_manager: &mut NoteManager, id: &str, stats: bool, render: Option<&DiagramConfig>) -> Result<()> {
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    let notes: Vec<Note> = note_manager.get_notes().iter()
+        .filter(|n| n.id == note_id)
+        .cloned()
+        .collect();
+    let options = DisplayOptions {
+        max_lines: None,
+        stats,
+        ..DisplayOptions::default()
+    };
+    note_manager.display_notes(&notes, &options);
+    if let (Some(diagram_config), Some(note)) = (render, notes.first()) {
+        render_diagrams(note, diagram_config, &note_manager.diagrams_dir());
+    }
+    note_manager.mark_viewed(&note_id)?;
+    Ok(())
+}
+
+// A failed diagram doesn't keep the rest from rendering
+fn render_diagrams(note: &Note, diagram_config: &DiagramConfig, dir: &Path) {
+    let diagrams = diagram::blocks(&note.content);
+    if diagrams.is_empty() {
+        println!("{} No mermaid or dot diagrams in {}", glyphs().warning.yellow(), format!("[{}]", note.id).yellow());
+    }
+    for (index, block) in diagrams.iter().enumerate() {
+        match diagram::render(block, diagram_config, dir) {
+            Ok(path) => println!("{} Diagram {} ({}): {}", glyphs().ok.green(), index + 1, block.language, path.display()),
+            Err(e) => eprintln!("{} Diagram {} ({}): {:#}", glyphs().error.red(), index + 1, block.language, e),
+        }
+    }
+}
+
+fn handle_edit_command(note_manager: &mut NoteManager, id: &str, snippets: Option<&BTreeMap<String, String>>, languages: Option<&[String]>) -> Result<()> {
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    let Some(note) = note_manager.get_notes().iter().find(|n| n.id == note_id).cloned() else {
+        return Ok(());
+    };
+    
+    let edited = editor::edit(&note.content, &note_id)?;
+    let edited = match snippets {
+        Some(snippets) => snippets::expand(&edited, snippets, &note.content, note_manager.now()),
+        None => edited,
+    };
+    if edited == note.content {
+        println!("{} No changes to {}", glyphs().ok.green(), format!("[{}]", note_id).yellow());
+        return Ok(());
+    }
+    
+    // Another process or a sync may have changed the note while the editor was open
+    note_manager.reload()?;
+    let current = note_manager.get_notes().iter()
+        .find(|n| n.id == note_id)
+        .map(|n| n.content.clone());
+    let content = match current {
+        Some(current) if current == note.content => edited,
+        Some(current) if current == edited => {
+            println!("{} No changes to {}", glyphs().ok.green(), format!("[{}]", note_id).yellow());
+            return Ok(());
+        }
+        Some(current) => {
+            println!("{} {} was changed while you were editing it", 
+                glyphs().warning.yellow(),
+                format!("[{}]", note_id).yellow()
+            );
+            match resolve_edit_conflict(&edited, &current, &note_id)? {
+                Some(content) => content,
+                None => {
+                    println!("{} Edit discarded", glyphs().error.red());
+                    return Ok(());
+                }
+            }
+        }
+        None => {
+            println!("{} {} was removed while you were editing it", 
+                glyphs().warning.yellow(),
+                format!("[{}]", note_id).yellow()
+            );
+            let answer = ask("Save your edit as a new note? [y/N]:")?;
+            if !answer.is_some_and(|answer| answer.eq_ignore_ascii_case("y")) {
+                println!("{} Edit discarded", glyphs().error.red());
+                return Ok(());
+            }
+            let new_id = note_manager.add_note_with_meta(edited, &note.tags(), note.meta.clone())?;
+            println!("{} Note saved {}", glyphs().ok.green(), format!("[{}]", new_id).yellow());
+            return Ok(());
+        }
+    };
+    
+    // An edit can change the language only when there's enough text to tell
+    let detected = languages.and_then(|languages| language::detect(&content, languages))
+        .filter(|lang| language::of(&note) != Some(*lang));
+    note_manager.update_content(&note_id, content)?;
+    if let Some(lang) = detected {
+        note_manager.update_meta(&note_id, language::LANG_KEY, Some(lang))?;
+    }
+    println!("{} Note {} updated", glyphs().ok.green(), format!("[{}]", note_id).yellow());
+    Ok(())
+}
+
+// Asks whether to merge, overwrite or abort; returns the content to save
+fn resolve_edit_conflict(mine: &str, theirs: &str, note_id: &str) -> Result<Option<String>> {
+    loop {
+        let Some(answer) = ask("[m]erge in the editor, [o]verwrite with your edit, [a]bort:")? else {
+            return Ok(None);
+        };
+        match answer.as_str() {
+            "m" => {
+                let mut merged = editor::edit(&editor::conflict_text(mine, theirs), note_id)?;
+                while editor::has_conflict_markers(&merged) {
+                    println!("  {}", "The note still has conflict markers".yellow());
+                    merged = editor::edit(&merged, note_id)?;
+                }
+                return Ok(Some(merged));
+            }
+            "o" => return Ok(Some(mine.to_string())),
+            "a" => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+// One line of input, or None at end of input
+fn ask(question: &str) -> Result<Option<String>> {
+    print!("  {} ", question.bright_black());
+    std::io::stdout().flush()?;
+    let mut line = String::new();
+    if std::io::stdin().read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(line.trim().to_string()))
+}
+
+fn handle_qr_command(note_manager: &NoteManager, id: &str, ascii: bool) -> Result<()> {
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    let Some(note) = note_manager.get_notes().iter().find(|n| n.id == note_id) else {
+        return Ok(());
+    };
+    println!("{}", qr::render(&note.content, ascii)?);
+    Ok(())
+}
+
+fn handle_remove_command(note_manager: &mut NoteManager, id: &str) -> Result<()> {
+    match note_manager.remove_note_by_id(id)? {
+        RemoveResult::Removed(note_id) => {
+            println!("{} Note {} removed", 
+                glyphs().ok.green(), 
+                format!("[{}]", note_id).yellow()
+            );
+        }
+        RemoveResult::NotFound => print_not_found(id),
+        RemoveResult::Ambiguous(matching_ids) => {
+            if let Some(note_id) = pick_ambiguous(note_manager, id, &matching_ids)? {
+                note_manager.remove_notes(std::slice::from_ref(&note_id))?;
+                println!("{} Note {} removed", 
+                    glyphs().ok.green(), 
+                    format!("[{}]", note_id).yellow()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// rm --shred takes no automatic backup, which would keep a copy of the note
+fn handle_shred_command(note_manager: &mut NoteManager, id: &str) -> Result<()> {
+    let ids = match selection::reference(id) {
+        Some(name) => match selected_notes(note_manager, name)? {
+            Some(ids) => ids,
+            None => return Ok(()),
+        },
+        None => match resolve_note_id(note_manager, id)? {
+            Some(note_id) => vec![note_id],
+            None => return Ok(()),
+        },
+    };
+    let removed = note_manager.shred_notes(&ids)?;
+    match ids.as_slice() {
+        [note_id] => println!("{} Note {} removed and overwritten on disk", 
+            glyphs().ok.green(),
+            format!("[{}]", note_id).yellow()
+        ),
+        _ => println!("{} Removed {} {} in {} and overwrote them on disk", 
+            glyphs().ok.green(),
+            removed,
+            if removed == 1 { "note" } else { "notes" },
+            id.cyan()
+        ),
+    }
+    
+    // Backups are compressed, so the note can't be overwritten in place there
+    let backups = note_manager.backups_holding(&ids)?;
+    if !backups.is_empty() {
+        println!("{} {} taken earlier still {}; delete {} from {} to be rid of it:", 
+            glyphs().warning.yellow(),
+            if backups.len() == 1 { "1 backup".to_string() } else { format!("{} backups", backups.len()) },
+            if ids.len() == 1 { "holds it" } else { "hold some of them" },
+            if backups.len() == 1 { "it" } else { "them" },
+            note_manager.backups_dir().display().to_string().bright_cyan()
+        );
+        for name in backups {
+            println!("  {}", name);
+        }
+    }
+    Ok(())
+}
+
+fn handle_remove_selection_command(note_manager: &mut NoteManager, name: &str) -> Result<()> {
+    let Some(ids) = selected_notes(note_manager, name)? else {
+        return Ok(());
+    };
+    let removed = note_manager.remove_notes(&ids)?;
+    println!("{} Removed {} {} in {}", 
+        glyphs().ok.green(),
+        removed,
+        if removed == 1 { "note" } else { "notes" },
+        format!("@{}", name).cyan()
+    );
+    Ok(())
+}
+
+fn handle_select_command(note_manager: &NoteManager, query: &str, regex: bool, tag: Option<&str>, name: &str) -> Result<()> {
+    if query.is_empty() && tag.is_none() {
+        let selections = note_manager.selections()?;
+        if selections.is_empty() {
+            println!("  {}", "No saved selections".bright_black());
+        }
+        for (name, ids) in selections {
+            println!("  {} {} {}", 
+                format!("@{}", name).cyan(),
+                ids.len(),
+                if ids.len() == 1 { "note" } else { "notes" }
+            );
+        }
+        return Ok(());
+    }
+    if !selection::is_valid_name(name) {
+        println!("{} Selection names can only have letters, digits, '-' and '_'", glyphs().error.red());
+        return Ok(());
+    }
+    
+    let filter = NoteFilter {
+        tag: tag.map(Note::normalize_tag),
+        query: (!query.is_empty()).then(|| Matcher::new(query, regex)).transpose()?,
+        include_archived: true,
+        ..NoteFilter::default()
+    };
+    let notes = note_manager.filtered_notes(&filter);
+    if notes.is_empty() {
+        println!("{} No notes match, {} left unchanged", glyphs().error.red(), format!("@{}", name).cyan());
+        return Ok(());
+    }
+    let ids: Vec<String> = notes.iter().map(|note| note.id.clone()).collect();
+    note_manager.save_selection(name, &ids)?;
+    for note in &notes {
+        println!("  {} {}", format!("[{}]", note.id).yellow(), note.content.lines().next().unwrap_or(""));
+    }
+    println!("{} Selected {} {} as {}", 
+        glyphs().ok.green(),
+        ids.len(),
+        if ids.len() == 1 { "note" } else { "notes" },
+        format!("@{}", name).cyan()
+    );
+    Ok(())
+}
+
+// The notes still in a saved selection, or None after saying why there are none
+fn selected_notes(note_manager: &NoteManager, name: &str) -> Result<Option<Vec<String>>> {
+    match note_manager.selection(name)? {
+        Some(ids) if !ids.is_empty() => Ok(Some(ids)),
+        Some(_) => {
+            println!("{} None of the notes in {} exist any more", glyphs().error.red(), format!("@{}", name).cyan());
+            Ok(None)
+        }
+        None => {
+            println!("{} No selection {}, create it with {}", 
+                glyphs().error.red(),
+                format!("@{}", name).cyan(),
+                format!("note select <query> --as {}", name).bright_cyan()
+            );
+            Ok(None)
+        }
+    }
+}
+
+fn print_not_found(id: &str) {
+    println!("{} No notes found matching {}", 
+        glyphs().error.red(), 
+        format!("[{}]", id).yellow()
+    );
+}
+
+fn print_ambiguous(note_manager: &NoteManager, id: &str, matching_ids: &[String]) {
+    println!("{} Multiple notes match {}:", 
+        glyphs().warning.yellow(), 
+        format!("[{}]", id).yellow()
+    );
+    println!("  Please be more specific. Matching notes:");
+    for matching_id in matching_ids {
+        if let Some(note) = note_manager.get_notes().iter().find(|n| &n.id == matching_id) {
+            let formatted_time = timezone::display(&note.timestamp).format("%b %d");
+            println!("    {} {} {}", 
+                format!("{:>6}", formatted_time).cyan(),
+                format!("[{}]", note.id).yellow(),
+                note.content.chars().take(50).collect::<String>()
+                    + if note.content.len() > 50 { "..." } else { "" }
+            );
+        }
+    }
+}
+
+// Resolves a partial ID to a single note, reporting misses and ambiguity
+fn resolve_note_id(note_manager: &NoteManager, id: &str) -> Result<Option<String>> {
+    match note_manager.lookup_note(id) {
+        LookupResult::Found(note_id) => Ok(Some(note_id)),
+        LookupResult::NotFound => {
+            print_not_found(id);
+            Ok(None)
+        }
+        LookupResult::Ambiguous(matching_ids) => pick_ambiguous(note_manager, id, &matching_ids),
+    }
+}
+
+// At a terminal, the notes an ID prefix matches are numbered to choose one
+// from; otherwise, as with --no-interactive, they are only listed
+fn pick_ambiguous(note_manager: &NoteManager, id: &str, matching_ids: &[String]) -> Result<Option<String>> {
+    if !PICK_AMBIGUOUS.load(Ordering::Relaxed) {
+        print_ambiguous(note_manager, id, matching_ids);
+        return Ok(None);
+    }
+    println!("{} Multiple notes match {}:", 
+        glyphs().warning.yellow(), 
+        format!("[{}]", id).yellow()
+    );
+    let notes: Vec<&Note> = matching_ids.iter()
+        .filter_map(|matching_id| note_manager.get_notes().iter().find(|n| &n.id == matching_id))
+        .collect();
+    for (index, note) in notes.iter().enumerate() {
+        let formatted_time = timezone::display(&note.timestamp).format("%b %d");
+        println!("  {} {} {} {}", 
+            format!("{:>2})", index + 1).bright_white(),
+            format!("{:>6}", formatted_time).cyan(),
+            format!("[{}]", note.id).yellow(),
+            note.content.lines().next().unwrap_or_default().chars().take(50).collect::<String>()
+        );
+    }
+    loop {
+        let Some(answer) = ask(&format!("Which one? (1-{}, Enter to cancel)", notes.len()))? else {
+            return Ok(None);
+        };
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(number) if (1..=notes.len()).contains(&number) => return Ok(Some(notes[number - 1].id.clone())),
+            _ => println!("  {}", format!("Enter a number from 1 to {}", notes.len()).yellow()),
+        }
+    }
+}
+
+// With [similar] check on, a note much like an existing one is only added
+// with --force. Returns true when the add should go ahead.
+fn check_similar(note_manager: &NoteManager, text: &[String], similar_config: &SimilarConfig) -> bool {
+    if !similar_config.check {
+        return true;
+    }
+    let Some((note, score)) = similar::most_similar(note_manager.get_notes(), &text.join(" "), similar_config.threshold) else {
+        return true;
+    };
+    eprintln!("{} Similar note {} exists ({:.0}% alike): {}", 
+        glyphs().warning.yellow(),
+        format!("[{}]", note.id).yellow(),
+        score * 100.0,
+        note.content.lines().next().unwrap_or_default()
+    );
+    eprintln!("  Not saved. Use {} to add it anyway", "--force".bright_cyan());
+    false
+}
+
+fn handle_add_command(note_manager: &mut NoteManager, text: Vec<String>, tags: &[String], kind: Option<NoteKind>, location: Option<String>, config: &Config, porcelain: bool) -> Result<()> {
+    // Join all text arguments with spaces to form the note content
+    let content = text.join(" ");
+    
+    // A bare link goes to the reading list
+    if kind.is_none() && reading::is_url(&content) {
+        return handle_read_command(note_manager, content.trim(), tags, porcelain);
+    }
+    
+    let (content, tags, meta) = prepare_note(content, tags, kind, location, config)?;
+    let note_id = note_manager.add_note_with_meta(content, &tags, meta)?;
+    print_note_saved(&note_id, porcelain);
+    let ids: Vec<String> = note_manager.get_notes().iter().map(|note| note.id.clone()).collect();
+    warn_if_prefixes_crowded(&ids, &note_id, config.display.id_prefix);
+    Ok(())
+}
+
+fn expand_snippets(text: Vec<String>, snippets: &BTreeMap<String, String>, now: DateTime<Local>) -> Vec<String> {
+    text.iter().map(|line| snippets::expand(line, snippets, "", now)).collect()
+}
+
+// The content, tags and metadata of a note about to be added
+fn prepare_note(content: String, tags: &[String], kind: Option<NoteKind>, location: Option<String>, config: &Config) -> Result<(String, Vec<String>, BTreeMap<String, String>)> {
+    let mut tags: Vec<String> = tags.iter().map(|t| Note::normalize_tag(t)).filter(|t| !t.is_empty()).collect();
+    if config.autotag.enabled {
+        tags.extend(AutoTagger::new(&config.autotag.ignore)?.extract(&content));
+    }
+    let mut meta = if config.inbox.capture { inbox::capture_meta() } else { BTreeMap::new() };
+    if config.language.detect && let Some(lang) = language::detect(&content, &config.language.languages) {
+        meta.insert(language::LANG_KEY.to_string(), lang.to_string());
+    }
+    if let Some(location) = location {
+        meta.insert(location::LOCATION_KEY.to_string(), location);
+    }
+    let content = match kind {
+        Some(kind) => {
+            meta.insert(KIND_KEY.to_string(), kind.name().to_string());
+            let template = config.templates.get(kind.name()).map_or(kind.default_template(), String::as_str);
+            kind::apply_template(template, &content)
+        }
+        None => content,
+    };
+    Ok((content, tags, meta))
+}
+
+fn print_note_saved(note_id: &str, porcelain: bool) {
+    let status = format!("{} Note saved {}", 
+        glyphs().ok.green(), 
+        format!("[{}]", note_id).yellow()
+    );
+    if porcelain {
+        println!("{}", note_id);
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
+}
+
+// Adds a note given on the command line without loading the store, unless
+// something about it needs the other notes: the similar-note check, or a
+// link, which goes to the reading list. Returns false to take the full path.
+fn quick_add(cli: &Cli, config: &Config, clock: &dyn Clock) -> Result<bool> {
+    let (text, tags, kind, location, here, table, force, no_snippets) = match &cli.command {
+        None if !cli.text.is_empty() => (&cli.text, &cli.tags, cli.kind, cli.location.as_deref(), cli.here, cli.table, cli.force, cli.no_snippets),
+        Some(Commands::Add { tags, kind, location, here, table, force, no_snippets, text }) if !text.is_empty() => {
+            (text, tags, *kind, location.as_deref(), *here, *table, *force, *no_snippets)
+        }
+        _ => return Ok(false),
+    };
+    if text.iter().all(|line| line.trim().is_empty())
+        || (config.similar.check && !force)
+        || (kind.is_none() && reading::is_url(&text.join(" "))) {
+        return Ok(false);
+    }
+    
+    let tags = [config.default_tags.as_slice(), tags].concat();
+    let location = capture_location(location, here, config)?;
+    let text = if no_snippets { text.clone() } else { expand_snippets(text.clone(), &config.snippets, clock.now()) };
+    let text = if table { format_pasted_table(text) } else { text };
+    let (content, tags, meta) = prepare_note(text.join(" "), &tags, kind, location, config)?;
+    let options = append_options(cli, config, clock);
+    let dir = match config.data_dir() {
+        Some(dir) => dir,
+        None => NoteManager::default_dir()?,
+    };
+    let added = NoteManager::append_note(&dir, content, &tags, meta, &options)?;
+    print_note_saved(&added.id, cli.porcelain);
+    warn_if_over_limits(added.note_count, added.file_size, &config.limits);
+    warn_if_prefixes_crowded(&added.ids, &added.id, config.display.id_prefix);
+    Ok(true)
+}
+
+fn handle_read_command(note_manager: &mut NoteManager, url: &str, tags: &[String], porcelain: bool) -> Result<()> {
+    // Without a title the link is still worth keeping
+    let title = reading::fetch_title(url).unwrap_or_else(|e| {
+        eprintln!("{} Couldn't fetch the page title: {:#}", glyphs().warning.yellow(), e);
+        None
+    });
+    
+    let mut tags: Vec<String> = tags.iter().map(|t| Note::normalize_tag(t)).filter(|t| !t.is_empty()).collect();
+    tags.insert(0, reading::READING_TAG.to_string());
+    let content = reading::note_content(title.as_deref(), url);
+    let note_id = note_manager.add_note(content.clone(), &tags)?;
+    let status = format!("{} Added to reading list {} {}", 
+        glyphs().ok.green(),
+        format!("[{}]", note_id).yellow(),
+        content
+    );
+    if porcelain {
+        println!("{}", note_id);
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
+    Ok(())
+}
+
+fn handle_reading_command(note_manager: &mut NoteManager, command: Option<&ReadingCommands>) -> Result<()> {
+    let Some(ReadingCommands::Done { id }) = command else {
+        // Oldest first, like a queue
+        let mut unread: Vec<Note> = note_manager.get_notes().iter()
+            .filter(|note| reading::is_unread(note))
+            .cloned()
+            .collect();
+        if unread.is_empty() {
+            println!("  {}", "Reading list is empty".bright_black());
+            return Ok(());
+        }
+        unread.sort_by_key(|note| note.timestamp);
+        note_manager.display_notes(&unread, &DisplayOptions::default());
+        println!("  {}", "Mark one as read with: note reading done <id>".bright_black());
+        return Ok(());
+    };
+    
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    let note = note_manager.get_notes().iter().find(|n| n.id == note_id);
+    if !note.is_some_and(|note| note.has_tag(reading::READING_TAG)) {
+        println!("{} {} is not on the reading list", 
+            glyphs().error.red(),
+            format!("[{}]", note_id).yellow()
+        );
+        return Ok(());
+    }
+    if !note.is_some_and(reading::is_unread) {
+        println!("{} {} was already read", 
+            glyphs().warning.yellow(),
+            format!("[{}]", note_id).yellow()
+        );
+        return Ok(());
+    }
+    
+    let today = note_manager.now().format("%Y-%m-%d").to_string();
+    note_manager.update_meta(&note_id, "read", Some(&today))?;
+    println!("{} Marked {} as read", 
+        glyphs().ok.green(),
+        format!("[{}]", note_id).yellow()
+    );
+    Ok(())
+}
+
+fn handle_output_command(note_manager: &NoteManager, file_path: Option<&str>, format: Option<&dyn Format>) -> Result<()> {
+    let content = note_manager.output(format)?;
+    match file_path {
+        Some(path) => {
+            std::fs::write(path, content).context("Failed to write to output file")?;
+            println!("{} Notes exported to {}", 
+                glyphs().ok.green(),
+                path.bright_cyan()
+            );
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+// Archived notes are left out of exports of many notes
+fn export_filter(since: &Option<NaiveDate>, matching: Option<&str>) -> Result<NoteFilter> {
+    Ok(NoteFilter {
+        since: *since,
+        query: matching.map(|text| Matcher::new(text, false)).transpose()?,
+        ..NoteFilter::default()
+    })
+}
+
+fn handle_export_command(note_manager: &NoteManager, id: &str, path: Option<&str>, force: bool, manifest: bool) -> Result<()> {
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    let target = note_manager.export_note(&note_id, path, force)?;
+    println!("{} {} exported to {}", 
+        glyphs().ok.green(),
+        format!("[{}]", note_id).yellow(),
+        target.display().to_string().bright_cyan()
+    );
+    if manifest {
+        seal_export(note_manager, &[note_id], std::slice::from_ref(&target), &target)?;
+    }
+    Ok(())
+}
+
+// With --manifest, records what an export wrote next to it
+fn seal_export(note_manager: &NoteManager, ids: &[String], files: &[PathBuf], target: &Path) -> Result<()> {
+    let path = note_manager.write_export_manifest(ids, files, target)?;
+    println!("  Manifest written to {}", path.display().to_string().bright_cyan());
+    Ok(())
+}
+
+// Whether the export at `import` is complete and unchanged, reporting what
+// isn't; status goes to stderr in porcelain mode, as the import's does
+fn verify_export(manifest: &Path, import: &Path, porcelain: bool) -> Result<bool> {
+    let verification = seal::verify(manifest, import)?;
+    let status = |message: String| if porcelain { eprintln!("{}", message) } else { println!("{}", message) };
+    if verification.is_ok() {
+        let exported = DateTime::parse_from_rfc3339(&verification.exported)
+            .map(|time| timezone::display(&time.with_timezone(&Local)).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or(verification.exported);
+        status(format!("{} Export of {} {} from {} matches its manifest", 
+            glyphs().ok.green(),
+            verification.count,
+            if verification.count == 1 { "note" } else { "notes" },
+            exported
+        ));
+        return Ok(true);
+    }
+    if !verification.intact {
+        status(format!("{} {} itself was altered or damaged; its hashes can't be trusted", 
+            glyphs().warning.yellow(),
+            manifest.display()
+        ));
+    }
+    for (label, files) in [("changed", &verification.changed), ("missing", &verification.missing), ("unlisted", &verification.unlisted)] {
+        for file in files {
+            status(format!("  {:<8} {}", label, file.bright_cyan()));
+        }
+    }
+    Ok(false)
+}
+
+// One Markdown file per note, in `dir` (created if needed) or here
+fn handle_export_selection_command(note_manager: &NoteManager, name: &str, dir: Option<&str>, force: bool, manifest: bool) -> Result<()> {
+    let Some(ids) = selected_notes(note_manager, name)? else {
+        return Ok(());
+    };
+    let dir = dir.unwrap_or(".");
+    let files = note_manager.export_notes(&ids, Path::new(dir), force)?;
+    println!("{} {} {} in {} exported to {}, listed in {}", 
+        glyphs().ok.green(),
+        ids.len(),
+        if ids.len() == 1 { "note" } else { "notes" },
+        format!("@{}", name).cyan(),
+        dir.bright_cyan(),
+        slug::MANIFEST_FILE.bright_cyan()
+    );
+    if manifest {
+        seal_export(note_manager, &ids, &files, Path::new(dir))?;
+    }
+    Ok(())
+}
+
+fn handle_task_export_command(note_manager: &NoteManager, id: Option<&str>, path: Option<&str>, force: bool, manifest: bool) -> Result<()> {
+    let note_id = match id {
+        Some(id) => match resolve_note_id(note_manager, id)? {
+            Some(note_id) => Some(note_id),
+            None => return Ok(()),
+        },
+        None => None,
+    };
+    
+    let notes: Vec<&Note> = note_manager.get_notes().iter()
+        .filter(|note| note_id.as_ref().is_none_or(|id| note.id == *id))
+        .collect();
+    let json = format::find("taskwarrior").context("Taskwarrior format not registered")?.write(&notes)?;
+    let Some(path) = path else {
+        print!("{}", json);
+        return Ok(());
+    };
+    
+    if Path::new(path).exists() && !force {
+        println!("{} {} already exists (use --force to replace it)", glyphs().error.red(), path.bright_cyan());
+        return Ok(());
+    }
+    std::fs::write(path, json)
+        .with_context(|| format!("Failed to write {}", path))?;
+    let task_count: usize = notes.iter().map(|note| taskwarrior::tasks_for_note(note).len()).sum();
+    println!("{} {} {} exported to {}, load them with {}", 
+        glyphs().ok.green(),
+        task_count,
+        if task_count == 1 { "task" } else { "tasks" },
+        path.bright_cyan(),
+        format!("task import {}", path).bright_cyan()
+    );
+    if manifest {
+        let ids: Vec<String> = notes.iter()
+            .filter(|note| !taskwarrior::tasks_for_note(note).is_empty())
+            .map(|note| note.id.clone())
+            .collect();
+        seal_export(note_manager, &ids, &[PathBuf::from(path)], Path::new(path))?;
+    }
+    Ok(())
+}
+
+// How `note import` reads its file
+struct ImportOptions {
+    format: &'static dyn Format,
+    split_by: SplitBy,
+    // Tolerance for --skip-duplicates
+    duplicates: Option<Duration>,
+}
+
+fn handle_import_command(note_manager: &mut NoteManager, file_path: &str, import: &ImportOptions, id_map: Option<&str>, report_path: Option<&str>, porcelain: bool) -> Result<()> {
+    let format = import.format;
+    let options = ReadOptions { split_by: import.split_by, now: note_manager.now() };
+    let report = note_manager.import(format, Path::new(file_path), &options, import.duplicates)?;
+    let (imported_ids, renamed) = (&report.ids, &report.renamed);
+    let imported_count = imported_ids.len();
+    
+    // Porcelain mode keeps stdout to one ID per line; everything else goes to stderr
+    let status = |message: String| if porcelain { eprintln!("{}", message) } else { println!("{}", message) };
+    if porcelain {
+        for note_id in imported_ids {
+            println!("{}", note_id);
+        }
+    }
+    
+    if let Some(report_path) = report_path {
+        write_import_report(report_path, file_path, format, &report, note_manager.now())?;
+    }
+    let report_written = || if let Some(report_path) = report_path {
+        status(format!("  Import report written to {}", report_path.bright_cyan()));
+    };
+    
+    let duplicates = report.entries.iter()
+        .filter(|entry| entry.reason.as_deref().is_some_and(|reason| reason.starts_with("duplicate of")))
+        .count();
+    if imported_count == 0 && duplicates == 0 && format.name() == "native" {
+        status(format!("{} No notes found in {}", 
+            glyphs().warning.yellow(),
+            file_path.bright_cyan()
+        ));
+        status(format!("  Files without {} headers can be imported with {}", 
+            "#id date".yellow(),
+            "--format plain".bright_cyan()
+        ));
+        report_written();
+        return Ok(());
+    }
+    
+    status(format!("{} {} {} imported from {}", 
+        glyphs().ok.green(),
+        imported_count,
+        if imported_count == 1 { "note" } else { "notes" },
+        file_path.bright_cyan()
+    ));
+    
+    if duplicates > 0 {
+        status(format!("  {} {} already in the store skipped", 
+            duplicates,
+            if duplicates == 1 { "note" } else { "notes" }
+        ));
+    }
+    if !renamed.is_empty() {
+        status(format!("  {} {} already taken and got new IDs:", 
+            renamed.len(),
+            if renamed.len() == 1 { "ID was" } else { "IDs were" }
+        ));
+        for (old_id, new_id) in renamed {
+            status(format!("    {} {} {}", old_id.yellow(), "->".bright_black(), new_id.yellow()));
+        }
+    }
+    if let Some(map_path) = id_map {
+        let lines: String = renamed.iter().map(|(old_id, new_id)| format!("{}\t{}\n", old_id, new_id)).collect();
+        std::fs::write(map_path, lines)
+            .with_context(|| format!("Failed to write {}", map_path))?;
+        status(format!("  ID mapping written to {}", map_path.bright_cyan()));
+    }
+    report_written();
+    Ok(())
+}
+
+// Everything needed to check a migration afterwards, or to remove what it
+// added: `jq -r '.notes[].id // empty' report.json | xargs -n1 note rm`
+fn write_import_report(path: &str, source: &str, format: &dyn Format, report: &ImportReport, time: DateTime<Local>) -> Result<()> {
+    let count = |status: &str| report.entries.iter().filter(|entry| entry.status == status).count();
+    let json = serde_json::json!({
+        "source": source,
+        "format": format.name(),
+        "time": timezone::storage(&time),
+        "created": count("created"),
+        "renamed": count("renamed"),
+        "skipped": count("skipped"),
+        "notes": report.entries,
+    });
+    std::fs::write(path, format!("{}\n", serde_json::to_string_pretty(&json)?))
+        .with_context(|| format!("Failed to write {}", path))
+}
+
+fn handle_attach_command(note_manager: &mut NoteManager, id: &str, file_path: Option<&str>) -> Result<()> {
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    
+    match file_path {
+        Some(path) => {
+            let deduplicated = note_manager.attach_file(&note_id, path)?;
+            println!("{} {} attached to {}{}", 
+                glyphs().ok.green(),
+                path.bright_cyan(),
+                format!("[{}]", note_id).yellow(),
+                if deduplicated { " (already stored)".bright_black().to_string() } else { String::new() }
+            );
+        }
+        None => {
+            let note = note_manager.get_notes().iter().find(|n| n.id == note_id);
+            for attachment in note.map(|n| n.attachments()).unwrap_or_default() {
+                println!("{} {}", 
+                    attachment.name,
+                    note_manager.attachment_path(&attachment).display().to_string().bright_black()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// A lone "list" lists the remote notes; anything else is a note to add
+fn handle_remote_command(host: &str, tags: &[String], text: &[String], remote_config: &RemoteConfig, porcelain: bool) -> Result<()> {
+    if let [word] = text && word == "list" {
+        let notes = remote::list(remote_config, host, tags.first().map(String::as_str))?;
+        if notes.is_empty() {
+            println!("  {}", format!("No notes on {}", host).bright_black());
+        }
+        for note in notes {
+            println!("{} {} {}", format!("[{}]", note.id).yellow(), note.date.bright_black(), note.first_line);
+        }
+        return Ok(());
+    }
+    let id = remote::add(remote_config, host, &text.join(" "), tags)?;
+    let status = format!("{} Note saved {} on {}", glyphs().ok.green(), format!("[{}]", id).yellow(), host.bright_cyan());
+    if porcelain {
+        println!("{}", id);
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
+    Ok(())
+}
+
+// Fails when anything differs, so scripts and sync hooks can check the result
+fn handle_verify_command(note_manager: &NoteManager) -> Result<()> {
+    let Some((manifest, differences)) = note_manager.verify_checksums()? else {
+        println!("{} No checksums yet; record them with {}", glyphs().warning.yellow(), "note checksum".bright_cyan());
+        return Ok(());
+    };
+    if !manifest.intact {
+        println!("{} checksums.txt itself was altered or damaged; its hashes can't be trusted", glyphs().warning.yellow());
+    }
+    if differences.is_empty() {
+        if !manifest.intact {
+            bail!("The checksum manifest is damaged");
+        }
+        println!("{} All {} notes match their checksums", glyphs().ok.green(), manifest.hashes.len());
+        return Ok(());
+    }
+    
+    let first_line = |id: &str| note_manager.get_notes().iter()
+        .find(|note| note.id == id)
+        .and_then(|note| note.content.lines().next())
+        .unwrap_or("")
+        .to_string();
+    for (label, ids) in [("changed", &differences.changed), ("missing", &differences.missing), ("added", &differences.added)] {
+        for id in ids {
+            println!("  {:<8} {} {}", label, format!("[{}]", id).yellow(), first_line(id).bright_black());
+        }
+    }
+    let count = differences.changed.len() + differences.missing.len() + differences.added.len();
+    bail!("{} from the checksums; run note checksum once they are as they should be",
+        if count == 1 { "1 note differs".to_string() } else { format!("{} notes differ", count) }
+    )
+}
+
+fn handle_diff_command(note_manager: &NoteManager, id: &str, revisions: &[String], file: Option<&Path>) -> Result<()> {
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    let current = note_manager.get_notes().iter()
+        .find(|n| n.id == note_id)
+        .map(|n| n.content.clone())
+        .unwrap_or_default();
+    
+    let ((old_name, old), (new_name, new)) = if let Some(file) = file {
+        let content = encoding::read(file)?;
+        ((CURRENT_REVISION.to_string(), current), (file.display().to_string(), content.trim_end().to_string()))
+    } else if revisions.is_empty() {
+        // Newest first, so this is the last change a backup caught
+        let mut earlier = None;
+        for backup in note_manager.list_backups()? {
+            if let Some(note) = note_manager.note_in_backup(&backup.name, &note_id)?
+                && note.content != current {
+                earlier = Some((backup.name, note.content));
+                break;
+            }
+        }
+        let Some(earlier) = earlier else {
+            println!("  {}", "No backup has an earlier version of this note".bright_black());
+            return Ok(());
+        };
+        (earlier, (CURRENT_REVISION.to_string(), current))
+    } else {
+        let mut resolved = Vec::new();
+        for revision in revisions {
+            match note_revision(note_manager, &note_id, revision, &current)? {
+                Some(found) => resolved.push(found),
+                None => return Ok(()),
+            }
+        }
+        let old = resolved.remove(0);
+        let new = resolved.pop().unwrap_or_else(|| (CURRENT_REVISION.to_string(), current));
+        (old, new)
+    };
+    
+    println!("{} {}", "---".red(), old_name);
+    println!("{} {}", "+++".green(), new_name);
+    let changes = diff::word_diff(&old, &new);
+    let (added, removed) = diff::counts(&changes);
+    if added == 0 && removed == 0 && old == new {
+        println!("  {}", "No differences".bright_black());
+        return Ok(());
+    }
+    println!("{}", diff::render(&changes));
+    println!("  {}", format!("+{} -{} words", added, removed).bright_black());
+    Ok(())
+}
+
+const CURRENT_REVISION: &str = "current";
+
+// The backup a revision names and the note's content in it, or None after
+// explaining why there isn't one
+fn note_revision(note_manager: &NoteManager, note_id: &str, revision: &str, current: &str) -> Result<Option<(String, String)>> {
+    if revision == CURRENT_REVISION {
+        return Ok(Some((CURRENT_REVISION.to_string(), current.to_string())));
+    }
+    let matches = note_manager.find_backups(revision)?;
+    let backup_name = match matches.as_slice() {
+        [single] => single.clone(),
+        [] => {
+            println!("{} No backup matching {}", 
+                glyphs().error.red(),
+                revision.yellow()
+            );
+            return Ok(None);
+        }
+        _ => {
+            println!("{} Multiple backups match {}:", 
+                glyphs().warning.yellow(),
+                revision.yellow()
+            );
+            for backup in matches {
+                println!("    {}", backup.bright_cyan());
+            }
+            return Ok(None);
+        }
+    };
+    match note_manager.note_in_backup(&backup_name, note_id)? {
+        Some(note) => Ok(Some((backup_name, note.content))),
+        None => {
+            println!("{} Note {} isn't in {}", 
+                glyphs().error.red(),
+                format!("[{}]", note_id).yellow(),
+                backup_name.bright_cyan()
+            );
+            Ok(None)
+        }
+    }
+}
+
+fn handle_log_command(note_manager: &NoteManager, id: Option<&str>, count: usize, enabled: bool) -> Result<()> {
+    let entries = note_manager.audit_entries()?;
+    // Deleted notes can't be looked up, so IDs are matched by prefix
+    let shown: Vec<&audit::Entry> = entries.iter().rev()
+        .filter(|entry| id.is_none_or(|id| entry.id.starts_with(id)))
+        .take(count)
+        .collect();
+    if shown.is_empty() {
+        println!("  {}", "No changes logged".bright_black());
+        if !enabled {
+            println!("  Set audit_log = true under [storage] in the config to log changes");
+        }
+        return Ok(());
+    }
+    
+    for entry in shown {
+        let op = format!("{:<4}", entry.op);
+        let op = match entry.op.as_str() {
+            "add" => op.green(),
+            "rm" => op.red(),
+            _ => op.yellow(),
+        };
+        let hashes = match (&entry.previous, &entry.hash) {
+            (Some(previous), Some(hash)) if previous != hash => format!("{} -> {}", previous, hash),
+            (_, Some(hash)) => hash.clone(),
+            (Some(previous), None) => previous.clone(),
+            (None, None) => String::new(),
+        };
+        println!("  {}  {} {}  note {}  {}", 
+            timezone::display(&entry.time).format("%Y-%m-%d %H:%M:%S").to_string().bright_black(),
+            op,
+            format!("[{}]", entry.id).bright_cyan(),
+            entry.command,
+            hashes.bright_black()
+        );
+    }
+    Ok(())
+}
+
+// Matches from each file are shown under its name; nothing is imported
+fn handle_external_search(note_manager: &NoteManager, files: &[String], filter: &NoteFilter, options: &DisplayOptions) -> Result<()> {
+    for file in files {
+        let mut notes: Vec<Note> = note_manager.read_external_notes(file)?.into_iter()
+            .filter(|note| filter.matches(note))
+            .collect();
+        notes.sort_by_key(|note| std::cmp::Reverse(note.timestamp));
+        
+        let name = if file == "-" { "stdin" } else { file.as_str() };
+        println!("{} {}", 
+            name.bright_cyan().bold(),
+            format!("({} {})", notes.len(), if notes.len() == 1 { "match" } else { "matches" }).bright_black()
+        );
+        if !notes.is_empty() {
+            note_manager.display_notes(&notes, options);
+        }
+    }
+    Ok(())
+}
+
+fn handle_gc_command(note_manager: &NoteManager) -> Result<()> {
+    let removed_count = note_manager.collect_garbage()?;
+    println!("{} {} unreferenced stored {} removed", 
+        glyphs().ok.green(),
+        removed_count,
+        if removed_count == 1 { "file" } else { "files" }
+    );
+    Ok(())
+}
+
+fn handle_sync_folder_command(note_manager: &mut NoteManager, folder: &Path) -> Result<()> {
+    let report = note_manager.sync_folder(folder, false)?;
+    let note_count = note_manager.get_notes().len();
+    println!("{} Synced {} {} with {}", 
+        glyphs().ok.green(),
+        note_count,
+        if note_count == 1 { "note" } else { "notes" },
+        folder.display().to_string().bright_cyan()
+    );
+    
+    let changes: Vec<String> = [
+        (report.written, "written to the folder"),
+        (report.renamed, "renamed"),
+        (report.updated, "updated from the folder"),
+        (report.added, "added from the folder"),
+        (report.removed_notes, "removed from your notes"),
+        (report.removed_files, "removed from the folder"),
+    ].iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, change)| format!("{} {}", count, change))
+        .collect();
+    if !changes.is_empty() {
+        println!("  {}", changes.join(", ").bright_black());
+    }
+    for id in &report.conflicts {
+        println!("{} Note {} changed on both sides; both versions were kept with conflict markers (resolve with {})", 
+            glyphs().warning.yellow(),
+            id.bright_cyan(),
+            format!("note edit {}", id).bright_cyan()
+        );
+    }
+    Ok(())
+}
+
+fn handle_sync_folder_dry_run(note_manager: &mut NoteManager, folder: &Path) -> Result<()> {
+    let report = note_manager.sync_folder(folder, true)?;
+    if report.changes.is_empty() {
+        println!("{} Nothing to sync with {}", 
+            glyphs().ok.green(),
+            folder.display().to_string().bright_cyan()
+        );
+        return Ok(());
+    }
+    println!("Syncing with {} would change {}:", 
+        folder.display().to_string().bright_cyan(),
+        if report.changes.len() == 1 { "1 note".to_string() } else { format!("{} notes", report.changes.len()) }
+    );
+    let width = report.changes.iter().map(|(subject, _)| subject.chars().count()).max().unwrap_or(0);
+    for (subject, change) in &report.changes {
+        println!("  {:<width$}  {}", subject.bright_cyan(), change);
+    }
+    println!("  {}", "Nothing was changed; run without --dry-run to sync".bright_black());
+    Ok(())
+}
+
+fn handle_pack_command(note_manager: &NoteManager, file: &Path) -> Result<()> {
+    let deleted = note_manager.pack(file)?;
+    let note_count = note_manager.get_notes().len();
+    println!("{} Packed {} {} and {} {} into {}", 
+        glyphs().ok.green(),
+        note_count,
+        if note_count == 1 { "note" } else { "notes" },
+        deleted,
+        if deleted == 1 { "deletion" } else { "deletions" },
+        file.display().to_string().bright_cyan()
+    );
+    println!("  {}", format!("Run `note unpack {}` on the other machine", file.display()).bright_black());
+    Ok(())
+}
+
+fn handle_unpack_command(note_manager: &mut NoteManager, file: &Path) -> Result<()> {
+    let report = note_manager.unpack(file)?;
+    if !report.changed() {
+        println!("{} Already up to date with {}", 
+            glyphs().ok.green(),
+            file.display().to_string().bright_cyan()
+        );
+        return Ok(());
+    }
+    let changes: Vec<String> = [
+        (report.added, "added"),
+        (report.updated, "updated"),
+        (report.removed, "removed"),
+    ].iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, change)| format!("{} {}", count, change))
+        .collect();
+    println!("{} Unpacked {}{}", 
+        glyphs().ok.green(),
+        file.display().to_string().bright_cyan(),
+        if changes.is_empty() { String::new() } else { format!(": {}", changes.join(", ")) }
+    );
+    for (old, new) in &report.renamed {
+        println!("{} Note {} is now {}, as the other machine has an older note with its ID", 
+            glyphs().warning.yellow(),
+            old.bright_cyan(),
+            new.bright_cyan()
+        );
+    }
+    for id in &report.conflicts {
+        println!("{} Note {} changed on both machines; both versions were kept with conflict markers (resolve with {})", 
+            glyphs().warning.yellow(),
+            id.bright_cyan(),
+            format!("note edit {}", id).bright_cyan()
+        );
+    }
+    Ok(())
+}
+
+// Blocks until the directory is unmounted, e.g. with `fusermount -u <dir>`
+#[cfg(all(unix, feature = "fuse"))]
+fn handle_mount_command(note_manager: NoteManager, dir: &std::path::Path) -> Result<()> {
+    let note_count = note_manager.get_notes().len();
+    println!("{} {} {} mounted at {}", 
+        glyphs().ok.green(),
+        note_count,
+        if note_count == 1 { "note" } else { "notes" },
+        dir.display().to_string().bright_cyan()
+    );
+    println!("  Saved files are written back to your notes. Unmount with {}", 
+        format!("fusermount -u {}", dir.display()).bright_cyan()
+    );
+    crate::mount::mount(note_manager, dir)
+}
+
+fn handle_summary_command(note_manager: &NoteManager, summary_config: &SummaryConfig) -> Result<()> {
+    let layout = Layout::parse(&summary_config.line)
+        .context("Invalid `line` in the [summary] config section")?;
+    let summary = summary::render(note_manager.get_notes(), summary_config, &layout, note_manager.now().date_naive());
+    if !summary.is_empty() {
+        println!("{}", summary);
+    }
+    Ok(())
+}
+
+fn handle_stats_command(note_manager: &NoteManager) {
+    let notes = note_manager.get_notes();
+    let archived = notes.iter().filter(|note| inbox::is_archived(note)).count();
+    let words: usize = notes.iter().map(|note| note.content.split_whitespace().count()).sum();
+    let rows = [
+        ("Notes", format!("{}{}", notes.len(), if archived > 0 { format!(" ({} archived)", archived) } else { String::new() })),
+        ("Words", words.to_string()),
+        ("Tags", note_manager.tag_counts().len().to_string()),
+        ("Days written on", note_manager.notes_per_day().len().to_string()),
+        ("Journal days", notes.iter().filter(|note| note.has_tag(diary::JOURNAL_TAG)).count().to_string()),
+    ];
+    for (label, value) in rows {
+        println!("  {:<16} {}", label.bright_black(), value);
+    }
+    if let Some(first) = notes.iter().map(|note| note.timestamp).min() {
+        println!("  {:<16} {}", "First note".bright_black(), timezone::display(&first).format("%b %-d %Y"));
+    }
+}
+
+fn handle_mood_stats_command(note_manager: &NoteManager, days: u32) {
+    let today = timezone::display(&note_manager.now()).date_naive();
+    let since = today - Duration::days(i64::from(days.max(1)) - 1);
+    let by_day = diary::moods_by_day(note_manager.get_notes(), since);
+    if by_day.is_empty() {
+        println!("  {}", format!("No moods in the last {} days; record one with note journal --mood good", days).bright_black());
+        return;
+    }
+    for line in diary::mood_chart(&by_day) {
+        println!("  {}", line);
+    }
+    let moods: Vec<u8> = by_day.values().flatten().copied().collect();
+    let average = diary::average(&moods);
+    println!();
+    println!("  {} {:.1} ({}) over {} {}, {} {}", 
+        "Average".bright_black(),
+        average,
+        diary::mood_name(average),
+        by_day.len(),
+        if by_day.len() == 1 { "day" } else { "days" },
+        moods.len(),
+        if moods.len() == 1 { "entry" } else { "entries" }
+    );
+}
+
+fn handle_journal_command(note_manager: &mut NoteManager, text: &str, mood: Option<u8>, day: Option<&str>, porcelain: bool) -> Result<()> {
+    let day = match day {
+        Some(day) => match NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                println!("{} Invalid date {}: use YYYY-MM-DD", glyphs().error.red(), day.yellow());
+                return Ok(());
+            }
+        },
+        None => timezone::display(&note_manager.now()).date_naive(),
+    };
+    
+    if text.trim().is_empty() && mood.is_none() {
+        match note_manager.journal_for(day).cloned() {
+            Some(note) => {
+                let lines = diary::render(&note.content);
+                println!("  {} {}", format!("[{}]", note.id).yellow(), lines.first().map_or("", String::as_str).bold());
+                for line in lines.iter().skip(1) {
+                    println!("  {}", line);
+                }
+                note_manager.mark_viewed(&note.id)?;
+            }
+            None => println!("  {}", format!("No journal for {}; write in it with note journal \"...\"", day.format("%b %-d")).bright_black()),
+        }
+        return Ok(());
+    }
+    
+    let id = note_manager.write_journal(day, mood, text)?;
+    let status = format!("{} Added to the journal {}", glyphs().ok.green(), format!("[{}]", id).yellow());
+    if porcelain {
+        println!("{}", id);
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
+    Ok(())
+}
+
+// Archived notes and saved recaps, which only repeat other notes, are left out
+fn handle_digest_command(note_manager: &NoteManager, since: &str, tag: Option<&str>, format: DigestFormat, week_start: Weekday) {
+    let today = timezone::display(&note_manager.now()).date_naive();
+    let Some(first) = digest::parse_since(since, today, week_start) else {
+        eprintln!("{} Invalid --since {}: use a date (YYYY-MM-DD), today, yesterday, this-week, last-week, this-month, last-month, or a count such as 3d or 2w", 
+            glyphs().error.red(),
+            since.yellow()
+        );
+        return;
+    };
+    let filter = NoteFilter {
+        tag: tag.map(Note::normalize_tag),
+        since: Some(first),
+        ..NoteFilter::default()
+    };
+    let mut notes: Vec<Note> = note_manager.filtered_notes(&filter).into_iter()
+        .filter(|note| !recap::is_recap(note))
+        .collect();
+    if notes.is_empty() {
+        eprintln!("  {}", format!("No notes since {}", first.format("%b %-d %Y")).bright_black());
+        return;
+    }
+    notes.reverse();
+    notes.iter_mut().for_each(|note| note_manager.redact_note(note));
+    let notes: Vec<&Note> = notes.iter().collect();
+    print!("{}", digest::render(&notes, first, today, format));
+}
+
+// With --notify or --save, as from a timer, only what was saved is printed
+fn handle_recap_command(note_manager: &mut NoteManager, notify: bool, save: bool, day: Option<&str>, recap_config: &RecapConfig) -> Result<()> {
+    let today = timezone::display(&note_manager.now()).date_naive();
+    let day = match day {
+        Some(day) => match NaiveDate::parse_from_str(day, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => {
+                println!("{} Invalid date {}: use YYYY-MM-DD", glyphs().error.red(), day.yellow());
+                return Ok(());
+            }
+        },
+        None => today,
+    };
+    let mut notes = note_manager.filtered_notes(&NoteFilter { day: Some(day), ..NoteFilter::default() });
+    notes.retain(|note| !recap::is_recap(note));
+    notes.reverse();
+    let notes: Vec<&Note> = notes.iter().collect();
+    let title = recap::title(notes.len(), day, today);
+    
+    if notify {
+        recap::notify(&recap_config.notify_command, &title, &recap::lines(&notes).join("\n"))?;
+    }
+    if save && !notes.is_empty() {
+        let content = recap::digest(&notes, day);
+        let key = day.format("%Y-%m-%d").to_string();
+        let earlier = note_manager.get_notes().iter()
+            .find(|note| recap::is_recap(note) && note.meta.get(recap::RECAP_KEY) == Some(&key))
+            .map(|note| note.id.clone());
+        let id = match earlier {
+            Some(id) => {
+                note_manager.update_content(&id, content)?;
+                id
+            }
+            None => note_manager.add_note_with_meta(content, &[recap::RECAP_TAG.to_string()], BTreeMap::from([(recap::RECAP_KEY.to_string(), key)]))?,
+        };
+        println!("{} Recap saved {}", glyphs().ok.green(), format!("[{}]", id).yellow());
+    }
+    if !notify && !save {
+        println!("{}", title.bold());
+        for line in recap::lines(&notes) {
+            println!("  {}", line);
+        }
+    }
+    Ok(())
+}
+
+fn handle_cal_command(note_manager: &NoteManager, month: Option<&str>, day: Option<&str>, calendar_config: &CalendarConfig) {
+    let today = timezone::display(&note_manager.now()).date_naive();
+    if let Some(day) = day {
+        let Ok(date) = NaiveDate::parse_from_str(day, "%Y-%m-%d") else {
+            println!("{} Invalid date {}: use YYYY-MM-DD", glyphs().error.red(), day.yellow());
+            return;
+        };
+        let filter = NoteFilter { day: Some(date), ..NoteFilter::default() };
+        let notes = note_manager.filtered_notes(&filter);
+        if notes.is_empty() {
+            println!("  {}", format!("No notes on {}", date.format("%b %-d, %Y")).bright_black());
+        } else {
+            note_manager.display_notes(&notes, &DisplayOptions::default());
+        }
+        return;
+    }
+    
+    let first = match month {
+        Some(month) => match calendar::parse_month(month, today.year()) {
+            Some(first) => first,
+            None => {
+                println!("{} Invalid month {}: use YYYY-MM, a month number or a name", glyphs().error.red(), month.yellow());
+                return;
+            }
+        },
+        None => today.with_day(1).unwrap_or(today),
+    };
+    let counts = note_manager.notes_per_day();
+    println!("{}", calendar::render(first, &counts, today, calendar_config));
+    
+    let in_month: Vec<(&NaiveDate, &usize)> = counts.iter()
+        .filter(|(date, _)| date.year() == first.year() && date.month() == first.month())
+        .collect();
+    let total: usize = in_month.iter().map(|(_, count)| **count).sum();
+    match in_month.iter().max_by_key(|(date, count)| (**count, std::cmp::Reverse(**date))) {
+        Some((date, count)) => println!("\n  {}", format!("{} {}, most on {} ({}); see a day with note cal --day {}", 
+            total,
+            if total == 1 { "note" } else { "notes" },
+            date.format("%b %-d"),
+            count,
+            date.format("%Y-%m-%d")
+        ).bright_black()),
+        None => println!("\n  {}", "No notes this month".bright_black()),
+    }
+}
+
+fn handle_lang_command(note_manager: &mut NoteManager, detect: bool, dry_run: bool, languages: &[String]) -> Result<()> {
+    if detect {
+        let detected: Vec<(String, String)> = note_manager.get_notes().iter()
+            .filter(|note| language::of(note).is_none())
+            .filter_map(|note| language::detect(&note.content, languages).map(|lang| (note.id.clone(), lang.to_string())))
+            .collect();
+        if detected.is_empty() {
+            println!("{} No new languages detected", glyphs().ok.green());
+            return Ok(());
+        }
+        for (id, lang) in &detected {
+            println!("  {} {}", format!("[{}]", id).yellow(), lang.cyan());
+        }
+        let summary = format!("{} {}", detected.len(), if detected.len() == 1 { "note" } else { "notes" });
+        if dry_run {
+            println!("{} Would store the language of {}", glyphs().ok.green(), summary);
+        } else {
+            note_manager.set_meta_values(language::LANG_KEY, &detected)?;
+            println!("{} Stored the language of {}", glyphs().ok.green(), summary);
+        }
+        return Ok(());
+    }
+    
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut unknown = 0;
+    for note in note_manager.get_notes() {
+        match language::of(note) {
+            Some(lang) => *counts.entry(lang).or_insert(0) += 1,
+            None => unknown += 1,
+        }
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    for (lang, count) in &counts {
+        println!("  {} {} {}", lang.cyan(), language::name(lang).unwrap_or(""), format!("({})", count).bright_black());
+    }
+    if unknown > 0 {
+        println!("  {}", format!("{} without a detected language; store it with note lang --detect", unknown).bright_black());
+    }
+    Ok(())
+}
+
+fn handle_tags_command(note_manager: &NoteManager, tree: bool) {
+    let counts = if tree { note_manager.tag_tree() } else { note_manager.tag_counts() };
+    if counts.is_empty() {
+        println!("  {}", "No tags yet".bright_black());
+        return;
+    }
+    for (tag, count) in counts {
+        let label = match tag.rsplit_once('/') {
+            // Nested tags show their own name, indented under their parent
+            Some((parent, name)) if tree => format!("{}{}", "  ".repeat(parent.split('/').count()), name),
+            _ => format!("#{}", tag),
+        };
+        println!("{} {}", 
+            label.cyan(),
+            format!("({})", count).bright_black()
+        );
+    }
+}
+
+fn handle_tag_command(note_manager: &mut NoteManager, command: &TagCommands, autotag_config: &AutoTagConfig) -> Result<()> {
+    let tag_exists = |manager: &NoteManager, tag: &str| manager.tag_counts().iter().any(|(t, _)| t == tag);
+    
+    match command {
+        TagCommands::Rename { old, new } => {
+            let (old, new) = (Note::normalize_tag(old), Note::normalize_tag(new));
+            if tag_exists(note_manager, &new) {
+                println!("{} Tag {} already exists, use {} to combine them", 
+                    glyphs().warning.yellow(),
+                    format!("#{}", new).cyan(),
+                    "note tag merge".bright_cyan()
+                );
+                return Ok(());
+            }
+            let changed = note_manager.retag_all(&old, Some(&new))?;
+            print_retag_result(&old, changed, &format!("renamed to {}", format!("#{}", new).cyan()));
+        }
+        TagCommands::Add { tag, ids, matching } => {
+            let tag = Note::normalize_tag(tag);
+            if tag.is_empty() {
+                println!("{} Tag can't be empty", glyphs().error.red());
+                return Ok(());
+            }
+            let Some(ids) = select_notes(note_manager, ids, matching.as_deref())? else {
+                return Ok(());
+            };
+            let changed = note_manager.tag_notes(&ids, &tag, true)?;
+            print_batch_tag_result(&tag, changed, ids.len(), "added to", "already tagged");
+        }
+        TagCommands::Remove { tag, ids, matching } => {
+            let tag = Note::normalize_tag(tag);
+            if ids.is_empty() && matching.is_none() {
+                let changed = note_manager.retag_all(&tag, None)?;
+                print_retag_result(&tag, changed, "removed");
+                return Ok(());
+            }
+            let Some(ids) = select_notes(note_manager, ids, matching.as_deref())? else {
+                return Ok(());
+            };
+            let changed = note_manager.tag_notes(&ids, &tag, false)?;
+            print_batch_tag_result(&tag, changed, ids.len(), "removed from", "not tagged");
+        }
+        TagCommands::Merge { from, into } => {
+            let (from, into) = (Note::normalize_tag(from), Note::normalize_tag(into));
+            let changed = note_manager.retag_all(&from, Some(&into))?;
+            print_retag_result(&from, changed, &format!("merged into {}", format!("#{}", into).cyan()));
+        }
+        TagCommands::Scan { dry_run } => {
+            let tagger = AutoTagger::new(&autotag_config.ignore)?;
+            let additions: Vec<(String, Vec<String>)> = note_manager.get_notes().iter()
+                .map(|note| (note.id.clone(), tagger.extract(&note.content).into_iter()
+                    .filter(|tag| !note.has_tag(tag))
+                    .collect::<Vec<String>>()))
+                .filter(|(_, tags)| !tags.is_empty())
+                .collect();
+            if additions.is_empty() {
+                println!("  {}", "No new tags found".bright_black());
+                return Ok(());
+            }
+            
+            for (id, tags) in &additions {
+                let tags: Vec<String> = tags.iter().map(|tag| format!("#{}", tag)).collect();
+                println!("  {} {}", format!("[{}]", id).yellow(), tags.join(" ").cyan());
+            }
+            let count: usize = additions.iter().map(|(_, tags)| tags.len()).sum();
+            let summary = format!("{} {} to {} {}", 
+                count,
+                if count == 1 { "tag" } else { "tags" },
+                additions.len(),
+                if additions.len() == 1 { "note" } else { "notes" }
+            );
+            if *dry_run {
+                println!("{} Would add {}", glyphs().ok.green(), summary);
+            } else {
+                note_manager.add_tags(&additions)?;
+                println!("{} Added {}", glyphs().ok.green(), summary);
+            }
+        }
+    }
+    Ok(())
+}
+
+// The full IDs of the given notes, with @name standing for a saved selection,
+// or of the notes matching `pattern`. None (after printing why) when an ID
+// doesn't resolve or nothing matches, so a typo never applies a change to
+// only part of the selection.
+fn select_notes(note_manager: &NoteManager, ids: &[String], pattern: Option<&str>) -> Result<Option<Vec<String>>> {
+    if let Some(pattern) = pattern {
+        let filter = NoteFilter {
+            query: Some(Matcher::new(pattern, true)?),
+            include_archived: true,
+            ..NoteFilter::default()
+        };
+        let ids: Vec<String> = note_manager.filtered_notes(&filter).into_iter().map(|note| note.id).collect();
+        if ids.is_empty() {
+            println!("{} No notes match {}", glyphs().error.red(), pattern.yellow());
+            return Ok(None);
+        }
+        return Ok(Some(ids));
+    }
+    
+    let mut resolved = Vec::new();
+    for id in ids {
+        let note_ids = match selection::reference(id) {
+            Some(name) => match selected_notes(note_manager, name)? {
+                Some(note_ids) => note_ids,
+                None => return Ok(None),
+            },
+            None => match resolve_note_id(note_manager, id)? {
+                Some(note_id) => vec![note_id],
+                None => return Ok(None),
+            },
+        };
+        for note_id in note_ids {
+            if !resolved.contains(&note_id) {
+                resolved.push(note_id);
+            }
+        }
+    }
+    Ok(Some(resolved))
+}
+
+fn print_batch_tag_result(tag: &str, changed: usize, selected: usize, action: &str, unchanged: &str) {
+    let glyph = if changed == 0 { glyphs().warning.yellow() } else { glyphs().ok.green() };
+    let mut summary = format!("{} {} {} {} {}", 
+        glyph,
+        format!("#{}", tag).cyan(),
+        action,
+        changed,
+        if changed == 1 { "note" } else { "notes" }
+    );
+    if selected > changed {
+        summary.push_str(&format!(" ({} {})", selected - changed, unchanged).bright_black().to_string());
+    }
+    println!("{}", summary);
+}
+
+fn print_retag_result(tag: &str, changed: usize, action: &str) {
+    if changed == 0 {
+        println!("{} No notes tagged {}", 
+            glyphs().error.red(),
+            format!("#{}", tag).cyan()
+        );
+        return;
+    }
+    println!("{} {} {} ({} {})", 
+        glyphs().ok.green(),
+        format!("#{}", tag).cyan(),
+        action,
+        changed,
+        if changed == 1 { "note" } else { "notes" }
+    );
+}
+
+fn handle_meta_command(note_manager: &mut NoteManager, command: &MetaCommands) -> Result<()> {
+    let (id, key) = match command {
+        MetaCommands::Set { id, key, .. } | MetaCommands::Remove { id, key } => (id, Some(key)),
+        MetaCommands::Show { id } => (id, None),
+    };
+    
+    if let Some(key) = key {
+        if !NoteParser::is_valid_meta_key(key) {
+            println!("{} Invalid key {}: use letters, digits, '-' and '_'", 
+                glyphs().error.red(),
+                key.yellow()
+            );
+            return Ok(());
+        }
+        if RESERVED_META_KEYS.contains(&key.as_str()) {
+            println!("{} {} is managed by its own command", 
+                glyphs().error.red(),
+                key.yellow()
+            );
+            return Ok(());
+        }
+    }
+    
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    
+    match command {
+        MetaCommands::Set { key, value, .. } => {
+            let value = value.join(" ");
+            note_manager.update_meta(&note_id, key, Some(&value))?;
+            println!("{} {}={} set on {}", 
+                glyphs().ok.green(),
+                key,
+                value.bright_cyan(),
+                format!("[{}]", note_id).yellow()
+            );
+        }
+        MetaCommands::Remove { key, .. } => {
+            if note_manager.update_meta(&note_id, key, None)? {
+                println!("{} {} removed from {}", 
+                    glyphs().ok.green(),
+                    key,
+                    format!("[{}]", note_id).yellow()
+                );
+            } else {
+                println!("{} {} has no {} field", 
+                    glyphs().error.red(),
+                    format!("[{}]", note_id).yellow(),
+                    key
+                );
+            }
+        }
+        MetaCommands::Show { .. } => {
+            if let Some(note) = note_manager.get_notes().iter().find(|n| n.id == note_id) {
+                for (key, value) in note.custom_meta() {
+                    println!("{} {}", key.bright_black(), value);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_srs_command(note_manager: &mut NoteManager, command: &SrsCommands) -> Result<()> {
+    match command {
+        SrsCommands::Add { id } => {
+            let Some(note_id) = resolve_note_id(note_manager, id)? else {
+                return Ok(());
+            };
+            if note_manager.enroll_srs(&note_id)? {
+                println!("{} {} added to the review queue", 
+                    glyphs().ok.green(),
+                    format!("[{}]", note_id).yellow()
+                );
+            } else {
+                println!("{} {} is already in the review queue", 
+                    glyphs().warning.yellow(),
+                    format!("[{}]", note_id).yellow()
+                );
+            }
+        }
+        SrsCommands::Remove { id } => {
+            let Some(note_id) = resolve_note_id(note_manager, id)? else {
+                return Ok(());
+            };
+            if note_manager.update_meta(&note_id, "srs", None)? {
+                println!("{} {} removed from the review queue", 
+                    glyphs().ok.green(),
+                    format!("[{}]", note_id).yellow()
+                );
+            } else {
+                println!("{} {} is not in the review queue", 
+                    glyphs().error.red(),
+                    format!("[{}]", note_id).yellow()
+                );
+            }
+        }
+        SrsCommands::Review => handle_srs_review(note_manager)?,
+    }
+    Ok(())
+}
+
+fn handle_triage_command(note_manager: &mut NoteManager) -> Result<()> {
+    let pending = note_manager.inbox_notes();
+    if pending.is_empty() {
+        println!("{} Inbox is empty", glyphs().ok.green());
+        return Ok(());
+    }
+    
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut prompt = |question: &str| -> Result<Option<String>> {
+        print!("  {} ", question.bright_black());
+        std::io::stdout().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim().to_string()))
+    };
+    let mut triaged = 0;
+    
+    'notes: for (index, note) in pending.iter().enumerate() {
+        println!();
+        println!("  {} {}", 
+            format!("[{}]", note.id).yellow(),
+            format!("{}/{}", index + 1, pending.len()).bright_black()
+        );
+        for line in note.content.lines() {
+            println!("  {}", line);
+        }
+        
+        loop {
+            let Some(answer) = prompt("[k]eep [t]ag [a]rchive [d]elete [s]nooze, Enter to skip, q to quit:")? else {
+                break 'notes;
+            };
+            let action = match answer.as_str() {
+                "" => continue 'notes,
+                "q" => break 'notes,
+                "k" => TriageAction::Keep(Vec::new()),
+                "t" => {
+                    let Some(tags) = prompt("Tags (separated by spaces):")? else {
+                        break 'notes;
+                    };
+                    let tags: Vec<String> = tags.split_whitespace().map(Note::normalize_tag).filter(|t| !t.is_empty()).collect();
+                    TriageAction::Keep(tags)
+                }
+                "a" => TriageAction::Archive,
+                "s" => {
+                    let Some(days) = prompt("Snooze for how many days? [1]")? else {
+                        break 'notes;
+                    };
+                    let days = if days.is_empty() { Some(1) } else { days.parse::<u32>().ok() };
+                    let Some(days) = days else {
+                        continue;
+                    };
+                    TriageAction::Snooze(note_manager.now().date_naive() + Duration::days(days.max(1) as i64))
+                }
+                "d" => {
+                    note_manager.remove_note_by_id(&note.id)?;
+                    println!("  {} deleted", glyphs().ok.green());
+                    triaged += 1;
+                    continue 'notes;
+                }
+                _ => continue,
+            };
+            
+            note_manager.triage_note(&note.id, &action)?;
+            let outcome = match &action {
+                TriageAction::Keep(tags) if tags.is_empty() => "kept".to_string(),
+                TriageAction::Keep(tags) => format!("kept with {}", tags.iter().map(|t| format!("#{}", t)).collect::<Vec<_>>().join(" ").cyan()),
+                TriageAction::Archive => "archived".to_string(),
+                TriageAction::Snooze(until) => format!("snoozed until {}", until.format("%b %d").to_string().cyan()),
+            };
+            println!("  {} {}", glyphs().ok.green(), outcome);
+            triaged += 1;
+            continue 'notes;
+        }
+    }
+    
+    println!();
+    println!("{} {} of {} triaged", 
+        glyphs().ok.green(),
+        triaged,
+        pending.len()
+    );
+    Ok(())
+}
+
+// The first line of a note is the prompt; the rest is revealed on Enter
+fn handle_srs_review(note_manager: &mut NoteManager) -> Result<()> {
+    let due_notes = note_manager.due_srs_notes();
+    if due_notes.is_empty() {
+        println!("{} Nothing to review today", glyphs().ok.green());
+        return Ok(());
+    }
+    
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut reviewed = 0;
+    
+    for (index, note) in due_notes.iter().enumerate() {
+        let mut lines = note.content.lines();
+        println!();
+        println!("  {} {}", 
+            format!("[{}]", note.id).yellow(),
+            format!("{}/{}", index + 1, due_notes.len()).bright_black()
+        );
+        println!("  {}", lines.next().unwrap_or(""));
+        
+        let rest: Vec<&str> = lines.collect();
+        if !rest.is_empty() {
+            print!("  {}", "Press Enter to reveal...".bright_black());
+            std::io::stdout().flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                break;
+            }
+            for line in rest {
+                println!("  {}", line);
+            }
+        }
+        
+        let grade = loop {
+            print!("  {} ", "Recall 0-5 (0 forgot, 5 perfect), q to quit:".bright_black());
+            std::io::stdout().flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                break None;
+            }
+            match line.trim() {
+                "q" => break None,
+                answer => {
+                    if let Ok(grade @ 0..=5) = answer.parse::<u8>() {
+                        break Some(grade);
+                    }
+                }
+            }
+        };
+        let Some(grade) = grade else {
+            break;
+        };
+        
+        let card = note_manager.record_srs_review(&note.id, grade)?;
+        println!("  {} next review {}", 
+            glyphs().ok.green(),
+            card.due.format("%b %d").to_string().cyan()
+        );
+        reviewed += 1;
+    }
+    
+    println!();
+    println!("{} {} of {} reviewed", 
+        glyphs().ok.green(),
+        reviewed,
+        due_notes.len()
+    );
+    Ok(())
+}
+
+fn handle_from_git_command(note_manager: &mut NoteManager, rev: &str, tags: &[String], porcelain: bool) -> Result<()> {
+    let commit = git::read_commit(rev)?;
+    let mut tags: Vec<String> = tags.iter().map(|t| Note::normalize_tag(t)).filter(|t| !t.is_empty()).collect();
+    let repo_tag = Note::normalize_tag(&commit.repo);
+    if !repo_tag.is_empty() {
+        tags.insert(0, repo_tag);
+    }
+    
+    let note_id = note_manager.add_note(git::note_content(&commit), &tags)?;
+    let subject = commit.message.lines().next().unwrap_or_default();
+    let status = format!("{} Saved commit {} {} {}", 
+        glyphs().ok.green(),
+        commit.sha.get(..7).unwrap_or(&commit.sha).bright_cyan(),
+        subject,
+        format!("[{}]", note_id).yellow()
+    );
+    if porcelain {
+        println!("{}", note_id);
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
+    Ok(())
+}
+
+fn handle_say_command(note_manager: &mut NoteManager, id: &str, say_config: &SayConfig) -> Result<()> {
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    let Some(mut note) = note_manager.get_notes().iter().find(|n| n.id == note_id).cloned() else {
+        return Ok(());
+    };
+    note_manager.redact_note(&mut note);
+    let text = speech::spoken_text(&note.content);
+    if text.is_empty() {
+        println!("{} Nothing to read in {}", glyphs().warning.yellow(), format!("[{}]", note_id).yellow());
+        return Ok(());
+    }
+    println!("{} Reading {} aloud", glyphs().ok.green(), format!("[{}]", note_id).yellow());
+    speech::say(&say_config.command, &text)?;
+    note_manager.mark_viewed(&note_id)?;
+    Ok(())
+}
+
+// Secrets typed or printed during the session are masked in the note itself,
+// since the transcript is never seen before it is stored
+fn handle_record_command(note_manager: &mut NoteManager, title: &str, tags: &[String], config: &Config, porcelain: bool) -> Result<()> {
+    let mut patterns = config.redact.patterns.clone();
+    if config.record.redact_secrets {
+        patterns.extend(record::SECRET_PATTERNS.iter().map(|pattern| pattern.to_string()));
+    }
+    let redactor = Redactor::new(&patterns, &config.redact.mask)?;
+    let started = timezone::display(&note_manager.now());
+    eprintln!("{} Recording a shell session; exit it to save the transcript", glyphs().ok.green());
+    let transcript = record::capture(&config.record.command)?
+        .map(|raw| record::clean(&raw))
+        .filter(|transcript| !transcript.is_empty());
+    let Some(transcript) = transcript else {
+        eprintln!("{} Nothing was recorded", glyphs().warning.yellow());
+        return Ok(());
+    };
+    let transcript = record::truncate(&redactor.redact(&transcript), config.record.max_bytes);
+    
+    let mut tags: Vec<String> = tags.iter().map(|t| Note::normalize_tag(t)).filter(|t| !t.is_empty()).collect();
+    tags.insert(0, record::RECORD_TAG.to_string());
+    let title = if title.trim().is_empty() {
+        format!("Terminal session {}", started.format("%Y-%m-%d %H:%M"))
+    } else {
+        title.trim().to_string()
+    };
+    let note_id = note_manager.add_note(record::note_content(&title, &transcript), &tags)?;
+    
+    let status = format!("{} Session saved {} ({} lines)", 
+        glyphs().ok.green(),
+        format!("[{}]", note_id).yellow(),
+        transcript.lines().count()
+    );
+    if porcelain {
+        println!("{}", note_id);
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
+    Ok(())
+}
+
+fn handle_snap_command(note_manager: &mut NoteManager, caption: &str, tags: &[String], snap_config: &SnapConfig, porcelain: bool) -> Result<()> {
+    let Some(image) = snap::capture(&snap_config.command, note_manager.now())? else {
+        eprintln!("{} No screenshot taken", glyphs().warning.yellow());
+        return Ok(());
+    };
+    
+    let mut tags: Vec<String> = tags.iter().map(|t| Note::normalize_tag(t)).filter(|t| !t.is_empty()).collect();
+    tags.insert(0, snap::SNAP_TAG.to_string());
+    let content = if caption.trim().is_empty() {
+        format!("Screenshot {}", timezone::display(&note_manager.now()).format("%Y-%m-%d %H:%M"))
+    } else {
+        caption.trim().to_string()
+    };
+    
+    let saved = note_manager.add_note(content, &tags)
+        .and_then(|note_id| note_manager.attach_file(&note_id, &image.to_string_lossy()).map(|_| note_id));
+    snap::clean_up(&image);
+    let note_id = saved?;
+    
+    let status = format!("{} Screenshot saved {}", glyphs().ok.green(), format!("[{}]", note_id).yellow());
+    if porcelain {
+        println!("{}", note_id);
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
+    Ok(())
+}
+
+fn handle_calc_command(note_manager: &mut NoteManager, expression: &str, porcelain: bool) -> Result<()> {
+    let result = calc::evaluate(expression)?;
+    let content = calc::note_content(expression, result);
+    let note_id = note_manager.add_note(content.clone(), &[calc::CALC_TAG.to_string()])?;
+    let status = format!("{} {} {}", 
+        glyphs().ok.green(),
+        content,
+        format!("[{}]", note_id).yellow()
+    );
+    if porcelain {
+        println!("{}", note_id);
+        eprintln!("{}", status);
+    } else {
+        println!("{}", status);
+    }
+    Ok(())
+}
+
+fn handle_calc_redo(note_manager: &mut NoteManager, id: &str) -> Result<()> {
+    let Some(note_id) = resolve_note_id(note_manager, id)? else {
+        return Ok(());
+    };
+    let Some(note) = note_manager.get_notes().iter().find(|n| n.id == note_id) else {
+        return Ok(());
+    };
+    if !note.has_tag(calc::CALC_TAG) {
+        println!("{} {} is not a calculation note", 
+            glyphs().error.red(),
+            format!("[{}]", note_id).yellow()
+        );
+        return Ok(());
+    }
+    
+    // Keep any lines the user added below the calculation
+    let expression = calc::expression(note).to_string();
+    let result = calc::evaluate(&expression)?;
+    let mut lines: Vec<String> = note.content.lines().map(str::to_string).collect();
+    let previous = lines.first().cloned().unwrap_or_default();
+    let updated = calc::note_content(&expression, result);
+    match lines.first_mut() {
+        Some(first) => *first = updated.clone(),
+        None => lines.push(updated.clone()),
+    }
+    
+    if note_manager.update_content(&note_id, lines.join("\n"))? {
+        println!("{} {} {} (was {})", 
+            glyphs().ok.green(),
+            updated,
+            format!("[{}]", note_id).yellow(),
+            previous.bright_black()
+        );
+    } else {
+        println!("{} {} {} (unchanged)", 
+            glyphs().ok.green(),
+            updated,
+            format!("[{}]", note_id).yellow()
+        );
+    }
+    Ok(())
+}
+
+fn handle_clock_command(note_manager: &mut NoteManager, command: &ClockCommands, week_start: Weekday) -> Result<()> {
+    match command {
+        ClockCommands::In { label } => {
+            let label = label.join(" ");
+            match note_manager.clock_in(&label)? {
+                ClockInResult::Started(note_id) => println!("{} Clocked in to {} {}", 
+                    glyphs().ok.green(),
+                    label.bright_cyan(),
+                    format!("[{}]", note_id).yellow()
+                ),
+                ClockInResult::AlreadyRunning(running) => println!("{} Already clocked in to {}, run {} first", 
+                    glyphs().warning.yellow(),
+                    running.bright_cyan(),
+                    "note clock out".bright_cyan()
+                ),
+            }
+        }
+        ClockCommands::Out => match note_manager.clock_out()? {
+            Some((label, seconds)) => println!("{} Clocked out of {} after {}", 
+                glyphs().ok.green(),
+                label.bright_cyan(),
+                clock::format_duration(seconds).yellow()
+            ),
+            None => println!("{} Not clocked in", glyphs().error.red()),
+        },
+        ClockCommands::Report { week } => {
+            let since = if *week {
+                let today = note_manager.now().date_naive();
+                calendar::week_start(today, week_start).and_hms_opt(0, 0, 0).and_then(|dt| dt.and_local_timezone(Local).single())
+            } else {
+                None
+            };
+            
+            let totals = clock::totals(note_manager.get_notes(), since);
+            if totals.is_empty() {
+                println!("  {}", "No tracked time".bright_black());
+                return Ok(());
+            }
+            
+            let width = totals.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+            for (label, seconds) in &totals {
+                println!("  {:<width$}  {}", label, clock::format_duration(*seconds).yellow(), width = width);
+            }
+            let total: i64 = totals.iter().map(|(_, seconds)| seconds).sum();
+            println!("  {:<width$}  {}", "Total".bright_black(), clock::format_duration(total).bold(), width = width);
+        }
+    }
+    Ok(())
+}
+
+// Nothing is written: the backup's notes are read into memory and queried
+// there, so the current notes are left as they are
+fn handle_at_command(note_manager: &NoteManager, snapshot: &str, command: &AtCommands, stats: bool) -> Result<()> {
+    let Some(backup_name) = resolve_snapshot(note_manager, snapshot)? else {
+        return Ok(());
+    };
+    let mut notes = note_manager.notes_in_backup(&backup_name)?;
+    notes.sort_by_key(|note| std::cmp::Reverse(note.timestamp));
+    notes.iter_mut().for_each(|note| note_manager.redact_note(note));
+    println!("{} {}", "Notes as of backup".bright_black(), backup_name.bright_cyan());
+    
+    let (filter, options) = match command {
+        AtCommands::List { tag, all, full } => (
+            NoteFilter {
+                tag: tag.as_deref().map(Note::normalize_tag),
+                include_archived: *all,
+                ..NoteFilter::default()
+            },
+            DisplayOptions {
+                max_lines: if *full { None } else { DisplayOptions::default().max_lines },
+                stats,
+                ..DisplayOptions::default()
+            },
+        ),
+        AtCommands::Search { query, regex, tag, full } => {
+            let matcher = Matcher::new(&query.join(" "), *regex)?;
+            (
+                NoteFilter {
+                    tag: tag.as_deref().map(Note::normalize_tag),
+                    query: Some(matcher.clone()),
+                    include_archived: true,
+                    ..NoteFilter::default()
+                },
+                DisplayOptions {
+                    max_lines: if *full { None } else { DisplayOptions::default().max_lines },
+                    highlight: Some(matcher),
+                    stats,
+                    ..DisplayOptions::default()
+                },
+            )
+        }
+        AtCommands::Show { id } => {
+            let matching: Vec<&Note> = match notes.iter().find(|note| note.id == *id) {
+                Some(note) => vec![note],
+                None => notes.iter().filter(|note| note.id.starts_with(id.as_str())).collect(),
+            };
+            match matching.as_slice() {
+                [] => print_not_found(id),
+                [note] => note_manager.display_notes(&[(*note).clone()], &DisplayOptions { max_lines: None, stats, ..DisplayOptions::default() }),
+                _ => {
+                    println!("{} Multiple notes match {} in this backup:", 
+                        glyphs().warning.yellow(), 
+                        format!("[{}]", id).yellow()
+                    );
+                    for note in matching {
+                        println!("    {} {}", 
+                            format!("[{}]", note.id).yellow(),
+                            note.content.lines().next().unwrap_or_default()
+                        );
+                    }
+                }
+            }
+            return Ok(());
+        }
+    };
+    let notes: Vec<Note> = notes.into_iter().filter(|note| filter.matches(note)).collect();
+    if notes.is_empty() {
+        println!("  {}", "No notes in this backup match".bright_black());
+    } else {
+        note_manager.display_notes(&notes, &options);
+    }
+    Ok(())
+}
+
+// A date picks the last backup taken by the end of it; anything else is
+// matched against backup names as `note backup restore` does
+fn resolve_snapshot(note_manager: &NoteManager, snapshot: &str) -> Result<Option<String>> {
+    if let Ok(day) = NaiveDate::parse_from_str(snapshot, "%Y-%m-%d") {
+        let found = note_manager.latest_backup_by(day)?;
+        if found.is_none() {
+            println!("{} No backup taken on or before {}", 
+                glyphs().error.red(),
+                snapshot.yellow()
+            );
+        }
+        return Ok(found);
+    }
+    let matches = note_manager.find_backups(snapshot)?;
+    match matches.as_slice() {
+        [name] => Ok(Some(name.clone())),
+        [] => {
+            println!("{} No backup matching {}", 
+                glyphs().error.red(),
+                snapshot.yellow()
+            );
+            Ok(None)
+        }
+        _ => {
+            println!("{} Multiple backups match {}:", 
+                glyphs().warning.yellow(),
+                snapshot.yellow()
+            );
+            for backup in matches {
+                println!("    {}", backup.bright_cyan());
+            }
+            Ok(None)
+        }
+    }
+}
+
+fn handle_backup_command(note_manager: &mut NoteManager, command: &BackupCommands, backup_config: &BackupConfig) -> Result<()> {
+    match command {
+        BackupCommands::Create => {
+            let name = note_manager.create_backup()?;
+            note_manager.prune_backups(backup_config.keep)?;
+            println!("{} Backup {} created", 
+                glyphs().ok.green(),
+                name.bright_cyan()
+            );
+        }
+        BackupCommands::List => {
+            let backups = note_manager.list_backups()?;
+            if backups.is_empty() {
+                println!("  {}", "No backups yet".bright_black());
+                return Ok(());
+            }
+            for backup in backups {
+                println!("{}  {}  {}", 
+                    backup.name.bright_cyan(),
+                    backup.created.format("%Y-%m-%d %H:%M").to_string().bright_black(),
+                    format!("{:.1} KB", backup.size as f64 / 1024.0).bright_black()
+                );
+            }
+        }
+        BackupCommands::Restore { name } => {
+            let matches = note_manager.find_backups(name)?;
+            let backup_name = match matches.as_slice() {
+                [single] => single.clone(),
+                [] => {
+                    println!("{} No backup matching {}", 
+                        glyphs().error.red(),
+                        name.yellow()
+                    );
+                    return Ok(());
+                }
+                _ => {
+                    println!("{} Multiple backups match {}:", 
+                        glyphs().warning.yellow(),
+                        name.yellow()
+                    );
+                    for backup in matches {
+                        println!("    {}", backup.bright_cyan());
+                    }
+                    return Ok(());
+                }
+            };
+            
+            // Always keep a way back from a restore, regardless of the auto setting
+            let safety = note_manager.create_backup()?;
+            let restored = note_manager.restore_backup(&backup_name)?;
+            note_manager.prune_backups(backup_config.keep.max(1))?;
+            println!("{} Restored {} {} from {}", 
+                glyphs().ok.green(),
+                restored,
+                if restored == 1 { "note" } else { "notes" },
+                backup_name.bright_cyan()
+            );
+            println!("  Previous notes saved as {}", safety.bright_cyan());
+        }
+    }
     Ok(())
 }
\ No newline at end of file
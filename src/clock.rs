@@ -0,0 +1,80 @@
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+
+use crate::note::Note;
+
+// Time tracking is built from plain notes: clocking in writes a note with
+// `clock=in`, clocking out writes one with `clock=out` that points back at it
+// through `clock-pair` and records the elapsed seconds.
+pub const CLOCK_TAG: &str = "clock";
+
+#[derive(Debug)]
+pub enum ClockInResult {
+    Started(String),
+    // Label of the session that is still running
+    AlreadyRunning(String),
+}
+
+pub fn in_meta(label: &str) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("clock".to_string(), "in".to_string()),
+        ("clock-label".to_string(), label.to_string()),
+    ])
+}
+
+pub fn out_meta(label: &str, pair_id: &str, seconds: i64) -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("clock".to_string(), "out".to_string()),
+        ("clock-label".to_string(), label.to_string()),
+        ("clock-pair".to_string(), pair_id.to_string()),
+        ("clock-secs".to_string(), seconds.to_string()),
+    ])
+}
+
+// The most recent clock-in that no clock-out refers to
+pub fn open_session(notes: &[Note]) -> Option<&Note> {
+    let closed: Vec<&String> = notes.iter()
+        .filter(|note| note.meta.get("clock").is_some_and(|v| v == "out"))
+        .filter_map(|note| note.meta.get("clock-pair"))
+        .collect();
+    
+    notes.iter()
+        .filter(|note| note.meta.get("clock").is_some_and(|v| v == "in"))
+        .filter(|note| !closed.contains(&&note.id))
+        .max_by_key(|note| note.timestamp)
+}
+
+pub fn label(note: &Note) -> String {
+    note.meta.get("clock-label").cloned().unwrap_or_default()
+}
+
+// Total tracked seconds per label for sessions that ended at or after `since`
+pub fn totals(notes: &[Note], since: Option<DateTime<Local>>) -> Vec<(String, i64)> {
+    let mut totals: BTreeMap<String, i64> = BTreeMap::new();
+    for note in notes {
+        if note.meta.get("clock").is_none_or(|v| v != "out") {
+            continue;
+        }
+        if since.is_some_and(|since| note.timestamp < since) {
+            continue;
+        }
+        let seconds: i64 = note.meta.get("clock-secs").and_then(|s| s.parse().ok()).unwrap_or(0);
+        *totals.entry(label(note)).or_insert(0) += seconds;
+    }
+    
+    let mut totals: Vec<(String, i64)> = totals.into_iter().collect();
+    totals.sort_by_key(|(_, seconds)| std::cmp::Reverse(*seconds));
+    totals
+}
+
+pub fn format_duration(seconds: i64) -> String {
+    if seconds < 60 {
+        return format!("{}s", seconds);
+    }
+    let minutes = seconds / 60;
+    match (minutes / 60, minutes % 60) {
+        (0, m) => format!("{}m", m),
+        (h, 0) => format!("{}h", h),
+        (h, m) => format!("{}h {:02}m", h, m),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,490 @@
+use anyhow::{Context, Result};
+use chrono::Weekday;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::recap;
+use crate::record;
+use crate::snap;
+use crate::speech;
+use crate::xdg;
+
+// User configuration read from ~/.config/note/config.toml ($XDG_CONFIG_HOME
+// if set). Every section is optional and falls back to the defaults below. A
+// `[profiles.<name>]` table holds the same settings and overrides them when
+// that profile is selected.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    // Directory holding notes.txt, blobs and backups (default
+    // ~/.local/share/note, or under $XDG_DATA_HOME)
+    pub data_dir: Option<String>,
+    // Tags added to every note created with `note "..."` or `note add`
+    pub default_tags: Vec<String>,
+    pub aging: AgingConfig,
+    pub autotag: AutoTagConfig,
+    pub backup: BackupConfig,
+    pub calendar: CalendarConfig,
+    pub diagram: DiagramConfig,
+    pub display: DisplayConfig,
+    pub inbox: InboxConfig,
+    pub language: LanguageConfig,
+    pub limits: LimitsConfig,
+    pub location: LocationConfig,
+    pub recap: RecapConfig,
+    pub record: RecordConfig,
+    pub redact: RedactConfig,
+    pub remote: RemoteConfig,
+    pub say: SayConfig,
+    pub search: SearchConfig,
+    pub similar: SimilarConfig,
+    pub snap: SnapConfig,
+    // Replacements for `:name:` in new and edited notes, by name
+    pub snippets: BTreeMap<String, String>,
+    pub storage: StorageConfig,
+    pub summary: SummaryConfig,
+    // Starting text for new notes of a kind, by kind name; `{text}` stands for
+    // what was typed
+    pub templates: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct AgingConfig {
+    // Notes older than this are dimmed in listings; 0 turns dimming off
+    pub dim_after_days: i64,
+    // Todos untouched for this long are flagged as stale; 0 turns it off
+    pub stale_todo_days: i64,
+}
+
+impl Default for AgingConfig {
+    fn default() -> Self {
+        Self {
+            dim_after_days: 365,
+            stale_todo_days: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct AutoTagConfig {
+    // Turn #hashtags in the text of new notes into tags
+    pub enabled: bool,
+    // Regexes for hashtags never to extract, matched against the whole tag
+    pub ignore: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct BackupConfig {
+    // Number of snapshots to retain (at least 1); older ones are deleted
+    pub keep: usize,
+    // Snapshot automatically before imports, bulk tag changes and restores
+    pub auto: bool,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            keep: 20,
+            auto: true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct CalendarConfig {
+    // First day of the week for `note cal` and `note clock report --week`,
+    // by name, e.g. "saturday" or "sun"
+    pub week_start: Weekday,
+    // Show ISO week numbers next to each week of `note cal`
+    pub week_numbers: bool,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            week_start: Weekday::Mon,
+            week_numbers: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DiagramConfig {
+    // Renderers for `note show --render`; `{input}` is replaced by the diagram
+    // source file, `{file}` by the image path and `{format}` by `format`
+    pub mermaid: String,
+    pub dot: String,
+    // Image format, and the extension of the files written
+    pub format: String,
+}
+
+impl Default for DiagramConfig {
+    fn default() -> Self {
+        Self {
+            mermaid: "mmdc -i {input} -o {file}".to_string(),
+            dot: "dot -T{format} {input} -o {file}".to_string(),
+            format: "png".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    // Plain text instead of symbols and box drawing, for screen readers
+    pub ascii: bool,
+    // Word count and reading time in listings (same as --stats)
+    pub stats: bool,
+    // Timezone dates are shown in, e.g. "UTC" or "Europe/Berlin" (same as
+    // --tz); the machine's own when unset
+    pub timezone: Option<String>,
+    // Listings highlight at least this many characters of each ID, more when
+    // needed to tell it apart, and adding a note warns once prefixes this
+    // long often match several notes; 0 only highlights what is needed
+    pub id_prefix: usize,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            ascii: false,
+            stats: false,
+            timezone: None,
+            id_prefix: 2,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct LanguageConfig {
+    // Store the detected language of new and edited notes as `lang`
+    pub detect: bool,
+    // Languages the notes are written in, e.g. ["en", "de"]; empty considers
+    // every language whatlang knows
+    pub languages: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct InboxConfig {
+    // Notes added with `note "..."` or `note add` start out untriaged
+    pub capture: bool,
+    // Leave untriaged captures out of `note list` until they are triaged
+    pub hide_untriaged: bool,
+    // Archive a checklist note once every box in it is ticked
+    pub archive_done_checklists: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    // Soft limits: exceeding them only prints a warning
+    pub max_notes: usize,
+    pub max_file_size_mb: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_notes: 10_000,
+            max_file_size_mb: 50,
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct LocationConfig {
+    // Command printing the current location for `--here`, e.g. "lat,lon place"
+    pub command: String,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RecapConfig {
+    // Desktop notifier for `note recap --notify`; `{title}` and `{body}` are
+    // replaced by the notification's text
+    pub notify_command: String,
+}
+
+impl Default for RecapConfig {
+    fn default() -> Self {
+        Self {
+            notify_command: recap::default_command().to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RecordConfig {
+    // Terminal recorder for `note record`; `{shell}` is replaced by $SHELL
+    // and `{file}` by the transcript path
+    pub command: String,
+    // Longest transcript kept, in bytes; the middle of longer ones is left
+    // out. 0 keeps everything.
+    pub max_bytes: usize,
+    // Mask the built-in secret patterns as well as the `[redact]` ones
+    pub redact_secrets: bool,
+}
+
+impl Default for RecordConfig {
+    fn default() -> Self {
+        Self {
+            command: record::default_command().to_string(),
+            max_bytes: 64 * 1024,
+            redact_secrets: true,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RedactConfig {
+    // Regular expressions whose matches are masked when notes are shown or
+    // exported (bypass with --no-redact)
+    pub patterns: Vec<String>,
+    pub mask: String,
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            mask: "[redacted]".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    // How `note remote` connects, e.g. "ssh -p 2222"; the host is added after it
+    pub ssh: String,
+    // The note binary on the remote host, for when it isn't on the PATH of
+    // non-interactive shells there, e.g. "~/.cargo/bin/note"
+    pub program: String,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            ssh: "ssh".to_string(),
+            program: "note".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SearchConfig {
+    // Snowball stemmer used by `note search --stem`
+    pub language: String,
+    // Keep an index of the words in every note so plain searches only look
+    // at notes that can match
+    pub index: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            language: "english".to_string(),
+            index: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SimilarConfig {
+    // Refuse to add a note much like an existing one unless --force is given
+    pub check: bool,
+    // Share of distinct words two notes must have in common, from 0.0 to 1.0
+    pub threshold: f64,
+}
+
+impl Default for SimilarConfig {
+    fn default() -> Self {
+        Self {
+            check: false,
+            threshold: 0.7,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SayConfig {
+    // Text-to-speech command for `note say`; `{text}` is replaced by what to say
+    pub command: String,
+}
+
+impl Default for SayConfig {
+    fn default() -> Self {
+        Self {
+            command: speech::default_command().to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SnapConfig {
+    // Screenshot tool for `note snap`; `{file}` is replaced by the image path
+    pub command: String,
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        Self {
+            command: snap::default_command().to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub order: NoteOrder,
+    // Record every change in audit.jsonl, shown by `note log`
+    pub audit_log: bool,
+    // Interactive sessions such as `note triage` write notes.txt at most once
+    // per this many seconds; 0 writes after every change
+    pub autosave_secs: u64,
+    // Lock and merge on every save, for a data_dir on a network share used
+    // from several hosts
+    pub shared: bool,
+    // In shared mode, how long a save waits for another host's lock
+    pub lock_timeout_secs: u64,
+    // Clean up pasted punctuation and whitespace in every note on save
+    pub tidy: bool,
+    // Notes longer than this keep their text in a blob rather than in
+    // notes.txt; 0 keeps every note inline
+    pub inline_limit_kb: usize,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            order: NoteOrder::default(),
+            audit_log: false,
+            autosave_secs: 2,
+            shared: false,
+            lock_timeout_secs: 10,
+            tidy: false,
+            inline_limit_kb: 64,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct SummaryConfig {
+    // What `note summary` shows, in order
+    pub sections: Vec<SummarySection>,
+    // Notes shown under Newest
+    pub recent: usize,
+    // Most notes shown under each other section
+    pub limit: usize,
+    // Notes with this tag are listed under Pinned
+    pub pinned_tag: String,
+    // Layout of each note's line, as for `note list --format`
+    pub line: String,
+}
+
+impl Default for SummaryConfig {
+    fn default() -> Self {
+        Self {
+            sections: vec![SummarySection::Pinned, SummarySection::Due, SummarySection::Inbox, SummarySection::Recent],
+            recent: 3,
+            limit: 5,
+            pinned_tag: "pinned".to_string(),
+            line: "[{id}] {first_line}".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SummarySection {
+    // Notes with the pinned tag
+    Pinned,
+    // Notes due today or overdue
+    Due,
+    // Untriaged captures
+    Inbox,
+    // The newest notes
+    Recent,
+}
+
+// Arrangement of notes in notes.txt. Listings are always newest first.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum NoteOrder {
+    // Keep the order the file already has and add new notes at the top
+    #[default]
+    Preserve,
+    NewestFirst,
+    OldestFirst,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        xdg::config_dir().map(|dir| dir.join("config.toml"))
+    }
+    
+    pub fn load(profile: Option<&str>) -> Result<Self> {
+        let path = Self::path().filter(|path| path.exists());
+        let mut table = match &path {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .context(format!("Failed to read config file: {}", path.display()))?;
+                content.parse::<toml::Table>()
+                    .context(format!("Failed to parse config file: {}", path.display()))?
+            }
+            None => toml::Table::new(),
+        };
+        
+        let profiles = table.remove("profiles");
+        if let Some(name) = profile {
+            let overrides = profiles.as_ref()
+                .and_then(|profiles| profiles.get(name))
+                .and_then(|profile| profile.as_table())
+                .with_context(|| format!("No [profiles.{}] section in the config file", name))?;
+            merge_tables(&mut table, overrides.clone());
+        }
+        
+        let location = path.map(|path| path.display().to_string()).unwrap_or_default();
+        table.try_into()
+            .context(format!("Failed to parse config file: {}", location))
+    }
+    
+    // The data directory with a leading `~` expanded
+    pub fn data_dir(&self) -> Option<PathBuf> {
+        let dir = self.data_dir.as_deref()?;
+        match dir.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+            None => Some(PathBuf::from(dir)),
+        }
+    }
+}
+
+// Profile values replace the base ones; nested sections merge key by key
+fn merge_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_section)), toml::Value::Table(section)) => merge_tables(base_section, section),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
\ No newline at end of file
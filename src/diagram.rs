@@ -0,0 +1,83 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::DiagramConfig;
+
+// `note show --render` passes fenced ```mermaid and ```dot blocks to an
+// external renderer. Each command is split on spaces, with `{input}` replaced
+// by a file holding the block, `{file}` by the image to write and `{format}`
+// by the configured image format. Images are kept in diagrams/ next to
+// notes.txt and named after what went into them, so an unchanged diagram is
+// only rendered once.
+pub const LANGUAGES: [&str; 2] = ["mermaid", "dot"];
+
+pub struct Diagram<'a> {
+    pub language: &'a str,
+    pub source: String,
+}
+
+// The diagram blocks of a note in order; an unclosed fence isn't one
+pub fn blocks(content: &str) -> Vec<Diagram<'_>> {
+    let mut diagrams = Vec::new();
+    // The language of the open fence, if any, and the lines so far
+    let mut open: Option<(&str, Vec<&str>)> = None;
+    for line in content.lines() {
+        let fence = line.trim().strip_prefix("```");
+        match (&mut open, fence) {
+            (None, Some(info)) => open = Some((info.trim(), Vec::new())),
+            (Some((language, lines)), Some("")) => {
+                if let Some(language) = LANGUAGES.iter().find(|known| known.eq_ignore_ascii_case(language)) {
+                    diagrams.push(Diagram { language, source: lines.join("\n") });
+                }
+                open = None;
+            }
+            (Some((_, lines)), _) => lines.push(line),
+            (None, None) => {}
+        }
+    }
+    diagrams
+}
+
+// The image for a diagram, rendering it unless an earlier run already has
+pub fn render(diagram: &Diagram, diagram_config: &DiagramConfig, dir: &Path) -> Result<PathBuf> {
+    let command = match diagram.language {
+        "mermaid" => &diagram_config.mermaid,
+        _ => &diagram_config.dot,
+    };
+    let key = blake3::hash(format!("{}\n{}\n{}", command, diagram_config.format, diagram.source).as_bytes()).to_hex();
+    let path = dir.join(format!("{}.{}", &key[..16], diagram_config.format));
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > 0) {
+        return Ok(path);
+    }
+    
+    fs::create_dir_all(dir).context("Failed to create diagrams directory")?;
+    let input = dir.join(format!("{}.{}", &key[..16], diagram.language));
+    fs::write(&input, &diagram.source).context("Failed to write diagram source")?;
+    let (input_arg, file_arg) = (input.to_string_lossy(), path.to_string_lossy());
+    let mut words = command.split_whitespace().map(|word| word
+        .replace("{input}", &input_arg)
+        .replace("{file}", &file_arg)
+        .replace("{format}", &diagram_config.format));
+    let program = words.next()
+        .with_context(|| format!("Set `{}` in the [diagram] config section to render {} diagrams", diagram.language, diagram.language))?;
+    let output = Command::new(&program)
+        .args(words)
+        .output()
+        .with_context(|| format!("Failed to run diagram renderer '{}'", program));
+    let _ = fs::remove_file(&input);
+    let output = output?;
+    
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        match stderr.trim() {
+            "" => bail!("{} failed ({})", program, output.status),
+            message => bail!("{} failed: {}", program, message),
+        }
+    }
+    if !path.exists() {
+        bail!("{} didn't write {}; check that the command uses {{file}}", program, path.display());
+    }
+    Ok(path)
+}
@@ -0,0 +1,166 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use colored::*;
+use std::collections::BTreeMap;
+
+use crate::glyphs::glyphs;
+use crate::note::Note;
+
+// `note journal "..."` keeps one note per day, tagged `journal`, with its
+// day in the `journal` field. Each entry is appended with the time it was
+// written, so the day reads as a timeline:
+//
+//   09:12  Standup, agreed to ship on Friday
+//   14:40  Long call with the vendor
+//          about the contract renewal
+pub const JOURNAL_TAG: &str = "journal";
+pub const JOURNAL_KEY: &str = "journal";
+
+// "HH:MM" and the two spaces after it; later lines of an entry are indented
+// as far, so they line up under its first line
+const STAMP_WIDTH: usize = 7;
+
+// `note journal --mood` starts an entry with "[mood: good]", on a scale from
+// 1 (awful) to 5 (great), which `note stats --mood` charts by day
+pub const MOODS: [&str; 5] = ["awful", "bad", "okay", "good", "great"];
+
+// Width of the bar for the best possible day in the mood chart
+const CHART_WIDTH: usize = 20;
+
+pub fn day_key(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+pub fn is_journal_for(note: &Note, key: &str) -> bool {
+    note.has_tag(JOURNAL_TAG) && note.meta.get(JOURNAL_KEY).is_some_and(|day| day == key)
+}
+
+// The first line of a new day's journal
+pub fn title(day: NaiveDate) -> String {
+    format!("Journal, {}", day.format("%A %b %-d %Y"))
+}
+
+pub fn entry(time: &DateTime<FixedOffset>, mood: Option<u8>, text: &str) -> String {
+    let indent = " ".repeat(STAMP_WIDTH);
+    let lines: Vec<&str> = text.trim().lines().map(str::trim_end).collect();
+    let text = lines.join(&format!("\n{}", indent));
+    match mood {
+        Some(mood) if text.is_empty() => format!("{}  {}", time.format("%H:%M"), mood_label(mood)),
+        Some(mood) => format!("{}  {} {}", time.format("%H:%M"), mood_label(mood), text),
+        None => format!("{}  {}", time.format("%H:%M"), text),
+    }
+}
+
+// A mood as a number from 1 to 5, one of MOODS or a few other words for
+// them, or a face emoji
+pub fn parse_mood(value: &str) -> Option<u8> {
+    let value = value.trim().trim_end_matches('\u{FE0F}').to_lowercase();
+    if let Ok(number) = value.parse::<u8>() {
+        return (1..=5).contains(&number).then_some(number);
+    }
+    if let Some(index) = MOODS.iter().position(|mood| *mood == value) {
+        return Some(index as u8 + 1);
+    }
+    Some(match value.as_str() {
+        "terrible" | "😭" | "😫" | "😩" | "😡" | "😠" | "😖" => 1,
+        "sad" | "low" | "😞" | "😢" | "😕" | "🙁" | "☹" | "😟" | "😔" => 2,
+        "ok" | "meh" | "fine" | "neutral" | "😐" | "😑" | "😶" => 3,
+        "happy" | "🙂" | "😊" | "😌" => 4,
+        "amazing" | "excellent" | "😀" | "😃" | "😄" | "😁" | "🤩" | "😍" | "🥳" => 5,
+        _ => return None,
+    })
+}
+
+fn mood_label(mood: u8) -> String {
+    format!("[mood: {}]", MOODS[usize::from(mood.clamp(1, 5)) - 1])
+}
+
+// The moods recorded in a journal's entries, in order
+pub fn moods(content: &str) -> Vec<u8> {
+    content.lines()
+        .filter_map(|line| split_stamp(line).and_then(|(_, rest)| split_mood(rest)))
+        .map(|(mood, _)| mood)
+        .collect()
+}
+
+fn split_mood(text: &str) -> Option<(u8, &str)> {
+    let (word, rest) = text.strip_prefix("[mood: ")?.split_once(']')?;
+    let index = MOODS.iter().position(|mood| *mood == word)?;
+    Some((index as u8 + 1, rest))
+}
+
+// Each day's moods, from the journals written on or after `since`
+pub fn moods_by_day(notes: &[Note], since: NaiveDate) -> BTreeMap<NaiveDate, Vec<u8>> {
+    notes.iter()
+        .filter(|note| note.has_tag(JOURNAL_TAG))
+        .filter_map(|note| {
+            let day = NaiveDate::parse_from_str(note.meta.get(JOURNAL_KEY)?, "%Y-%m-%d").ok()?;
+            let moods = moods(&note.content);
+            (day >= since && !moods.is_empty()).then_some((day, moods))
+        })
+        .collect()
+}
+
+// One line per day with a bar as long as its average mood, oldest first
+pub fn mood_chart(days: &BTreeMap<NaiveDate, Vec<u8>>) -> Vec<String> {
+    days.iter()
+        .map(|(day, moods)| {
+            let average = average(moods);
+            let filled = (average * CHART_WIDTH as f64 / 5.0).round() as usize;
+            let bar = mood_color(&glyphs().bar.repeat(filled), average);
+            format!("{}  {}{}  {:.1} {}",
+                day.format("%a %b %e").to_string().bright_black(),
+                bar,
+                " ".repeat(CHART_WIDTH - filled.min(CHART_WIDTH)),
+                average,
+                mood_color(mood_name(average), average)
+            )
+        })
+        .collect()
+}
+
+pub fn average(moods: &[u8]) -> f64 {
+    moods.iter().map(|mood| f64::from(*mood)).sum::<f64>() / moods.len().max(1) as f64
+}
+
+pub fn mood_name(average: f64) -> &'static str {
+    MOODS[(average.round() as usize).clamp(1, 5) - 1]
+}
+
+fn mood_color(text: &str, average: f64) -> ColoredString {
+    match average.round() as u8 {
+        0..=1 => text.red(),
+        2 => text.bright_red(),
+        3 => text.yellow(),
+        4 => text.bright_green(),
+        _ => text.green(),
+    }
+}
+
+pub fn append(content: &str, entry: &str) -> String {
+    if content.lines().count() == 1 {
+        format!("{}\n\n{}", content.trim_end(), entry)
+    } else {
+        format!("{}\n{}", content.trim_end(), entry)
+    }
+}
+
+// A journal's entries with their times dimmed, for `note journal` without
+// text; other lines, such as hand-written ones, are shown as they are
+pub fn render(content: &str) -> Vec<String> {
+    content.lines()
+        .map(|line| match split_stamp(line) {
+            Some((stamp, rest)) => match split_mood(rest) {
+                Some((mood, text)) => format!("{}  {}{}", stamp.bright_black(), mood_color(&mood_label(mood), f64::from(mood)), text),
+                None => format!("{}  {}", stamp.bright_black(), rest),
+            },
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+fn split_stamp(line: &str) -> Option<(&str, &str)> {
+    let (stamp, rest) = line.split_once("  ")?;
+    let (hours, minutes) = stamp.split_once(':')?;
+    let digits = |part: &str| part.len() == 2 && part.chars().all(|c| c.is_ascii_digit());
+    (digits(hours) && digits(minutes)).then_some((stamp, rest))
+}
@@ -0,0 +1,167 @@
+use colored::*;
+
+// Word-level differences between two versions of a note, in the style of
+// `git diff --word-diff`. Whitespace runs are tokens of their own so the
+// original line breaks and spacing come through unchanged.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Change<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+// The LCS table needs a cell for every pair of tokens between the common
+// start and end, so past this many the diff compares whole lines instead, and
+// lines that still don't fit are shown as one block replaced
+const MAX_CELLS: usize = 4_000_000;
+
+// Longest common subsequence of the two token lists, walked back into changes
+pub fn word_diff<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    let (old_words, new_words) = (tokens(old), tokens(new));
+    if fits(&old_words, &new_words) {
+        return group(lcs(&old_words, &new_words));
+    }
+    
+    // Split the lines back into words so the counts and grouping still see words
+    let (old_lines, new_lines): (Vec<&str>, Vec<&str>) = (old.split_inclusive('\n').collect(), new.split_inclusive('\n').collect());
+    let changes = lcs(&old_lines, &new_lines).into_iter().flat_map(|change| match change {
+        Change::Same(line) => tokens(line).into_iter().map(Change::Same).collect::<Vec<_>>(),
+        Change::Removed(line) => tokens(line).into_iter().map(Change::Removed).collect(),
+        Change::Added(line) => tokens(line).into_iter().map(Change::Added).collect(),
+    }).collect();
+    group(changes)
+}
+
+// Lengths of the common start and end, which need no table
+fn common_ends(old: &[&str], new: &[&str]) -> (usize, usize) {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    (prefix, suffix)
+}
+
+fn fits(old: &[&str], new: &[&str]) -> bool {
+    let (prefix, suffix) = common_ends(old, new);
+    (old.len() - prefix - suffix + 1).saturating_mul(new.len() - prefix - suffix + 1) <= MAX_CELLS
+}
+
+fn lcs<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Change<'a>> {
+    let (prefix, suffix) = common_ends(old, new);
+    let (middle_old, middle_new) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+    let mut changes: Vec<Change> = old[..prefix].iter().map(|token| Change::Same(token)).collect();
+    if fits(middle_old, middle_new) {
+        let mut lengths = vec![vec![0usize; middle_new.len() + 1]; middle_old.len() + 1];
+        for i in (0..middle_old.len()).rev() {
+            for j in (0..middle_new.len()).rev() {
+                lengths[i][j] = if middle_old[i] == middle_new[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        
+        let (mut i, mut j) = (0, 0);
+        while i < middle_old.len() && j < middle_new.len() {
+            if middle_old[i] == middle_new[j] {
+                changes.push(Change::Same(middle_old[i]));
+                i += 1;
+                j += 1;
+            } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+                changes.push(Change::Removed(middle_old[i]));
+                i += 1;
+            } else {
+                changes.push(Change::Added(middle_new[j]));
+                j += 1;
+            }
+        }
+        changes.extend(middle_old[i..].iter().map(|token| Change::Removed(token)));
+        changes.extend(middle_new[j..].iter().map(|token| Change::Added(token)));
+    } else {
+        changes.extend(middle_old.iter().map(|token| Change::Removed(token)));
+        changes.extend(middle_new.iter().map(|token| Change::Added(token)));
+    }
+    changes.extend(old[old.len() - suffix..].iter().map(|token| Change::Same(token)));
+    changes
+}
+
+// The LCS happily matches the single spaces between replaced words, which
+// splits one edit into several. Spaces inside an edit are folded into it and
+// each edit is shown as everything removed followed by everything added.
+fn group(changes: Vec<Change>) -> Vec<Change> {
+    let is_edit = |change: Option<&Change>| change.is_some_and(|change| !matches!(change, Change::Same(_)));
+    let mut grouped = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for (index, &change) in changes.iter().enumerate() {
+        match change {
+            Change::Same(text) if text.trim().is_empty()
+                && index > 0 && is_edit(changes.get(index - 1)) && is_edit(changes.get(index + 1)) => {
+                removed.push(Change::Removed(text));
+                added.push(Change::Added(text));
+            }
+            Change::Same(_) => {
+                grouped.append(&mut removed);
+                grouped.append(&mut added);
+                grouped.push(change);
+            }
+            Change::Removed(_) => removed.push(change),
+            Change::Added(_) => added.push(change),
+        }
+    }
+    grouped.append(&mut removed);
+    grouped.append(&mut added);
+    grouped
+}
+
+// Removed words in red and added ones in green, or marked `[-old-]{+new+}`
+// when colors are off
+pub fn render(changes: &[Change]) -> String {
+    let color = colored::control::SHOULD_COLORIZE.should_colorize();
+    let mut rendered = String::new();
+    let mut index = 0;
+    while index < changes.len() {
+        // Group a run of the same kind, so "[-two words-]" is marked once
+        let mut end = index + 1;
+        while end < changes.len() && same_kind(changes[end], changes[index]) {
+            end += 1;
+        }
+        let text: String = changes[index..end].iter().map(|change| match change {
+            Change::Same(text) | Change::Removed(text) | Change::Added(text) => *text,
+        }).collect();
+        match changes[index] {
+            Change::Same(_) => rendered.push_str(&text),
+            Change::Removed(_) if color => rendered.push_str(&text.red().strikethrough().to_string()),
+            Change::Added(_) if color => rendered.push_str(&text.green().underline().to_string()),
+            Change::Removed(_) => rendered.push_str(&format!("[-{}-]", text)),
+            Change::Added(_) => rendered.push_str(&format!("{{+{}+}}", text)),
+        }
+        index = end;
+    }
+    rendered
+}
+
+// Words added and removed, not counting whitespace
+pub fn counts(changes: &[Change]) -> (usize, usize) {
+    let is_word = |text: &str| !text.trim().is_empty();
+    let added = changes.iter().filter(|change| matches!(change, Change::Added(text) if is_word(text))).count();
+    let removed = changes.iter().filter(|change| matches!(change, Change::Removed(text) if is_word(text))).count();
+    (added, removed)
+}
+
+fn same_kind(a: Change, b: Change) -> bool {
+    std::mem::discriminant(&a) == std::mem::discriminant(&b)
+}
+
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let space = c.is_whitespace();
+        if chars.peek().is_none_or(|(_, next)| next.is_whitespace() != space) {
+            let end = chars.peek().map_or(text.len(), |(index, _)| *index);
+            tokens.push(&text[start..end]);
+            start = end;
+        }
+    }
+    tokens
+}
@@ -0,0 +1,78 @@
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+
+use crate::calendar;
+use crate::note::Note;
+use crate::timezone;
+
+// `note digest` writes the notes of a stretch of days as one document, oldest
+// first under a heading for each day, to paste into a status email or a
+// weekly review.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum DigestFormat {
+    /// Markdown headings for the range and each day
+    Markdown,
+    /// Plain text with underlined headings
+    Text,
+}
+
+// The first day a `--since` value covers: a date, "today", "yesterday",
+// "this-week", "last-week" (the past seven days), "this-month", "last-month"
+// (since this day last month), or a count of days or weeks such as 3d or 2w
+pub fn parse_since(value: &str, today: NaiveDate, week_start: Weekday) -> Option<NaiveDate> {
+    let value = value.trim().to_lowercase();
+    match value.as_str() {
+        "today" => return Some(today),
+        "yesterday" => return today.checked_sub_days(Days::new(1)),
+        "this-week" => return Some(calendar::week_start(today, week_start)),
+        "last-week" => return today.checked_sub_days(Days::new(6)),
+        "this-month" => return today.with_day(1),
+        "last-month" => return today.checked_sub_months(Months::new(1)),
+        _ => {}
+    }
+    if let Some(days) = value.strip_suffix('d').and_then(|count| count.parse::<u64>().ok()) {
+        return today.checked_sub_days(Days::new(days));
+    }
+    if let Some(weeks) = value.strip_suffix('w').and_then(|count| count.parse::<u64>().ok()) {
+        return today.checked_sub_days(Days::new(weeks * 7));
+    }
+    NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok()
+}
+
+// The notes, oldest first, grouped by the day they were written on
+pub fn render(notes: &[&Note], since: NaiveDate, today: NaiveDate, format: DigestFormat) -> String {
+    let range = if since == today {
+        format!("Notes from {}", today.format("%b %-d %Y"))
+    } else {
+        format!("Notes from {} to {}", since.format("%b %-d"), today.format("%b %-d %Y"))
+    };
+    let mut document = vec![heading(&range, 1, format)];
+    let mut current_day = None;
+    for note in notes {
+        let day = timezone::display(&note.timestamp).date_naive();
+        if current_day != Some(day) {
+            current_day = Some(day);
+            document.push(heading(&day.format("%A, %b %-d").to_string(), 2, format));
+        }
+        let mut text = note.content.trim_end().to_string();
+        let tags = note.tags();
+        if !tags.is_empty() {
+            let tags: Vec<String> = tags.iter().map(|tag| format!("#{}", tag)).collect();
+            text.push_str(&match format {
+                DigestFormat::Markdown => format!("\n\n_{}_", tags.join(" ")),
+                DigestFormat::Text => format!("\n{}", tags.join(" ")),
+            });
+        }
+        document.push(text);
+    }
+    document.join("\n\n") + "\n"
+}
+
+fn heading(text: &str, level: usize, format: DigestFormat) -> String {
+    match format {
+        DigestFormat::Markdown => format!("{} {}", "#".repeat(level), text),
+        DigestFormat::Text => {
+            let underline = if level == 1 { "=" } else { "-" };
+            format!("{}\n{}", text, underline.repeat(text.chars().count()))
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::process::Command;
+
+use crate::encoding;
+
+// Opens text in $VISUAL or $EDITOR (vi when neither is set) through a temporary
+// file and returns what was saved
+pub fn edit(text: &str, name: &str) -> Result<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // Editors given with arguments, like "code --wait", are split on spaces
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("The editor command is empty")?;
+    
+    let path = std::env::temp_dir().join(format!("note-{}-{}.txt", name, std::process::id()));
+    fs::write(&path, format!("{}\n", text)).context("Failed to write temporary file")?;
+    
+    let status = Command::new(program).args(words).arg(&path).status();
+    let edited = encoding::read(&path);
+    fs::remove_file(&path).ok();
+    
+    let status = status.with_context(|| format!("Failed to start editor '{}'", editor))?;
+    if !status.success() {
+        bail!("Editor '{}' exited with {}", editor, status);
+    }
+    let edited = edited.context("Failed to read back the edited note")?;
+    Ok(edited.trim_end_matches(['\n', '\r']).to_string())
+}
+
+// Both versions of a note that changed while it was being edited, marked the
+// way git marks merge conflicts, for the user to resolve
+pub fn conflict_text(mine: &str, theirs: &str) -> String {
+    format!("<<<<<<< your edit\n{}\n=======\n{}\n>>>>>>> changed meanwhile", mine, theirs)
+}
+
+pub fn has_conflict_markers(text: &str) -> bool {
+    text.lines().any(|line| line.starts_with("<<<<<<< ") || line == "=======" || line.starts_with(">>>>>>> "))
+}
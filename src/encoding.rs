@@ -0,0 +1,44 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::fs;
+use std::path::Path;
+
+use crate::glyphs::glyphs;
+
+// Text from files written by other programs, such as Notepad, as the parser
+// expects it: without a UTF-8 byte order mark and with "\n" line endings in
+// place of "\r\n" and old Mac "\r". Bytes that aren't UTF-8, as in a file
+// saved as Windows-1252, become U+FFFD rather than failing the read.
+const BOM: char = '\u{feff}';
+
+// The decoded text, and whether any bytes had to be replaced
+pub fn decode(bytes: &[u8]) -> (String, bool) {
+    let text = String::from_utf8_lossy(bytes);
+    let lossy = matches!(text, std::borrow::Cow::Owned(_));
+    let text = text.strip_prefix(BOM).unwrap_or(&text);
+    let text = if text.contains('\r') {
+        text.replace("\r\n", "\n").replace('\r', "\n")
+    } else {
+        text.to_string()
+    };
+    (text, lossy)
+}
+
+// Reads and decodes a file, warning once if it wasn't all UTF-8. The notes
+// are saved back normalized the next time they change.
+pub fn read(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    Ok(decode_with_warning(&bytes, &path.display().to_string()))
+}
+
+pub fn decode_with_warning(bytes: &[u8], source: &str) -> String {
+    let (text, lossy) = decode(bytes);
+    if lossy {
+        eprintln!("{} {} isn't valid UTF-8; unreadable characters were replaced with {}",
+            glyphs().warning.yellow(),
+            source,
+            char::REPLACEMENT_CHARACTER
+        );
+    }
+    text
+}
@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::encoding;
+
+// Two-way mirror of the notes as one `<id>-<slug>.txt` file each in a
+// folder kept in sync by Dropbox, Drive or similar. folder-sync.txt next to
+// notes.txt remembers the folder and the content hash of every note as of
+// the last sync, which tells which side changed:
+//
+//   %note-folder <folder path>
+//   <note id> <content hash>
+const HEADER: &str = "%note-folder";
+
+pub struct SyncState {
+    pub folder: PathBuf,
+    pub base: BTreeMap<String, String>,
+}
+
+impl SyncState {
+    // Syncing with a different folder than last time starts from scratch
+    pub fn load(path: &Path, folder: &Path) -> Self {
+        let mut state = Self { folder: folder.to_path_buf(), base: BTreeMap::new() };
+        let Ok(content) = fs::read_to_string(path) else {
+            return state;
+        };
+        let mut lines = content.lines();
+        let same_folder = lines.next()
+            .and_then(|header| header.strip_prefix(HEADER))
+            .is_some_and(|previous| Path::new(previous.trim()) == folder);
+        if same_folder {
+            state.base = lines
+                .filter_map(|line| line.split_once(' '))
+                .map(|(id, hash)| (id.to_string(), hash.to_string()))
+                .collect();
+        }
+        state
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut content = format!("{} {}\n", HEADER, self.folder.display());
+        for (id, hash) in &self.base {
+            content.push_str(&format!("{} {}\n", id, hash));
+        }
+        fs::write(path, content).context("Failed to save folder sync state")
+    }
+}
+
+pub struct FolderFile {
+    pub path: PathBuf,
+    pub name: String,
+    // Without the trailing newline editors add
+    pub content: String,
+}
+
+impl FolderFile {
+    // The note ID a file name claims: everything before the first '-'
+    pub fn id(&self) -> &str {
+        let stem = self.name.strip_suffix(".txt").unwrap_or(&self.name);
+        stem.split('-').next().unwrap_or(stem)
+    }
+}
+
+#[derive(Default)]
+pub struct SyncReport {
+    // Files written because the note is new or changed in the store
+    pub written: usize,
+    // Files renamed after their note's first line changed
+    pub renamed: usize,
+    // Notes changed or added from files
+    pub updated: usize,
+    pub added: usize,
+    pub removed_notes: usize,
+    pub removed_files: usize,
+    // Notes changed on both sides, saved with conflict markers
+    pub conflicts: Vec<String>,
+    // What happened to each note by ID, or by file name for files that
+    // became new notes, in the words of the counts above
+    pub changes: Vec<(String, &'static str)>,
+}
+
+// The .txt files directly in `folder`, shortest name first so an original
+// sorts before the "conflicted copy" a sync client makes of it. Hidden files
+// are skipped since sync clients and editors keep their temporary files there.
+pub fn read_files(folder: &Path) -> Result<Vec<FolderFile>> {
+    let mut files = Vec::new();
+    let entries = fs::read_dir(folder)
+        .with_context(|| format!("Failed to read {}", folder.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        if !path.is_file() || name.starts_with('.') || !name.ends_with(".txt") {
+            continue;
+        }
+        let text = encoding::read(&path)?;
+        let content = text.strip_suffix('\n').unwrap_or(&text).to_string();
+        files.push(FolderFile { path, name, content });
+    }
+    files.sort_by(|a, b| a.name.len().cmp(&b.name.len()).then_with(|| a.name.cmp(&b.name)));
+    Ok(files)
+}
+
+pub fn write_file(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, format!("{}\n", content))
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// Both versions of a note edited on both sides since the last sync
+pub fn conflict_text(note: &str, file: &str, file_name: &str) -> String {
+    format!("<<<<<<< notes\n{}\n=======\n{}\n>>>>>>> {}", note, file, file_name)
+}
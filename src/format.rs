@@ -0,0 +1,303 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use std::path::Path;
+
+use crate::encoding;
+use crate::note::Note;
+use crate::notion;
+use crate::parser::{FileFormat, NoteParser, SplitBy};
+use crate::taskwarrior;
+
+// The formats `note import` reads and `note output` and the taskwarrior
+// export write, all found by name in FORMATS. A new format is one more
+// implementation registered there: the manager only ever sees the notes a
+// format reads, and decides their IDs and whether they are duplicates itself.
+pub trait Format: Sync {
+    // What --format calls it
+    fn name(&self) -> &'static str;
+    
+    // One line for --help
+    fn about(&self) -> &'static str;
+    
+    fn reads(&self) -> bool {
+        false
+    }
+    
+    fn writes(&self) -> bool {
+        false
+    }
+    
+    // Every note in a file or directory, and what was left out and why, in
+    // the order they appear
+    fn read(&self, _path: &Path, _options: &ReadOptions) -> Result<Vec<Item>> {
+        bail!("{} files can't be imported", self.name())
+    }
+    
+    // The notes as one document, ending in a newline
+    fn write(&self, _notes: &[&Note]) -> Result<String> {
+        bail!("note can't write {} files", self.name())
+    }
+}
+
+pub struct ReadOptions {
+    // How plain text is split into notes
+    pub split_by: SplitBy,
+    // When notes that don't say when they were written were
+    pub now: DateTime<Local>,
+}
+
+pub enum Item {
+    Note(Incoming),
+    Skipped {
+        line: Option<usize>,
+        source: Option<String>,
+        reason: String,
+    },
+}
+
+pub struct Incoming {
+    pub note: Note,
+    // Whether the note's ID came from the file and should be kept when free;
+    // otherwise the manager gives it a new one
+    pub keeps_id: bool,
+    // Line the note starts on, for text files
+    pub line: Option<usize>,
+    // The Notion page or Taskwarrior task UUID it came from
+    pub source: Option<String>,
+}
+
+impl Item {
+    // A note with no ID of its own yet
+    fn fresh(note: Note, line: Option<usize>, source: Option<String>) -> Self {
+        Item::Note(Incoming { note, keeps_id: false, line, source })
+    }
+}
+
+pub static FORMATS: &[&dyn Format] = &[&Native, &NativeV1, &Plain, &Markdown, &Notion, &Taskwarrior];
+
+pub fn find(name: &str) -> Option<&'static dyn Format> {
+    FORMATS.iter().copied().find(|format| format.name() == name)
+}
+
+// --format values for `note import`
+pub fn readers() -> impl TypedValueParser<Value = &'static dyn Format> {
+    parser(|format| format.reads())
+}
+
+// --format values for `note output`
+pub fn writers() -> impl TypedValueParser<Value = &'static dyn Format> {
+    parser(|format| format.writes())
+}
+
+fn parser(supports: fn(&'static dyn Format) -> bool) -> impl TypedValueParser<Value = &'static dyn Format> {
+    let values = FORMATS.iter()
+        .filter(|format| supports(**format))
+        .map(|format| PossibleValue::new(format.name()).help(format.about()));
+    PossibleValuesParser::new(values).map(|name| find(&name).expect("only registered names are accepted"))
+}
+
+struct Native;
+
+impl Format for Native {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+    
+    fn about(&self) -> &'static str {
+        "Notes with `#id date` headers, as written by `note output`"
+    }
+    
+    fn reads(&self) -> bool {
+        true
+    }
+    
+    fn writes(&self) -> bool {
+        true
+    }
+    
+    // Either version of the file format, going by the marker line
+    fn read(&self, path: &Path, options: &ReadOptions) -> Result<Vec<Item>> {
+        let content = encoding::read(path)?;
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let notes = NoteParser::parse_notes_from_text(&content, options.now)
+            .context("Failed to parse imported notes")?;
+        let (lines, dropped) = NoteParser::header_lines(&content, &notes);
+        let mut items: Vec<Item> = notes.into_iter().enumerate()
+            .map(|(index, note)| Item::Note(Incoming { note, keeps_id: true, line: lines.get(index).copied(), source: None }))
+            .collect();
+        items.extend(dropped.into_iter().map(|(line, reason)| Item::Skipped {
+            line: Some(line),
+            source: None,
+            reason: reason.to_string(),
+        }));
+        Ok(items)
+    }
+    
+    fn write(&self, notes: &[&Note]) -> Result<String> {
+        Ok(NoteParser::format_notes(notes, FileFormat::V2))
+    }
+}
+
+// `native` reads this too
+struct NativeV1;
+
+impl Format for NativeV1 {
+    fn name(&self) -> &'static str {
+        "native-v1"
+    }
+    
+    fn about(&self) -> &'static str {
+        "The file format before `note migrate`, for older versions of note"
+    }
+    
+    fn writes(&self) -> bool {
+        true
+    }
+    
+    fn write(&self, notes: &[&Note]) -> Result<String> {
+        Ok(NoteParser::format_notes(notes, FileFormat::V1))
+    }
+}
+
+struct Plain;
+
+impl Format for Plain {
+    fn name(&self) -> &'static str {
+        "plain"
+    }
+    
+    fn about(&self) -> &'static str {
+        "Arbitrary text without headers"
+    }
+    
+    fn reads(&self) -> bool {
+        true
+    }
+    
+    fn writes(&self) -> bool {
+        true
+    }
+    
+    fn read(&self, path: &Path, options: &ReadOptions) -> Result<Vec<Item>> {
+        let content = encoding::read(path)?;
+        Ok(NoteParser::split_plain_text(&content, options.split_by).into_iter()
+            .map(|(line, chunk)| Item::fresh(Note::new(chunk, &[], options.now), Some(line), None))
+            .collect())
+    }
+    
+    // Just the text, a blank line between notes
+    fn write(&self, notes: &[&Note]) -> Result<String> {
+        let texts: Vec<&str> = notes.iter().map(|note| note.content.trim_end()).collect();
+        Ok(format!("{}\n", texts.join("\n\n")))
+    }
+}
+
+struct Markdown;
+
+impl Format for Markdown {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+    
+    fn about(&self) -> &'static str {
+        "Each note as Markdown after its YAML front matter"
+    }
+    
+    fn writes(&self) -> bool {
+        true
+    }
+    
+    fn write(&self, notes: &[&Note]) -> Result<String> {
+        let documents: Vec<String> = notes.iter()
+            .map(|note| format!("{}\n{}\n", NoteParser::format_front_matter(note), note.content))
+            .collect();
+        Ok(documents.join("\n"))
+    }
+}
+
+struct Notion;
+
+impl Format for Notion {
+    fn name(&self) -> &'static str {
+        "notion"
+    }
+    
+    fn about(&self) -> &'static str {
+        "A Notion \"Markdown & CSV\" export (zip file or extracted folder)"
+    }
+    
+    fn reads(&self) -> bool {
+        true
+    }
+    
+    // Pages nested under another are tagged with its title
+    fn read(&self, path: &Path, options: &ReadOptions) -> Result<Vec<Item>> {
+        Ok(notion::read_export(path)?.into_iter()
+            .map(|page| {
+                let tags: Vec<String> = page.parent.iter()
+                    .map(|parent| Note::normalize_tag(&parent.to_lowercase()))
+                    .collect();
+                let mut note = Note::new(page.content, &[], options.now);
+                note.set_tags(&tags);
+                let source = match &page.parent {
+                    Some(parent) => format!("{}/{}", parent, page.title),
+                    None => page.title,
+                };
+                Item::fresh(note, None, Some(source))
+            })
+            .collect())
+    }
+}
+
+struct Taskwarrior;
+
+impl Format for Taskwarrior {
+    fn name(&self) -> &'static str {
+        "taskwarrior"
+    }
+    
+    fn about(&self) -> &'static str {
+        "JSON from `task export`, or for `task import`; pending tasks become todo notes"
+    }
+    
+    fn reads(&self) -> bool {
+        true
+    }
+    
+    fn writes(&self) -> bool {
+        true
+    }
+    
+    fn read(&self, path: &Path, options: &ReadOptions) -> Result<Vec<Item>> {
+        let content = encoding::read(path)?;
+        let mut items = Vec::new();
+        for task in taskwarrior::parse_export(&content)? {
+            if !task.is_open() {
+                items.push(Item::Skipped { line: None, source: Some(task.uuid), reason: format!("{} task", task.status) });
+                continue;
+            }
+            let mut note = Note::new(task.content, &[], options.now);
+            if let Some(entry) = task.entry {
+                note.timestamp = entry;
+            }
+            note.set_tags(&task.tags);
+            if let Some(due) = task.due {
+                note.meta.insert("due".to_string(), due.format("%Y-%m-%d").to_string());
+            }
+            if let Some(priority) = task.priority {
+                note.meta.insert("priority".to_string(), priority);
+            }
+            items.push(Item::fresh(note, None, Some(task.uuid)));
+        }
+        Ok(items)
+    }
+    
+    // The todo notes and checkboxes among the notes, as tasks
+    fn write(&self, notes: &[&Note]) -> Result<String> {
+        let tasks: Vec<taskwarrior::Task> = notes.iter().flat_map(|note| taskwarrior::tasks_for_note(note)).collect();
+        Ok(format!("{}\n", serde_json::to_string_pretty(&tasks)?))
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+// A commit read from the repository in the current directory with the `git`
+// command line tool
+pub struct Commit {
+    pub sha: String,
+    pub author: String,
+    pub message: String,
+    pub files: Vec<String>,
+    // Name of the repository's top-level directory
+    pub repo: String,
+}
+
+pub fn read_commit(rev: &str) -> Result<Commit> {
+    let toplevel = git(&["rev-parse", "--show-toplevel"])?;
+    let repo = Path::new(toplevel.trim())
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    
+    // Ends with `--` so a revision is never mistaken for a path
+    let header = git(&["show", "--no-patch", "--format=%H%n%an <%ae>%n%B", rev, "--"])?;
+    let mut lines = header.lines();
+    let sha = lines.next().unwrap_or_default().to_string();
+    let author = lines.next().unwrap_or_default().to_string();
+    let message = lines.collect::<Vec<_>>().join("\n").trim().to_string();
+    
+    let files = git(&["diff-tree", "--no-commit-id", "--name-only", "-r", "--root", &sha])?
+        .lines()
+        .map(str::to_string)
+        .collect();
+    
+    Ok(Commit { sha, author, message, files, repo })
+}
+
+pub fn note_content(commit: &Commit) -> String {
+    let mut content = format!("{}\n\ncommit {}\nAuthor: {}", commit.message, commit.sha, commit.author);
+    if !commit.files.is_empty() {
+        content.push_str("\nFiles:");
+        for file in &commit.files {
+            content.push_str(&format!("\n- {}", file));
+        }
+    }
+    content
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .context("Failed to run git; is it installed?")?;
+    if !output.status.success() {
+        bail!("git {}: {}", args[0], String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
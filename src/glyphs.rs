@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Symbols used in terminal output. The ASCII set spells them out so screen
+// readers don't announce long glyph names on every line.
+pub struct Glyphs {
+    pub ok: &'static str,
+    pub error: &'static str,
+    pub warning: &'static str,
+    pub empty: &'static str,
+    pub attachment: &'static str,
+    pub more: &'static str,
+    pub prompt: &'static str,
+    pub separator: &'static str,
+    // Chart bars, such as the mood chart's
+    pub bar: &'static str,
+    // Note kinds
+    pub todo: &'static str,
+    pub idea: &'static str,
+    pub meeting: &'static str,
+    pub snippet: &'static str,
+}
+
+const UNICODE_GLYPHS: Glyphs = Glyphs {
+    ok: "✓",
+    error: "✗",
+    warning: "⚠",
+    empty: "✨",
+    attachment: "📎",
+    more: "…",
+    prompt: "›",
+    separator: "────────────────────────────────────",
+    bar: "█",
+    todo: "☐",
+    idea: "💡",
+    meeting: "👥",
+    snippet: "⌨",
+};
+
+const ASCII_GLYPHS: Glyphs = Glyphs {
+    ok: "OK:",
+    error: "Error:",
+    warning: "Warning:",
+    empty: "",
+    attachment: "Attachment:",
+    more: "...",
+    prompt: ">",
+    separator: "---",
+    bar: "#",
+    todo: "todo",
+    idea: "idea",
+    meeting: "meeting",
+    snippet: "snippet",
+};
+
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+pub fn set_ascii(enabled: bool) {
+    ASCII.store(enabled, Ordering::Relaxed);
+}
+
+pub fn glyphs() -> &'static Glyphs {
+    if ASCII.load(Ordering::Relaxed) {
+        &ASCII_GLYPHS
+    } else {
+        &UNICODE_GLYPHS
+    }
+}
\ No newline at end of file
@@ -0,0 +1,134 @@
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::note::Note;
+use crate::slug;
+use crate::timezone;
+
+// A static, read-only site: index.html lists the notes with a filter box
+// that searches as you type, and each note gets a page under notes/ named
+// after its first line, listed in manifest.tsv
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:46rem;margin:2rem auto;padding:0 1rem;color:#222}\
+a{color:#0b5cad;text-decoration:none}a:hover{text-decoration:underline}\
+input{width:100%;padding:.5rem;font-size:1rem;box-sizing:border-box;margin-bottom:1rem}\
+ul{list-style:none;padding:0}li{padding:.5rem 0;border-bottom:1px solid #eee}\
+.meta{color:#777;font-size:.85rem}.tag{color:#2a7f62;margin-left:.4rem}\
+.content{white-space:pre-wrap;line-height:1.5}";
+
+const SEARCH_SCRIPT: &str = "const q=document.getElementById('q');\
+q.addEventListener('input',()=>{const t=q.value.toLowerCase();\
+for(const li of document.querySelectorAll('li[data-text]')){li.hidden=!li.dataset.text.includes(t);}});";
+
+// Writes the site to `dir`; returns the number of note pages written
+pub fn write_site(notes: &[Note], dir: &Path, overwrite: bool) -> Result<usize> {
+    let index_path = dir.join("index.html");
+    if index_path.exists() && !overwrite {
+        bail!("{} already exists (use --force to replace it)", index_path.display());
+    }
+    let notes_dir = dir.join("notes");
+    fs::create_dir_all(&notes_dir)
+        .with_context(|| format!("Failed to create {}", notes_dir.display()))?;
+    
+    let all: Vec<&Note> = notes.iter().collect();
+    let pages: Vec<String> = slug::unique(&all).into_iter()
+        .map(|stem| format!("{}.html", stem))
+        .collect();
+    let mut items = String::new();
+    let mut manifest = BTreeMap::new();
+    for (note, page_name) in notes.iter().zip(&pages) {
+        let page = note_page(note);
+        let page_path = notes_dir.join(page_name);
+        fs::write(&page_path, page)
+            .with_context(|| format!("Failed to write {}", page_path.display()))?;
+        
+        manifest.insert(note.id.clone(), format!("notes/{}", page_name));
+        
+        let search_text = format!("{} {}", note.content, note.tags().join(" ")).to_lowercase();
+        items.push_str(&format!(
+            "<li data-text=\"{}\"><a href=\"notes/{}\">{}</a><div class=\"meta\">{}{}</div></li>\n",
+            escape(&search_text),
+            escape(&url_encode(page_name)),
+            escape(&title(note)),
+            timezone::display(&note.timestamp).format("%Y-%m-%d %H:%M"),
+            tag_list(note)
+        ));
+    }
+    
+    // Pages of notes left out this time, or since renamed, must not stay
+    // published
+    let written: HashSet<&str> = pages.iter().map(String::as_str).collect();
+    for entry in fs::read_dir(&notes_dir).context("Failed to read the notes directory")? {
+        let path = entry?.path();
+        let exported = path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| written.contains(name));
+        if path.extension().is_some_and(|ext| ext == "html") && !exported {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    
+    let index = format!(
+        "{}<h1>Notes</h1>\n<input id=\"q\" type=\"search\" placeholder=\"Search {} notes\" autofocus>\n<ul>\n{}</ul>\n<script>{}</script>\n</body>\n</html>\n",
+        head("Notes"),
+        notes.len(),
+        items,
+        SEARCH_SCRIPT
+    );
+    fs::write(&index_path, index)
+        .with_context(|| format!("Failed to write {}", index_path.display()))?;
+    slug::write_manifest(dir, &manifest)?;
+    Ok(notes.len())
+}
+
+fn note_page(note: &Note) -> String {
+    format!(
+        "{}<p><a href=\"../index.html\">&larr; All notes</a></p>\n<div class=\"meta\">{}{}</div>\n<div class=\"content\">{}</div>\n</body>\n</html>\n",
+        head(&title(note)),
+        timezone::display(&note.timestamp).format("%Y-%m-%d %H:%M"),
+        tag_list(note),
+        escape(&note.content)
+    )
+}
+
+fn head(title: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(title),
+        STYLE
+    )
+}
+
+fn title(note: &Note) -> String {
+    let first_line = note.content.lines().next().unwrap_or("").trim();
+    if first_line.is_empty() { note.id.clone() } else { first_line.to_string() }
+}
+
+fn tag_list(note: &Note) -> String {
+    note.tags().iter()
+        .map(|tag| format!("<span class=\"tag\">#{}</span>", escape(tag)))
+        .collect()
+}
+
+// Slugs may keep non-ASCII letters, which links need percent-encoded
+fn url_encode(name: &str) -> String {
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
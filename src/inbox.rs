@@ -0,0 +1,68 @@
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+use crate::note::Note;
+
+// Quick captures are marked `inbox=new` until they are triaged. Snoozing sets
+// `snooze=<date>` to hide a capture from triage until then, and archiving
+// records `archived=<date>` so the note drops out of the default listing.
+pub const INBOX_KEY: &str = "inbox";
+pub const SNOOZE_KEY: &str = "snooze";
+pub const ARCHIVED_KEY: &str = "archived";
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug)]
+pub enum TriageAction {
+    // Take the note out of the inbox, adding these tags
+    Keep(Vec<String>),
+    Archive,
+    Snooze(NaiveDate),
+}
+
+pub fn capture_meta() -> BTreeMap<String, String> {
+    BTreeMap::from([(INBOX_KEY.to_string(), "new".to_string())])
+}
+
+pub fn is_untriaged(note: &Note) -> bool {
+    note.meta.contains_key(INBOX_KEY)
+}
+
+pub fn is_archived(note: &Note) -> bool {
+    note.meta.contains_key(ARCHIVED_KEY)
+}
+
+pub fn snoozed_until(note: &Note) -> Option<NaiveDate> {
+    note.meta.get(SNOOZE_KEY)
+        .and_then(|date| NaiveDate::parse_from_str(date, DATE_FORMAT).ok())
+}
+
+// Untriaged and not snoozed past `today`
+pub fn is_pending(note: &Note, today: NaiveDate) -> bool {
+    is_untriaged(note) && snoozed_until(note).is_none_or(|until| until <= today)
+}
+
+// Applies a triage decision to the note's metadata
+pub fn apply(note: &mut Note, action: &TriageAction, today: NaiveDate) {
+    match action {
+        TriageAction::Keep(tags) => {
+            let mut all_tags = note.tags();
+            all_tags.extend(tags.iter().cloned());
+            note.set_tags(&all_tags);
+            note.meta.remove(INBOX_KEY);
+            note.meta.remove(SNOOZE_KEY);
+        }
+        TriageAction::Archive => {
+            note.meta.remove(INBOX_KEY);
+            note.meta.remove(SNOOZE_KEY);
+            note.meta.insert(ARCHIVED_KEY.to_string(), format_date(today));
+        }
+        TriageAction::Snooze(until) => {
+            note.meta.insert(SNOOZE_KEY.to_string(), format_date(*until));
+        }
+    }
+}
+
+pub fn format_date(date: NaiveDate) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
\ No newline at end of file
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::note::Note;
+
+// An inverted index from lowercase words to the notes containing them, kept
+// in search-index.txt next to notes.txt:
+//
+//   %note-index 1 <hash of the notes file it describes>
+//   @<note id> <hash of the note's content>
+//   <word> <note id> <note id> ...
+//
+// Each save re-indexes only the notes whose content hash changed.
+const HEADER: &str = "%note-index 1";
+
+#[derive(Default)]
+pub struct SearchIndex {
+    path: PathBuf,
+    // Hash of the notes file contents the index was last brought up to date with
+    source: String,
+    hashes: BTreeMap<String, String>,
+    postings: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl SearchIndex {
+    // A missing or unreadable index file gives an empty index
+    pub fn load(path: PathBuf) -> Self {
+        let mut index = Self { path, ..Self::default() };
+        let Ok(content) = fs::read_to_string(&index.path) else {
+            return index;
+        };
+        let mut lines = content.lines();
+        let Some(source) = lines.next().and_then(|header| header.strip_prefix(HEADER)) else {
+            return index;
+        };
+        index.source = source.trim().to_string();
+        
+        for line in lines {
+            let mut fields = line.split(' ');
+            let Some(first) = fields.next() else { continue };
+            if let Some(id) = first.strip_prefix('@') {
+                index.hashes.insert(id.to_string(), fields.next().unwrap_or("").to_string());
+            } else {
+                index.postings.insert(first.to_string(), fields.map(str::to_string).collect());
+            }
+        }
+        index
+    }
+    
+    // Whether the index describes exactly this notes file
+    pub fn is_current(&self, notes_hash: &str) -> bool {
+        !self.source.is_empty() && self.source == notes_hash
+    }
+    
+    // Re-indexes changed notes and drops removed ones
+    pub fn update(&mut self, notes: &[Note], notes_hash: &str) {
+        let mut current = HashSet::new();
+        let mut changed = Vec::new();
+        for note in notes {
+            current.insert(note.id.as_str());
+            let hash = content_hash(&note.content);
+            if self.hashes.get(&note.id) != Some(&hash) {
+                changed.push((note, hash));
+            }
+        }
+        
+        let stale: HashSet<String> = self.hashes.keys()
+            .filter(|id| !current.contains(id.as_str()))
+            .cloned()
+            .chain(changed.iter().map(|(note, _)| note.id.clone()))
+            .collect();
+        if !stale.is_empty() {
+            for ids in self.postings.values_mut() {
+                ids.retain(|id| !stale.contains(id));
+            }
+            self.postings.retain(|_, ids| !ids.is_empty());
+            self.hashes.retain(|id, _| !stale.contains(id));
+        }
+        
+        for (note, hash) in &changed {
+            for word in tokenize(&note.content) {
+                self.postings.entry(word).or_default().insert(note.id.clone());
+            }
+            self.hashes.insert(note.id.clone(), hash.clone());
+        }
+        self.source = notes_hash.to_string();
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    pub fn save(&self) -> Result<()> {
+        let mut content = format!("{} {}\n", HEADER, self.source);
+        for (id, hash) in &self.hashes {
+            content.push_str(&format!("@{} {}\n", id, hash));
+        }
+        for (word, ids) in &self.postings {
+            content.push_str(word);
+            for id in ids {
+                content.push(' ');
+                content.push_str(id);
+            }
+            content.push('\n');
+        }
+        
+        // Write to a temporary file first so a crash never leaves half an index
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, content).context("Failed to write search index")?;
+        fs::rename(&tmp_path, &self.path).context("Failed to save search index")?;
+        Ok(())
+    }
+    
+    // Notes that may contain the plain query, or None when the index can't
+    // narrow it down. Every word of the query must appear, possibly as part
+    // of a longer word, since the query may start or end mid-word.
+    pub fn candidates(&self, query: &str) -> Option<HashSet<&str>> {
+        let words = tokenize(query);
+        if words.is_empty() {
+            return None;
+        }
+        
+        // The posting lists of every indexed word containing each query word
+        let mut lists: Vec<Vec<&BTreeSet<String>>> = words.iter()
+            .map(|word| self.postings.iter()
+                .filter(|(indexed, _)| indexed.contains(word.as_str()))
+                .map(|(_, ids)| ids)
+                .collect())
+            .collect();
+        
+        // Start from the rarest word and check the others by lookup
+        lists.sort_by_key(|lists| lists.iter().map(|ids| ids.len()).sum::<usize>());
+        let (rarest, others) = lists.split_first()?;
+        Some(rarest.iter()
+            .flat_map(|ids| ids.iter().map(String::as_str))
+            .filter(|id| others.iter().all(|lists| lists.iter().any(|ids| ids.contains(*id))))
+            .collect())
+    }
+}
+
+pub fn content_hash(text: &str) -> String {
+    // Short hashes keep the index small; a collision only means a stale entry
+    blake3::hash(text.as_bytes()).to_hex()[..16].to_string()
+}
+
+// Lowercase runs of letters and digits, each once
+fn tokenize(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
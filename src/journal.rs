@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Marks a journal entry as completely written; followed by the content hash
+const COMMIT_MARKER: &str = "\n%%journal-commit ";
+
+// Write-ahead journal for the notes file. A save first records the complete
+// new content in the journal, then replaces the notes file atomically, then
+// clears the journal. If the process dies in between, the next start replays
+// a committed entry or discards a torn one, so notes.txt is never truncated.
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn for_file(target: &Path) -> Self {
+        let mut name = target.file_name().unwrap_or_default().to_os_string();
+        name.push(".journal");
+        Self {
+            path: target.with_file_name(name),
+        }
+    }
+    
+    pub fn write(&self, target: &Path, content: &str) -> Result<()> {
+        let entry = format!("{}{}{}\n", content, COMMIT_MARKER, blake3::hash(content.as_bytes()).to_hex());
+        write_synced(&self.path, entry.as_bytes())
+            .context("Failed to write journal")?;
+        
+        replace_atomically(target, content)?;
+        
+        fs::remove_file(&self.path)
+            .context("Failed to clear journal")?;
+        Ok(())
+    }
+    
+    // Finishes an interrupted save. Returns true if a pending entry was replayed.
+    pub fn recover(&self, target: &Path) -> Result<bool> {
+        if !self.path.exists() {
+            return Ok(false);
+        }
+        
+        let entry = fs::read_to_string(&self.path)
+            .context("Failed to read journal")?;
+        let replayed = match Self::committed_content(&entry) {
+            Some(content) => {
+                replace_atomically(target, content)?;
+                true
+            }
+            // A torn entry means the crash happened before the notes file was touched
+            None => false,
+        };
+        
+        fs::remove_file(&self.path)
+            .context("Failed to clear journal")?;
+        Ok(replayed)
+    }
+    
+    fn committed_content(entry: &str) -> Option<&str> {
+        let (content, hash) = entry.rsplit_once(COMMIT_MARKER)?;
+        (blake3::hash(content.as_bytes()).to_hex().as_str() == hash.trim_end()).then_some(content)
+    }
+}
+
+// Writes to a sibling temp file and renames it over the target
+fn replace_atomically(target: &Path, content: &str) -> Result<()> {
+    let mut tmp_name = target.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = target.with_file_name(tmp_name);
+    
+    write_synced(&tmp_path, content.as_bytes())
+        .context("Failed to write notes file")?;
+    fs::rename(&tmp_path, target)
+        .context("Failed to replace notes file")?;
+    Ok(())
+}
+
+fn write_synced(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
\ No newline at end of file
@@ -0,0 +1,67 @@
+use colored::*;
+
+use crate::glyphs::glyphs;
+use crate::note::Note;
+
+// A note's kind is kept in `kind=<name>` and gives light structure without
+// tagging everything: an icon in listings, `note list --kind` and a starting
+// template for new notes.
+pub const KIND_KEY: &str = "kind";
+
+#[derive(Clone, Copy, PartialEq, Debug, clap::ValueEnum)]
+pub enum NoteKind {
+    Todo,
+    Idea,
+    Meeting,
+    Snippet,
+}
+
+impl NoteKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            NoteKind::Todo => "todo",
+            NoteKind::Idea => "idea",
+            NoteKind::Meeting => "meeting",
+            NoteKind::Snippet => "snippet",
+        }
+    }
+    
+    pub fn of(note: &Note) -> Option<Self> {
+        match note.meta.get(KIND_KEY)?.as_str() {
+            "todo" => Some(NoteKind::Todo),
+            "idea" => Some(NoteKind::Idea),
+            "meeting" => Some(NoteKind::Meeting),
+            "snippet" => Some(NoteKind::Snippet),
+            _ => None,
+        }
+    }
+    
+    pub fn icon(self) -> ColoredString {
+        let glyphs = glyphs();
+        match self {
+            NoteKind::Todo => glyphs.todo.green(),
+            NoteKind::Idea => glyphs.idea.bright_yellow(),
+            NoteKind::Meeting => glyphs.meeting.blue(),
+            NoteKind::Snippet => glyphs.snippet.bright_cyan(),
+        }
+    }
+    
+    // `{text}` is replaced by what was typed for the note
+    pub fn default_template(self) -> &'static str {
+        match self {
+            NoteKind::Todo => "- [ ] {text}",
+            NoteKind::Idea => "{text}",
+            NoteKind::Meeting => "{text}\n\nAttendees:\nNotes:\nAction items:",
+            NoteKind::Snippet => "```\n{text}\n```",
+        }
+    }
+}
+
+// Fills in a template; one without `{text}` is added after the text
+pub fn apply_template(template: &str, text: &str) -> String {
+    if template.contains("{text}") {
+        template.replace("{text}", text)
+    } else {
+        format!("{}\n{}", text, template)
+    }
+}
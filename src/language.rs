@@ -0,0 +1,81 @@
+use whatlang::{Detector, Lang};
+
+use crate::note::Note;
+
+// With `detect` under [language], new and edited notes get the language of
+// their text in the `lang` field as a two-letter ISO 639-1 code, for
+// `note list --lang de` and for stemming each note in its own language.
+pub const LANG_KEY: &str = "lang";
+
+// Texts shorter than this rarely give a reliable guess
+const MIN_CHARS: usize = 20;
+
+// whatlang's ISO 639-3 codes and the ISO 639-1 codes stored instead
+const CODES: &[(&str, &str)] = &[
+    ("afr", "af"), ("aka", "ak"), ("amh", "am"), ("ara", "ar"), ("aze", "az"),
+    ("bel", "be"), ("ben", "bn"), ("bul", "bg"), ("cat", "ca"), ("ces", "cs"),
+    ("cmn", "zh"), ("dan", "da"), ("deu", "de"), ("ell", "el"), ("eng", "en"),
+    ("epo", "eo"), ("est", "et"), ("fin", "fi"), ("fra", "fr"), ("guj", "gu"),
+    ("heb", "he"), ("hin", "hi"), ("hrv", "hr"), ("hun", "hu"), ("hye", "hy"),
+    ("ind", "id"), ("ita", "it"), ("jav", "jv"), ("jpn", "ja"), ("kan", "kn"),
+    ("kat", "ka"), ("khm", "km"), ("kor", "ko"), ("lat", "la"), ("lav", "lv"),
+    ("lit", "lt"), ("mal", "ml"), ("mar", "mr"), ("mkd", "mk"), ("mya", "my"),
+    ("nep", "ne"), ("nld", "nl"), ("nob", "nb"), ("ori", "or"), ("pan", "pa"),
+    ("pes", "fa"), ("pol", "pl"), ("por", "pt"), ("ron", "ro"), ("rus", "ru"),
+    ("sin", "si"), ("slk", "sk"), ("slv", "sl"), ("sna", "sn"), ("spa", "es"),
+    ("srp", "sr"), ("swe", "sv"), ("tam", "ta"), ("tel", "te"), ("tgl", "tl"),
+    ("tha", "th"), ("tuk", "tk"), ("tur", "tr"), ("ukr", "uk"), ("urd", "ur"),
+    ("uzb", "uz"), ("vie", "vi"), ("yid", "yi"), ("zul", "zu"),
+];
+
+// Stored codes that `note search --stem` has a stemmer for, with the name
+// of its language in the [search] config
+pub const STEMMING: &[(&str, &str)] = &[
+    ("ar", "arabic"), ("da", "danish"), ("de", "german"), ("el", "greek"),
+    ("en", "english"), ("es", "spanish"), ("fi", "finnish"), ("fr", "french"),
+    ("hu", "hungarian"), ("it", "italian"), ("nb", "norwegian"), ("nl", "dutch"),
+    ("pt", "portuguese"), ("ro", "romanian"), ("ru", "russian"), ("sv", "swedish"),
+    ("ta", "tamil"), ("tr", "turkish"),
+];
+
+// The language of the text, when whatlang is confident about it. Limiting
+// the guess to the `allowed` languages, as codes or names, makes it both
+// likelier and more accurate; unknown names are ignored.
+pub fn detect(text: &str, allowed: &[String]) -> Option<&'static str> {
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_CHARS {
+        return None;
+    }
+    let allowlist: Vec<Lang> = allowed.iter()
+        .filter_map(|name| parse(name))
+        .filter_map(|code| CODES.iter().find(|(_, short)| *short == code))
+        .filter_map(|(long, _)| Lang::from_code(*long))
+        .collect();
+    let detector = if allowlist.is_empty() { Detector::new() } else { Detector::with_allowlist(allowlist) };
+    let info = detector.detect(text).filter(|info| info.is_reliable())?;
+    short_code(info.lang())
+}
+
+// "de", "deu" or "german" as the code stored in notes
+pub fn parse(name: &str) -> Option<&'static str> {
+    let name = name.trim().to_lowercase();
+    if let Some((_, short)) = CODES.iter().find(|(long, short)| *long == name || *short == name) {
+        return Some(short);
+    }
+    Lang::all().iter()
+        .find(|lang| lang.eng_name().to_lowercase() == name)
+        .and_then(|lang| short_code(*lang))
+}
+
+pub fn of(note: &Note) -> Option<&str> {
+    note.meta.get(LANG_KEY).map(String::as_str)
+}
+
+// The English name of a stored code, e.g. "German", for listings
+pub fn name(code: &str) -> Option<&'static str> {
+    let (long, _) = CODES.iter().find(|(_, short)| *short == code)?;
+    Lang::from_code(*long).map(|lang| lang.eng_name())
+}
+
+fn short_code(lang: Lang) -> Option<&'static str> {
+    CODES.iter().find(|(long, _)| *long == lang.code()).map(|(_, short)| *short)
+}
@@ -0,0 +1,129 @@
+use anyhow::{bail, Result};
+
+use crate::kind::NoteKind;
+use crate::note::Note;
+use crate::timezone;
+
+// A `note list --format` layout such as '{id}\t{date:%Y-%m-%d}\t{first_line}'.
+// Fields are written in braces, `{{` and `}}` are literal braces, and \t, \n
+// and \\ are escapes so layouts can be typed in single quotes.
+pub struct Layout {
+    segments: Vec<Segment>,
+}
+
+enum Segment {
+    Text(String),
+    Field(Field),
+}
+
+enum Field {
+    Id,
+    // strftime format
+    Date(String),
+    FirstLine,
+    Content,
+    Tags,
+    Kind,
+    Words,
+    Meta(String),
+}
+
+pub const FIELDS: &str = "id, date, date:FORMAT, first_line, content, tags, kind, words, meta:KEY";
+
+impl Layout {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = spec.chars().peekable();
+        
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some(other) => text.push(other),
+                    None => text.push('\\'),
+                },
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("Unclosed '{{' in format"),
+                        }
+                    }
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(parse_field(&name)?));
+                }
+                '}' => bail!("Unmatched '}}' in format; write '}}}}' for a literal brace"),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self { segments })
+    }
+    
+    pub fn render(&self, note: &Note) -> String {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Field(field) => output.push_str(&field_value(field, note)),
+            }
+        }
+        output
+    }
+}
+
+fn parse_field(name: &str) -> Result<Field> {
+    let (name, argument) = match name.split_once(':') {
+        Some((name, argument)) => (name, Some(argument)),
+        None => (name, None),
+    };
+    let field = match (name.trim(), argument) {
+        ("id", None) => Field::Id,
+        ("date", None) => Field::Date("%Y-%m-%d %H:%M".to_string()),
+        ("date", Some(format)) => Field::Date(format.to_string()),
+        ("first_line", None) => Field::FirstLine,
+        ("content", None) => Field::Content,
+        ("tags", None) => Field::Tags,
+        ("kind", None) => Field::Kind,
+        ("words", None) => Field::Words,
+        ("meta", Some(key)) if !key.is_empty() => Field::Meta(key.to_string()),
+        _ => bail!("Unknown format field '{{{}}}'; available fields: {}", name, FIELDS),
+    };
+    if let Field::Date(format) = &field {
+        // Catch bad strftime specifiers up front rather than panicking mid-listing
+        let items = chrono::format::StrftimeItems::new(format);
+        if items.clone().any(|item| matches!(item, chrono::format::Item::Error)) {
+            bail!("Invalid date format '{}'", format);
+        }
+    }
+    Ok(field)
+}
+
+fn field_value(field: &Field, note: &Note) -> String {
+    match field {
+        Field::Id => note.id.clone(),
+        Field::Date(format) => timezone::display(&note.timestamp).format(format).to_string(),
+        Field::FirstLine => note.content.lines().next().unwrap_or("").to_string(),
+        Field::Content => note.content.clone(),
+        Field::Tags => note.tags().join(","),
+        Field::Kind => NoteKind::of(note).map(|kind| kind.name().to_string()).unwrap_or_default(),
+        Field::Words => note.content.split_whitespace().count().to_string(),
+        Field::Meta(key) => note.meta.get(key).cloned().unwrap_or_default(),
+    }
+}
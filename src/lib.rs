@@ -0,0 +1,65 @@
+pub mod audit;
+pub mod autotag;
+pub mod backup;
+pub mod blobs;
+pub mod bodies;
+pub mod calc;
+pub mod calendar;
+pub mod checklist;
+pub mod checksum;
+pub mod cli;
+pub mod clock;
+pub mod config;
+pub mod diagram;
+pub mod diary;
+pub mod diff;
+pub mod digest;
+pub mod editor;
+pub mod encoding;
+pub mod folder;
+pub mod format;
+pub mod git;
+pub mod glyphs;
+pub mod html;
+pub mod inbox;
+pub mod index;
+pub mod journal;
+pub mod kind;
+pub mod language;
+pub mod layout;
+pub mod location;
+pub mod manager;
+#[cfg(all(unix, feature = "fuse"))]
+pub mod mount;
+pub mod note;
+pub mod notion;
+pub mod pack;
+pub mod parser;
+pub mod prefix;
+pub mod qr;
+pub mod reading;
+pub mod recap;
+pub mod record;
+pub mod redact;
+pub mod remote;
+pub mod resurface;
+pub mod seal;
+pub mod search;
+pub mod selection;
+pub mod shared;
+pub mod shell;
+pub mod shred;
+pub mod similar;
+pub mod slug;
+pub mod snap;
+pub mod snippets;
+pub mod speech;
+pub mod srs;
+pub mod summary;
+pub mod table;
+pub mod taskwarrior;
+pub mod tidy;
+pub mod time;
+pub mod timezone;
+pub mod watch;
+pub mod xdg;
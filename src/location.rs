@@ -0,0 +1,62 @@
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+use crate::note::Note;
+
+// Where a note was written, kept in `location=<place>`. A place is free text
+// such as "Berlin", or starts with "lat,lon" coordinates when it comes from a
+// provider command, which makes it usable with `note list --near`.
+pub const LOCATION_KEY: &str = "location";
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+// Runs the configured provider and returns the first line it prints
+pub fn from_provider(command: &str) -> Result<String> {
+    let mut words = command.split_whitespace();
+    let program = words.next().context("Set `command` in the [location] config section to use --here")?;
+    let output = Command::new(program)
+        .args(words)
+        .output()
+        .with_context(|| format!("Failed to run location command '{}'", command))?;
+    if !output.status.success() {
+        bail!("Location command '{}' failed: {}", command, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    
+    let location = String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or("").trim().to_string();
+    if location.is_empty() {
+        bail!("Location command '{}' printed nothing", command);
+    }
+    Ok(location)
+}
+
+// "52.52,13.405" or "52.52, 13.405 Berlin"
+pub fn coordinates(place: &str) -> Option<(f64, f64)> {
+    let (lat, rest) = place.split_once(',')?;
+    let lon = rest.split_whitespace().next()?;
+    let (lat, lon): (f64, f64) = (lat.trim().parse().ok()?, lon.parse().ok()?);
+    if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lon) {
+        Some((lat, lon))
+    } else {
+        None
+    }
+}
+
+// Great-circle distance using the haversine formula
+pub fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lon1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, lon2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+pub fn is_at(note: &Note, place: &str) -> bool {
+    note.meta.get(LOCATION_KEY)
+        .is_some_and(|location| location.to_lowercase().contains(&place.to_lowercase()))
+}
+
+pub fn is_near(note: &Note, center: (f64, f64), radius_km: f64) -> bool {
+    note.meta.get(LOCATION_KEY)
+        .and_then(|location| coordinates(location))
+        .is_some_and(|point| distance_km(center, point) <= radius_km)
+}
@@ -1,12 +1,8 @@
-mod cli;
-mod manager;
-mod note;
-mod parser;
-
 use anyhow::Result;
-use clap::Parser;
+
+use note::cli;
 
 fn main() -> Result<()> {
-    let cli = cli::Cli::parse();
+    let cli = cli::Cli::parse_args();
     cli::run(cli)
 }
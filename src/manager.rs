@@ -1,21 +1,332 @@
-use anyhow::{Context, Result};
-use chrono::{DateTime, Local};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local, NaiveDate};
 use colored::*;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::note::{Note, RemoveResult};
-use crate::parser::NoteParser;
+use crate::audit::{self, AuditLog};
+use crate::backup::{BackupInfo, BackupStore};
+use crate::blobs::BlobStore;
+use crate::bodies;
+use crate::checklist;
+use crate::checksum;
+use crate::clock::{self, ClockInResult};
+use crate::config::{AgingConfig, NoteOrder};
+use crate::diary;
+use crate::encoding;
+use crate::folder::{self, FolderFile, SyncReport, SyncState};
+use crate::format::{Format, Incoming, Item, ReadOptions};
+use crate::glyphs::glyphs;
+use crate::html;
+use crate::inbox::{self, TriageAction};
+use crate::index::{self, SearchIndex};
+use crate::journal::Journal;
+use crate::kind::NoteKind;
+use crate::language;
+use crate::location;
+use crate::note::{Attachment, LookupResult, Note, RemoveResult};
+use crate::pack::{self, PackState, UnpackReport};
+use crate::parser::{self, FileFormat, NoteParser};
+use crate::prefix::PrefixTrie;
+use crate::redact::Redactor;
+use crate::resurface;
+use crate::seal;
+use crate::search::Matcher;
+use crate::selection;
+use crate::shared::{self, StoreLock};
+use crate::shred::Shredder;
+use crate::slug;
+use crate::srs::SrsCard;
+use crate::table;
+use crate::taskwarrior;
+use crate::tidy;
+use crate::time::{Clock, SystemClock};
+use crate::timezone;
+use crate::xdg;
+
+// Number of content lines shown per note before the rest is collapsed
+const DEFAULT_MAX_LINES: usize = 6;
+
+// Average silent reading speed used for reading time estimates
+const WORDS_PER_MINUTE: usize = 200;
+
+pub struct DisplayOptions {
+    // None shows every line of every note
+    pub max_lines: Option<usize>,
+    // Occurrences of the active search are emphasized in the content
+    pub highlight: Option<Matcher>,
+    // Notes longer than max_lines only show this many lines around each
+    // highlighted match
+    pub context: Option<usize>,
+    // Word count and reading time next to each note's date
+    pub stats: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            max_lines: Some(DEFAULT_MAX_LINES),
+            highlight: None,
+            context: None,
+            stats: false,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct NoteFilter {
+    pub tag: Option<String>,
+    pub kind: Option<NoteKind>,
+    pub query: Option<Matcher>,
+    // Metadata conditions: (key, Some(value)) for equality, (key, None) for presence
+    pub meta: Vec<(String, Option<String>)>,
+    // Substring of the note's location
+    pub location: Option<String>,
+    // Notes whose location coordinates are within (center, radius in km)
+    pub near: Option<((f64, f64), f64)>,
+    // Notes written on this local date
+    pub day: Option<NaiveDate>,
+    // Notes written on or after this local date
+    pub since: Option<NaiveDate>,
+    // Archived notes only show up when asked for
+    pub include_archived: bool,
+    // Some(true) keeps only untriaged captures, Some(false) hides them
+    pub untriaged: Option<bool>,
+}
+
+impl NoteFilter {
+    pub fn matches(&self, note: &Note) -> bool {
+        if let Some(tag) = &self.tag && !note.has_tag_under(tag) {
+            return false;
+        }
+        if self.kind.is_some() && NoteKind::of(note) != self.kind {
+            return false;
+        }
+        if let Some(query) = &self.query && !query.for_language(language::of(note)).is_match(&note.content) {
+            return false;
+        }
+        if let Some(place) = &self.location && !location::is_at(note, place) {
+            return false;
+        }
+        if let Some((center, radius)) = self.near && !location::is_near(note, center, radius) {
+            return false;
+        }
+        if self.day.is_some_and(|day| timezone::display(&note.timestamp).date_naive() != day) {
+            return false;
+        }
+        if self.since.is_some_and(|since| timezone::display(&note.timestamp).date_naive() < since) {
+            return false;
+        }
+        if !self.include_archived && inbox::is_archived(note) {
+            return false;
+        }
+        if self.untriaged.is_some_and(|untriaged| untriaged != inbox::is_untriaged(note)) {
+            return false;
+        }
+        for (key, expected) in &self.meta {
+            let matched = match (note.meta.get(key), expected) {
+                (Some(value), Some(expected)) => value == expected,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            if !matched {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Default)]
+pub struct ImportReport {
+    // IDs of the imported notes, in file order
+    pub ids: Vec<String>,
+    // (ID in the file, ID it was given) for notes whose ID was already taken
+    pub renamed: Vec<(String, String)>,
+    // What became of every note in the source, in source order
+    pub entries: Vec<ImportEntry>,
+}
+
+impl ImportReport {
+    fn created(&mut self, id: &str, line: Option<usize>, source: Option<String>) {
+        self.ids.push(id.to_string());
+        self.entries.push(ImportEntry { status: "created", id: Some(id.to_string()), line, source, ..ImportEntry::default() });
+    }
+    
+    fn skipped(&mut self, line: Option<usize>, source: Option<String>, reason: &str) {
+        self.entries.push(ImportEntry { status: "skipped", line, source, reason: Some(reason.to_string()), ..ImportEntry::default() });
+    }
+}
+
+// One line of `note import --report`
+#[derive(Serialize, Default)]
+pub struct ImportEntry {
+    // "created", "renamed" (imported under a new ID as its own was taken) or
+    // "skipped"
+    pub status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    // The ID in the file, for "renamed"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_id: Option<String>,
+    // Line the note starts on, for text files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    // The Notion page or Taskwarrior task UUID it came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+// What `NoteManager::append_note` needs to know of the configuration
+pub struct AppendOptions<'a> {
+    pub order: NoteOrder,
+    // Command to log the note under, when the audit log is on
+    pub audit_command: Option<&'a str>,
+    // Lock timeout in shared mode
+    pub lock_timeout: Option<Duration>,
+    pub tidy: bool,
+    // Where the note's time comes from
+    pub clock: &'a dyn Clock,
+    // Longest text kept in notes.txt, in bytes
+    pub inline_limit: usize,
+}
+
+pub struct AppendedNote {
+    pub id: String,
+    // Size of the store after the add, for the limit warnings
+    pub note_count: usize,
+    pub file_size: u64,
+    // Every ID in the store after the add, for the ID prefix warning
+    pub ids: Vec<String>,
+}
 
 pub struct NoteManager {
     notes_file: PathBuf,
     notes: Vec<Note>,
+    blobs: BlobStore,
+    backups: BackupStore,
+    order: NoteOrder,
+    aging: AgingConfig,
+    redactor: Option<Redactor>,
+    tidy: bool,
+    id_prefix: usize,
+    // Hash of the notes file as last read or written
+    notes_hash: String,
+    // Saves keep the format the file is in until it is migrated
+    format: FileFormat,
+    index: Option<SearchIndex>,
+    audit: Option<AuditLog>,
+    autosave: Option<Autosave>,
+    shared: Option<Shared>,
+    // Note hashes as last read or written, kept while a checksum manifest exists
+    checksums: Option<BTreeMap<String, String>>,
+    // Notes the next write removes for good, overwriting them in the old files
+    shred: HashSet<String>,
+    // Archive checklists whose last box an update ticks, and which ones were
+    archive_done_checklists: bool,
+    archived_checklists: Vec<String>,
+    clock: Arc<dyn Clock>,
+    // Longest text kept in notes.txt, in bytes, and the blob holding the
+    // text of each longer note as the file was last read or written
+    inline_limit: usize,
+    stored_bodies: HashMap<String, String>,
+}
+
+// Shared mode keeps the notes as last read or written, so a save can tell its
+// own changes from those another host made since
+struct Shared {
+    lock_timeout: Duration,
+    base: Vec<Note>,
+}
+
+// Interactive sessions write the file at most once per interval. Changes made
+// in between are only kept in memory until the next save after the interval,
+// an explicit flush, or the manager being dropped.
+struct Autosave {
+    interval: Duration,
+    last_write: Option<Instant>,
+    dirty: bool,
 }
 
 impl NoteManager {
     pub fn new() -> Result<Self> {
-        let home_dir = dirs::home_dir().context("Failed to get home directory")?;
-        let notes_dir = home_dir.join(".local").join("share").join("note");
+        Self::open(Self::default_dir()?)
+    }
+    
+    pub fn default_dir() -> Result<PathBuf> {
+        xdg::data_dir().context("Failed to get home directory")
+    }
+    
+    // Adds a note without loading the store: only the IDs in the file are
+    // scanned and the new note is written into it as it stands, so `note
+    // "..."` stays instant however large the file grows. A search index
+    // notices the change and catches up the next time it is used.
+    pub fn append_note(notes_dir: &Path, content: String, tags: &[String], meta: BTreeMap<String, String>, options: &AppendOptions) -> Result<AppendedNote> {
+        fs::create_dir_all(notes_dir).context("Failed to create notes directory")?;
+        let notes_file = notes_dir.join("notes.txt");
+        let _lock = options.lock_timeout
+            .map(|timeout| StoreLock::acquire(&notes_file, timeout))
+            .transpose()?;
+        // As in open(), unless this process holds the lock itself
+        if (options.lock_timeout.is_some() || !shared::is_locked(&notes_file))
+            && Journal::for_file(&notes_file).recover(&notes_file)? {
+            eprintln!("{} Recovered unsaved changes from the journal", glyphs().warning.yellow());
+        }
+        
+        let existing = if notes_file.exists() {
+            encoding::read(&notes_file).context("Failed to read notes file")?
+        } else {
+            String::new()
+        };
+        let ids = NoteParser::scan_ids(&existing);
+        let content = if options.tidy { tidy::content(&content) } else { content };
+        let mut note = Note::new(content, &ids, options.clock.now());
+        note.meta = meta;
+        note.set_tags(tags);
+        let stowed = bodies::stow(&note, options.inline_limit, &BlobStore::new(notes_dir.join("blobs")))?;
+        
+        // Laid out exactly as a full save would write it
+        let format = NoteParser::detect_format(&existing);
+        let rest = match format {
+            FileFormat::V1 => existing.as_str(),
+            FileFormat::V2 => existing.split_once('\n').map_or("", |(_, rest)| rest),
+        };
+        let text = if rest.trim().is_empty() {
+            NoteParser::format_notes(&[&stowed], if existing.trim().is_empty() { FileFormat::V2 } else { format })
+        } else if options.order == NoteOrder::OldestFirst {
+            format!("{}\n\n{}", existing.trim_end(), NoteParser::format_note(&stowed, format))
+        } else {
+            let marker = if format == FileFormat::V2 { format!("{}\n", parser::FORMAT_2_MARKER) } else { String::new() };
+            format!("{}{}\n{}", marker, NoteParser::format_note(&stowed, format), rest)
+        };
+        
+        Journal::for_file(&notes_file).write(&notes_file, &text)?;
+        if let Some(command) = options.audit_command {
+            audit::record_added(&notes_file.with_file_name("audit.jsonl"), command, &note)?;
+        }
+        checksum::record_added(&notes_file.with_file_name(checksum::MANIFEST_FILE), &note)?;
+        let mut ids = ids;
+        ids.push(note.id.clone());
+        Ok(AppendedNote { id: note.id, note_count: ids.len(), file_size: text.len() as u64, ids })
+    }
+    
+    // Opens the store kept in the given data directory
+    pub fn open(notes_dir: PathBuf) -> Result<Self> {
+        Self::open_with_clock(notes_dir, Arc::new(SystemClock))
+    }
+    
+    // Opens the store in `notes_dir`, taking the current time from `clock`
+    // rather than the system, e.g. a FixedClock for a reproducible store
+    pub fn open_with_clock(notes_dir: PathBuf, clock: Arc<dyn Clock>) -> Result<Self> {
         let notes_file = notes_dir.join("notes.txt");
         
         // Create parent directories if they don't exist
@@ -27,105 +338,475 @@ impl NoteManager {
         let mut manager = Self {
             notes_file,
             notes: Vec::new(),
+            blobs: BlobStore::new(notes_dir.join("blobs")),
+            backups: BackupStore::new(notes_dir.join("backups")),
+            order: NoteOrder::default(),
+            aging: AgingConfig::default(),
+            redactor: None,
+            tidy: false,
+            id_prefix: 0,
+            notes_hash: String::new(),
+            format: FileFormat::V2,
+            index: None,
+            audit: None,
+            autosave: None,
+            shared: None,
+            checksums: None,
+            shred: HashSet::new(),
+            archive_done_checklists: false,
+            archived_checklists: Vec::new(),
+            clock,
+            inline_limit: 0,
+            stored_bodies: HashMap::new(),
         };
         
+        // Complete a save that was interrupted by a crash or power loss. A
+        // journal next to a held lock belongs to a save in progress elsewhere.
+        if !shared::is_locked(&manager.notes_file)
+            && Journal::for_file(&manager.notes_file).recover(&manager.notes_file)? {
+            eprintln!("{} Recovered unsaved changes from the journal", glyphs().warning.yellow());
+        }
+        
         manager.load_notes()?;
         Ok(manager)
     }
     
+    // How notes are arranged in the file when it is saved
+    pub fn with_order(mut self, order: NoteOrder) -> Self {
+        self.order = order;
+        self
+    }
+    
+    // When listings dim old notes and flag stale todos
+    pub fn with_aging(mut self, aging: AgingConfig) -> Self {
+        self.aging = aging;
+        self
+    }
+    
+    // Fewest characters of each ID highlighted in listings
+    pub fn with_id_prefix(mut self, id_prefix: usize) -> Self {
+        self.id_prefix = id_prefix;
+        self
+    }
+    
+    // Cleans up the text of every note whenever the file is saved
+    pub fn with_tidy(mut self, tidy: bool) -> Self {
+        self.tidy = tidy;
+        self
+    }
+    
+    // Archives a checklist note when a change ticks its last box
+    pub fn with_archive_done_checklists(mut self, enabled: bool) -> Self {
+        self.archive_done_checklists = enabled;
+        self
+    }
+    
+    // Keeps the text of notes longer than `bytes` in the blob store
+    pub fn with_inline_limit(mut self, bytes: usize) -> Self {
+        self.inline_limit = bytes;
+        self
+    }
+    
+    // Masks secrets in displayed and exported notes
+    pub fn with_redactor(mut self, redactor: Option<Redactor>) -> Self {
+        self.redactor = redactor;
+        self
+    }
+    
+    pub fn redact_note(&self, note: &mut Note) {
+        if let Some(redactor) = &self.redactor {
+            redactor.redact_note(note);
+        }
+    }
+    
+    fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match &self.redactor {
+            Some(redactor) => redactor.redact(text),
+            None => Cow::Borrowed(text),
+        }
+    }
+    
+    // Shared mode: saves lock the file, waiting up to `lock_timeout` for other
+    // hosts, and merge in the changes they made
+    pub fn with_sharing(mut self, lock_timeout: Option<Duration>) -> Self {
+        self.shared = lock_timeout.map(|lock_timeout| Shared { lock_timeout, base: self.notes.clone() });
+        self
+    }
+    
+    // Appends every change saved by `command` to audit.jsonl
+    pub fn with_audit_log(mut self, enabled: bool, command: &str) -> Self {
+        if enabled {
+            self.audit = Some(AuditLog::new(self.audit_log_path(), command, &self.notes));
+        }
+        self
+    }
+    
+    fn audit_log_path(&self) -> PathBuf {
+        self.notes_file.with_file_name("audit.jsonl")
+    }
+    
+    pub fn audit_entries(&self) -> Result<Vec<audit::Entry>> {
+        audit::read(&self.audit_log_path())
+    }
+    
+    // Keeps a search index next to the notes file, bringing it up to date
+    // first if the file changed without it
+    pub fn with_search_index(mut self, enabled: bool) -> Result<Self> {
+        if !enabled {
+            return Ok(self);
+        }
+        let path = self.notes_file.with_file_name("search-index.txt");
+        let mut index = SearchIndex::load(path);
+        if !index.is_current(&self.notes_hash) {
+            index.update(&self.notes, &self.notes_hash);
+            index.save()?;
+        }
+        self.index = Some(index);
+        Ok(self)
+    }
+    
+    // Moves the notes pushed since `first_new` to the top of the file, in the
+    // order they were added
+    fn place_new_notes(&mut self, first_new: usize) {
+        let added = self.notes.len() - first_new;
+        self.notes.rotate_right(added);
+    }
+    
     fn load_notes(&mut self) -> Result<()> {
-        if !self.notes_file.exists() {
+        let content = if self.notes_file.exists() {
+            encoding::read(&self.notes_file).context("Failed to read notes file")?
+        } else {
             // File doesn't exist, start with empty list
+            String::new()
+        };
+        self.notes_hash = index::content_hash(&content);
+        
+        if content.trim().is_empty() {
             self.notes = Vec::new();
+            self.format = FileFormat::V2;
+        } else {
+            self.format = NoteParser::detect_format(&content);
+            self.notes = NoteParser::parse_notes_from_text(&content, self.clock.now())
+                .context("Failed to parse notes file")?;
+        }
+        self.stored_bodies = bodies::restore(&mut self.notes, &self.blobs);
+        bodies::warn_missing(&self.notes, &self.blobs);
+        if let Some(shared) = &mut self.shared {
+            shared.base = self.notes.clone();
+        }
+        if self.checksums_path().exists() {
+            self.checksums = Some(checksum::hashes(&self.notes));
+        }
+        
+        Ok(())
+    }
+    
+    // Rate-limits saves for the rest of an interactive session; call flush()
+    // when the session ends
+    pub fn start_autosave(&mut self, interval: Duration) {
+        self.autosave = Some(Autosave { interval, last_write: None, dirty: false });
+    }
+    
+    // Writes changes held back by autosave, if any
+    pub fn flush(&mut self) -> Result<()> {
+        if self.autosave.as_ref().is_some_and(|autosave| autosave.dirty) {
+            self.write_notes()?;
+        }
+        Ok(())
+    }
+    
+    pub fn save_notes(&mut self) -> Result<()> {
+        if let Some(autosave) = &mut self.autosave
+            && autosave.last_write.is_some_and(|last| last.elapsed() < autosave.interval) {
+            autosave.dirty = true;
             return Ok(());
         }
+        self.write_notes()
+    }
+    
+    fn write_notes(&mut self) -> Result<()> {
+        // Held until the new file is in place
+        let _lock = self.lock_shared()?;
+        self.merge_shared_changes()?;
+        if self.tidy {
+            for note in &mut self.notes {
+                note.content = tidy::content(&note.content);
+            }
+        }
         
-        let content = fs::read_to_string(&self.notes_file)
-            .context("Failed to read notes file")?;
+        // Preserving the existing order keeps diffs of the file to the notes
+        // that actually changed
+        let mut ordered_notes: Vec<&Note> = self.notes.iter().collect();
+        match self.order {
+            NoteOrder::Preserve => {}
+            NoteOrder::NewestFirst => ordered_notes.sort_by_key(|note| std::cmp::Reverse(note.timestamp)),
+            NoteOrder::OldestFirst => ordered_notes.sort_by_key(|note| note.timestamp),
+        }
+        let stowed = ordered_notes.iter()
+            .map(|note| bodies::stow(note, self.inline_limit, &self.blobs))
+            .collect::<Result<Vec<_>>>()?;
+        let content = NoteParser::format_notes(&stowed.iter().map(|note| note.as_ref()).collect::<Vec<_>>(), self.format);
+        let stored_bodies: HashMap<String, String> = stowed.iter()
+            .filter_map(|note| note.meta.get(bodies::BODY_KEY).map(|hash| (note.id.clone(), hash.clone())))
+            .collect();
+        // Removed once overwritten, so the same text added again isn't
+        // taken to be stored already
+        let mut shredded_bodies = Vec::new();
+        let shredder = if self.shred.is_empty() {
+            None
+        } else {
+            let mut shredder = Shredder::notes(&self.notes_file, &self.shred)?;
+            if let Some(index) = &self.index {
+                shredder.add_file(index.path())?;
+            }
+            // Unless another note has the same text
+            for id in &self.shred {
+                let Some(hash) = self.stored_bodies.get(id) else {
+                    continue;
+                };
+                let path = self.blobs.path_for(hash);
+                if !stored_bodies.values().any(|kept| kept == hash) && !shredded_bodies.contains(&path) {
+                    shredder.add_file(&path)?;
+                    shredded_bodies.push(path);
+                }
+            }
+            Some(shredder)
+        };
+        self.stored_bodies = stored_bodies;
         
-        if content.trim().is_empty() {
-            self.notes = Vec::new();
+        Journal::for_file(&self.notes_file).write(&self.notes_file, &content)?;
+        self.notes_hash = index::content_hash(&content);
+        if let Some(audit) = &mut self.audit {
+            audit.record(&self.notes, self.clock.now())?;
+        }
+        if let Some(autosave) = &mut self.autosave {
+            autosave.last_write = Some(Instant::now());
+            autosave.dirty = false;
+        }
+        if let Some(shared) = &mut self.shared {
+            shared.base = self.notes.clone();
+        }
+        let loaded = self.checksums.take();
+        self.update_checksums(loaded)?;
+        self.refresh_index()?;
+        if let Some(shredder) = shredder {
+            shredder.overwrite()?;
+        }
+        for path in shredded_bodies {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+    
+    fn lock_shared(&self) -> Result<Option<StoreLock>> {
+        self.shared.as_ref()
+            .map(|shared| StoreLock::acquire(&self.notes_file, shared.lock_timeout))
+            .transpose()
+    }
+    
+    // In shared mode, rebases this process's changes onto the file if another
+    // host saved since it was read. Call with the lock held.
+    fn merge_shared_changes(&mut self) -> Result<()> {
+        let Some(shared) = &self.shared else {
+            return Ok(());
+        };
+        let content = if self.notes_file.exists() {
+            encoding::read(&self.notes_file).context("Failed to read notes file")?
+        } else {
+            String::new()
+        };
+        if index::content_hash(&content) == self.notes_hash {
             return Ok(());
         }
         
-        self.notes = NoteParser::parse_notes_from_text(&content)
+        let mut theirs = NoteParser::parse_notes_from_text(&content, self.clock.now())
             .context("Failed to parse notes file")?;
-        
+        self.stored_bodies.extend(bodies::restore(&mut theirs, &self.blobs));
+        // Their changes were logged by whoever made them
+        if let Some(audit) = &mut self.audit {
+            audit.reset(&theirs);
+        }
+        self.notes = shared::merge(&shared.base, &self.notes, theirs);
         Ok(())
     }
     
-    fn save_notes(&self) -> Result<()> {
-        let mut content = String::new();
-        
-        // Sort notes by timestamp (newest first) for consistent output
-        let mut sorted_notes = self.notes.clone();
-        sorted_notes.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        
-        for (index, note) in sorted_notes.iter().enumerate() {
-            if index > 0 {
-                content.push('\n');
-            }
-            
-            // Write header line: #id timestamp
-            content.push_str(&format!("#{} {}\n", note.id, note.timestamp.to_rfc3339()));
-            
-            // Write note content, escaping lines that start with #
-            let escaped_content = NoteParser::escape_content(&note.content);
-            content.push_str(&escaped_content);
-            content.push('\n');
+    fn refresh_index(&mut self) -> Result<()> {
+        if let Some(index) = &mut self.index {
+            index.update(&self.notes, &self.notes_hash);
+            index.save()?;
         }
-        
-        fs::write(&self.notes_file, content)
-            .context("Failed to write notes file")?;
-        
         Ok(())
     }
     
-    pub fn add_note(&mut self, content: String) -> Result<String> {
+    pub fn add_note(&mut self, content: String, tags: &[String]) -> Result<String> {
+        self.add_note_with_meta(content, tags, BTreeMap::new())
+    }
+    
+    pub fn add_note_with_meta(&mut self, content: String, tags: &[String], meta: BTreeMap<String, String>) -> Result<String> {
         let existing_ids: Vec<String> = self.notes.iter().map(|n| n.id.clone()).collect();
-        let note = Note::new(content, &existing_ids);
+        let mut note = Note::new(content, &existing_ids, self.clock.now());
+        note.meta = meta;
+        note.set_tags(tags);
         let note_id = note.id.clone();
         self.notes.push(note);
+        self.place_new_notes(self.notes.len() - 1);
         self.save_notes()?;
         
         Ok(note_id)
     }
     
-    pub fn display_notes(&self, notes: &[Note]) {
+    pub fn display_notes(&self, notes: &[Note], options: &DisplayOptions) {
         println!();
+        let trie = PrefixTrie::new(self.notes.iter().map(|note| note.id.as_str()));
         
         for (index, note) in notes.iter().enumerate() {
             // Add separating line between notes
             if index > 0 {
-                println!("  {}", "────────────────────────────────────".bright_black());
+                println!("  {}", glyphs().separator.bright_black());
             }
             
             let formatted_time = self.format_natural_date(&note.timestamp);
             
-            // Show ID first, then date
-            println!("  {} {}", 
-                format!("[{}]", note.id).yellow(),
-                formatted_time.bright_black()
+            let tags: Vec<String> = note.tags().iter().map(|t| format!(" #{}", t)).collect();
+            let custom_meta: Vec<String> = note.custom_meta().map(|(k, v)| format!(" {}={}", k, v)).collect();
+            let stats = if options.stats { format!(" {}", Self::format_stats(&note.content)) } else { String::new() };
+            
+            let kind = NoteKind::of(note).map(|kind| format!("{} ", kind.icon())).unwrap_or_default();
+            let age_days = (self.clock.now() - note.timestamp).num_days();
+            let dimmed = self.aging.dim_after_days > 0 && age_days >= self.aging.dim_after_days;
+            let is_todo = note.has_tag(taskwarrior::TODO_TAG) || NoteKind::of(note) == Some(NoteKind::Todo);
+            let stale = if is_todo && self.aging.stale_todo_days > 0 && age_days >= self.aging.stale_todo_days {
+                format!(" {}", "stale".yellow())
+            } else {
+                String::new()
+            };
+            
+            // Show ID first, then kind, date, stats, tags and custom metadata
+            println!("  {} {}{}{}{}{}{}", 
+                self.format_id(&note.id, &trie),
+                kind,
+                if dimmed { formatted_time.bright_black().dimmed() } else { formatted_time.bright_black() },
+                stale,
+                stats.magenta(),
+                tags.concat().cyan(),
+                custom_meta.concat().bright_black()
             );
             
             // Display content with comfortable indentation, no highlighting
-            for line in note.content.lines() {
-                println!("  {}", line);
+            let content = self.redact(&note.content);
+            let aligned = table::align_tab_rows(&content.lines().collect::<Vec<_>>());
+            let lines: Vec<&str> = aligned.iter().map(String::as_str).collect();
+            let highlight = options.highlight.as_ref().map(|matcher| matcher.for_language(language::of(note)));
+            let print_line = |line: &str| {
+                let line = match &highlight {
+                    Some(matcher) => matcher.highlight(line),
+                    None => line.to_string(),
+                };
+                if dimmed { println!("  {}", line.dimmed()) } else { println!("  {}", line) }
+            };
+            
+            if let Some(windows) = Self::match_windows(&lines, options, highlight.as_deref()) {
+                // Like grep -C: only the lines around matches, gaps marked
+                for (index, window) in windows.iter().enumerate() {
+                    if index > 0 || window.start > 0 {
+                        println!("  {}", glyphs().more.bright_black());
+                    }
+                    lines[window.clone()].iter().for_each(|line| print_line(line));
+                }
+                if windows.last().is_some_and(|window| window.end < lines.len()) {
+                    println!("  {}", glyphs().more.bright_black());
+                }
+            } else {
+                let shown = options.max_lines.unwrap_or(lines.len()).min(lines.len());
+                lines[..shown].iter().for_each(|line| print_line(line));
+                let hidden = lines.len() - shown;
+                if hidden > 0 {
+                    println!("  {}", format!("{} (+{} {})", glyphs().more, hidden, if hidden == 1 { "line" } else { "lines" }).bright_black());
+                }
+            }
+            
+            for attachment in note.attachments() {
+                println!("  {} {}", glyphs().attachment.bright_black(), attachment.name.bright_black());
             }
         }
         
         println!();
     }
     
+    // Merged ranges of lines around each highlighted match, for notes too long
+    // to show whole; None when the note is shown normally
+    fn match_windows(lines: &[&str], options: &DisplayOptions, highlight: Option<&Matcher>) -> Option<Vec<Range<usize>>> {
+        let (Some(context), Some(matcher), Some(max_lines)) = (options.context, highlight, options.max_lines) else {
+            return None;
+        };
+        if lines.len() <= max_lines {
+            return None;
+        }
+        
+        // The first line stays, as it usually says what the note is about
+        let mut windows = vec![Range { start: 0, end: 1 }];
+        let mut matched = false;
+        for (index, line) in lines.iter().enumerate() {
+            if matcher.match_ranges(line).is_empty() {
+                continue;
+            }
+            matched = true;
+            let window = index.saturating_sub(context)..(index + context + 1).min(lines.len());
+            match windows.last_mut() {
+                Some(last) if last.end >= window.start => last.end = window.end,
+                _ => windows.push(window),
+            }
+        }
+        if matched { Some(windows) } else { None }
+    }
+    
+    // The part of the ID that names the note alone in bold, like git's
+    // abbreviated hashes; notes from elsewhere, such as a backup, show plainly
+    fn format_id(&self, id: &str, trie: &PrefixTrie) -> String {
+        if !trie.contains(id) {
+            return format!("[{}]", id).yellow().to_string();
+        }
+        let len = trie.unique_len(id).max(self.id_prefix);
+        let split = id.char_indices().nth(len).map_or(id.len(), |(index, _)| index);
+        let (prefix, rest) = id.split_at(split);
+        format!("{}{}{}{}", "[".yellow(), prefix.yellow().bold(), rest.yellow().dimmed(), "]".yellow())
+    }
+    
+    // "120 words, 1 min"; reading time is rounded up so short notes show 1 min
+    fn format_stats(content: &str) -> String {
+        let words = content.split_whitespace().count();
+        let minutes = words.div_ceil(WORDS_PER_MINUTE).max(1);
+        format!("({} {}, {} min)", words, if words == 1 { "word" } else { "words" }, minutes)
+    }
+    
     fn format_natural_date(&self, timestamp: &DateTime<Local>) -> String {
-        timestamp.format("%b %d").to_string()
+        timezone::display(timestamp).format("%b %d").to_string()
+    }
+    
+    // Notes passing the filter, newest first
+    pub fn filtered_notes(&self, filter: &NoteFilter) -> Vec<Note> {
+        // The index narrows a plain search down to the notes sharing its words
+        let candidates = filter.query.as_ref()
+            .and_then(|query| query.literal())
+            .zip(self.index.as_ref())
+            .and_then(|(query, index)| index.candidates(query));
+        
+        // Sort by timestamp, newest first
+        let mut sorted_notes: Vec<Note> = self.notes.iter()
+            .filter(|note| candidates.as_ref().is_none_or(|ids| ids.contains(note.id.as_str())))
+            .filter(|note| filter.matches(note))
+            .cloned()
+            .collect();
+        sorted_notes.sort_by_key(|note| std::cmp::Reverse(note.timestamp));
+        sorted_notes
     }
     
-    pub fn list_notes(&self) {
+    pub fn list_notes(&self, filter: &NoteFilter, options: &DisplayOptions) {
         if self.notes.is_empty() {
             println!();
-            println!("  {} {}", 
-                "✨".bright_white(),
+            let empty = glyphs().empty;
+            println!("  {}{}", 
+                if empty.is_empty() { String::new() } else { format!("{} ", empty.bright_white()) },
                 "No notes yet".bright_black()
             );
             println!("     {}", "Create your first note with:".white());
@@ -137,121 +818,993 @@ impl NoteManager {
             return;
         }
         
-        // Sort by timestamp, newest first
-        let mut sorted_notes = self.notes.clone();
-        sorted_notes.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        let sorted_notes = self.filtered_notes(filter);
+        
+        if sorted_notes.is_empty() {
+            println!();
+            println!("  {}", "No matching notes".bright_black());
+            println!();
+            return;
+        }
         
-        self.display_notes(&sorted_notes);
+        self.display_notes(&sorted_notes, options);
     }
     
-    pub fn remove_note_by_id(&mut self, id: &str) -> Result<RemoveResult> {
+    pub fn lookup_note(&self, id: &str) -> LookupResult {
         // Find all notes that start with the given partial ID
-        let matching_notes: Vec<&Note> = self.notes.iter()
-            .filter(|note| note.id.starts_with(id))
-            .collect();
+        let matching_ids = PrefixTrie::new(self.notes.iter().map(|note| note.id.as_str())).matching(id);
         
-        match matching_notes.len() {
-            0 => Ok(RemoveResult::NotFound),
-            1 => {
-                let note_id = matching_notes[0].id.clone();
+        match matching_ids.len() {
+            0 => LookupResult::NotFound,
+            1 => LookupResult::Found(matching_ids[0].clone()),
+            _ => LookupResult::Ambiguous(matching_ids),
+        }
+    }
+    
+    pub fn remove_note_by_id(&mut self, id: &str) -> Result<RemoveResult> {
+        match self.lookup_note(id) {
+            LookupResult::NotFound => Ok(RemoveResult::NotFound),
+            LookupResult::Found(note_id) => {
                 self.notes.retain(|note| note.id != note_id);
                 self.save_notes()?;
                 Ok(RemoveResult::Removed(note_id))
             }
-            _ => {
-                let ambiguous_ids: Vec<String> = matching_notes.iter()
-                    .map(|note| note.id.clone())
-                    .collect();
-                Ok(RemoveResult::Ambiguous(ambiguous_ids))
+            LookupResult::Ambiguous(ambiguous_ids) => Ok(RemoveResult::Ambiguous(ambiguous_ids)),
+        }
+    }
+    
+    // Removes the notes with the given full IDs; returns how many there were
+    pub fn remove_notes(&mut self, ids: &[String]) -> Result<usize> {
+        let before = self.notes.len();
+        self.notes.retain(|note| !ids.contains(&note.id));
+        let removed = before - self.notes.len();
+        if removed > 0 {
+            self.save_notes()?;
+        }
+        Ok(removed)
+    }
+    
+    // Removes the notes and overwrites where they were in the notes file and
+    // the search index once the rewritten files are in place. Written at
+    // once, as autosave holding the write back would leave them on disk.
+    pub fn shred_notes(&mut self, ids: &[String]) -> Result<usize> {
+        let before = self.notes.len();
+        self.notes.retain(|note| !ids.contains(&note.id));
+        let removed = before - self.notes.len();
+        if removed > 0 {
+            self.shred = ids.iter().cloned().collect();
+            let written = self.write_notes();
+            self.shred.clear();
+            written?;
+        }
+        Ok(removed)
+    }
+    
+    pub fn backups_dir(&self) -> &Path {
+        self.backups.dir()
+    }
+    
+    // Names of the backups that still hold any of the notes
+    pub fn backups_holding(&self, ids: &[String]) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        for backup in self.list_backups()? {
+            if self.notes_in_backup(&backup.name)?.iter().any(|note| ids.contains(&note.id)) {
+                names.push(backup.name);
             }
         }
+        Ok(names)
     }
     
-    pub fn get_notes(&self) -> &[Note] {
-        &self.notes
+    fn checksums_path(&self) -> PathBuf {
+        self.notes_file.with_file_name(checksum::MANIFEST_FILE)
     }
     
-    pub fn output_raw_content(&self) -> Result<()> {
-        if !self.notes_file.exists() {
-            // If notes file doesn't exist, output nothing
-            return Ok(());
+    // Writes the manifest `note verify` checks against; returns the store hash
+    pub fn save_checksums(&mut self) -> Result<String> {
+        let hashes = checksum::hashes(&self.notes);
+        let store = checksum::save(&self.checksums_path(), &hashes)?;
+        self.checksums = Some(hashes);
+        Ok(store)
+    }
+    
+    // How the notes differ from the manifest, or None when there is none
+    pub fn verify_checksums(&self) -> Result<Option<(checksum::Manifest, checksum::Differences)>> {
+        let Some(manifest) = checksum::load(&self.checksums_path())? else {
+            return Ok(None);
+        };
+        let differences = checksum::compare(&manifest.hashes, &checksum::hashes(&self.notes));
+        Ok(Some((manifest, differences)))
+    }
+    
+    // Carries this process's changes into the manifest, given the note hashes
+    // from before them
+    fn update_checksums(&mut self, loaded: Option<BTreeMap<String, String>>) -> Result<()> {
+        if let Some(loaded) = loaded {
+            self.checksums = Some(checksum::update(&self.checksums_path(), &loaded, &self.notes)?);
         }
+        Ok(())
+    }
+    
+    // Where `note show --render` keeps rendered diagrams
+    pub fn diagrams_dir(&self) -> PathBuf {
+        self.notes_file.with_file_name("diagrams")
+    }
+    
+    fn selections_path(&self) -> PathBuf {
+        self.notes_file.with_file_name("selections.txt")
+    }
+    
+    pub fn selections(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        selection::load(&self.selections_path())
+    }
+    
+    // The IDs saved under `name` that still belong to notes, or None when
+    // there is no such selection
+    pub fn selection(&self, name: &str) -> Result<Option<Vec<String>>> {
+        Ok(self.selections()?.remove(name).map(|ids| {
+            ids.into_iter().filter(|id| self.notes.iter().any(|note| &note.id == id)).collect()
+        }))
+    }
+    
+    pub fn save_selection(&self, name: &str, ids: &[String]) -> Result<()> {
+        let mut selections = self.selections()?;
+        selections.insert(name.to_string(), ids.to_vec());
+        selection::save(&self.selections_path(), &selections)
+    }
+    
+    // Picks up changes another process made to the notes file
+    pub fn reload(&mut self) -> Result<()> {
+        self.load_notes()?;
+        if let Some(audit) = &mut self.audit {
+            audit.reset(&self.notes);
+        }
+        self.refresh_index()
+    }
+    
+    // Replaces a note's text, keeping its ID, timestamp and metadata.
+    // Returns false when the text is unchanged.
+    pub fn update_content(&mut self, note_id: &str, content: String) -> Result<bool> {
+        let note = self.notes.iter_mut()
+            .find(|n| n.id == note_id)
+            .context("Note not found")?;
+        
+        if note.content == content {
+            return Ok(false);
+        }
+        note.content = content;
+        if self.archive_done_checklists && checklist::archive_if_done(note, self.clock.now().date_naive()) {
+            self.archived_checklists.push(note_id.to_string());
+        }
+        self.save_notes()?;
+        Ok(true)
+    }
+    
+    // The current time, as the manager's clock has it
+    pub fn now(&self) -> DateTime<Local> {
+        self.clock.now()
+    }
+    
+    // Checklists archived this run because their last box was ticked
+    pub fn archived_checklists(&self) -> &[String] {
+        &self.archived_checklists
+    }
+    
+    // Sets (Some) or clears (None) a metadata field. Returns false when
+    // clearing a field the note doesn't have.
+    pub fn update_meta(&mut self, note_id: &str, key: &str, value: Option<&str>) -> Result<bool> {
+        let note = self.notes.iter_mut()
+            .find(|n| n.id == note_id)
+            .context("Note not found")?;
         
-        let content = fs::read_to_string(&self.notes_file)
-            .context("Failed to read notes file")?;
+        let changed = match value {
+            Some(value) => {
+                note.meta.insert(key.to_string(), value.to_string());
+                true
+            }
+            None => note.meta.remove(key).is_some(),
+        };
         
-        // Output the raw content directly without any formatting
-        print!("{}", content);
+        if changed {
+            self.save_notes()?;
+        }
+        Ok(changed)
+    }
+    
+    // Returns false if the note was already enrolled
+    pub fn enroll_srs(&mut self, note_id: &str) -> Result<bool> {
+        let note = self.notes.iter_mut()
+            .find(|n| n.id == note_id)
+            .context("Note not found")?;
+        if SrsCard::from_note(note).is_some() {
+            return Ok(false);
+        }
         
+        SrsCard::new(self.clock.now().date_naive()).store(note);
+        self.save_notes()?;
+        Ok(true)
+    }
+    
+    // Notes due for review, most overdue first
+    pub fn due_srs_notes(&self) -> Vec<Note> {
+        let today = self.clock.now().date_naive();
+        let mut due: Vec<(SrsCard, &Note)> = self.notes.iter()
+            .filter_map(|note| SrsCard::from_note(note).map(|card| (card, note)))
+            .filter(|(card, _)| card.is_due(today))
+            .collect();
+        due.sort_by_key(|(card, _)| card.due);
+        due.into_iter().map(|(_, note)| note.clone()).collect()
+    }
+    
+    // Records a recall grade and returns the rescheduled card
+    pub fn record_srs_review(&mut self, note_id: &str, grade: u8) -> Result<SrsCard> {
+        let today = self.clock.now().date_naive();
+        let note = self.notes.iter_mut()
+            .find(|n| n.id == note_id)
+            .context("Note not found")?;
+        let mut card = SrsCard::from_note(note).unwrap_or_else(|| SrsCard::new(today));
+        card.review(grade, today);
+        card.store(note);
+        self.save_notes()?;
+        Ok(card)
+    }
+    
+    // Captures waiting for triage, oldest first
+    pub fn inbox_notes(&self) -> Vec<Note> {
+        let today = self.clock.now().date_naive();
+        let mut pending: Vec<Note> = self.notes.iter()
+            .filter(|note| inbox::is_pending(note, today))
+            .cloned()
+            .collect();
+        pending.sort_by_key(|note| note.timestamp);
+        pending
+    }
+    
+    pub fn triage_note(&mut self, note_id: &str, action: &TriageAction) -> Result<()> {
+        let note = self.notes.iter_mut()
+            .find(|n| n.id == note_id)
+            .context("Note not found")?;
+        inbox::apply(note, action, self.clock.now().date_naive());
+        self.save_notes()
+    }
+    
+    // Picks notes to look at again and records that they were just viewed
+    pub fn resurface_notes(&mut self, count: usize, random: bool) -> Result<Vec<Note>> {
+        let picked: Vec<Note> = resurface::pick(&self.notes, count, random).into_iter().cloned().collect();
+        if picked.is_empty() {
+            return Ok(picked);
+        }
+        let now = timezone::storage(&self.clock.now());
+        for note in self.notes.iter_mut().filter(|note| picked.iter().any(|p| p.id == note.id)) {
+            note.meta.insert(resurface::VIEWED_KEY.to_string(), now.clone());
+        }
+        self.save_notes()?;
+        Ok(picked)
+    }
+    
+    // Records that the note was just looked at, for `note resurface`
+    pub fn mark_viewed(&mut self, note_id: &str) -> Result<()> {
+        let now = timezone::storage(&self.clock.now());
+        self.update_meta(note_id, resurface::VIEWED_KEY, Some(&now))?;
         Ok(())
     }
     
-    pub fn output_raw_content_to_file(&self, file_path: &str) -> Result<()> {
-        if !self.notes_file.exists() {
-            // If notes file doesn't exist, create empty file
-            fs::write(file_path, "").context("Failed to write to output file")?;
-            return Ok(());
+    // The journal note for `day`, if one was started
+    pub fn journal_for(&self, day: NaiveDate) -> Option<&Note> {
+        let key = diary::day_key(day);
+        self.notes.iter().find(|note| diary::is_journal_for(note, &key))
+    }
+    
+    // Adds a timed entry to the journal for `day`, starting it if needed.
+    // Returns the journal's note ID.
+    pub fn write_journal(&mut self, day: NaiveDate, mood: Option<u8>, text: &str) -> Result<String> {
+        let entry = diary::entry(&timezone::display(&self.clock.now()), mood, text);
+        match self.journal_for(day).map(|note| (note.id.clone(), note.content.clone())) {
+            Some((id, content)) => {
+                self.update_content(&id, diary::append(&content, &entry))?;
+                Ok(id)
+            }
+            None => {
+                let content = diary::append(&diary::title(day), &entry);
+                let meta = BTreeMap::from([(diary::JOURNAL_KEY.to_string(), diary::day_key(day))]);
+                self.add_note_with_meta(content, &[diary::JOURNAL_TAG.to_string()], meta)
+            }
+        }
+    }
+    
+    // Starts a time tracking session unless one is already open
+    pub fn clock_in(&mut self, label: &str) -> Result<ClockInResult> {
+        if let Some(open) = clock::open_session(&self.notes) {
+            return Ok(ClockInResult::AlreadyRunning(clock::label(open)));
         }
         
-        let content = fs::read_to_string(&self.notes_file)
-            .context("Failed to read notes file")?;
+        let note_id = self.add_note_with_meta(
+            format!("Clock in: {}", label),
+            &[clock::CLOCK_TAG.to_string()],
+            clock::in_meta(label),
+        )?;
+        Ok(ClockInResult::Started(note_id))
+    }
+    
+    // Ends the open session, returning its label and duration in seconds
+    pub fn clock_out(&mut self) -> Result<Option<(String, i64)>> {
+        let Some(open) = clock::open_session(&self.notes) else {
+            return Ok(None);
+        };
+        let label = clock::label(open);
+        let pair_id = open.id.clone();
+        let seconds = (self.clock.now() - open.timestamp).num_seconds().max(0);
+        
+        self.add_note_with_meta(
+            format!("Clock out: {} ({})", label, clock::format_duration(seconds)),
+            &[clock::CLOCK_TAG.to_string()],
+            clock::out_meta(&label, &pair_id, seconds),
+        )?;
+        Ok(Some((label, seconds)))
+    }
+    
+    // How many notes were written on each day in the display timezone, not
+    // counting archived ones
+    pub fn notes_per_day(&self) -> BTreeMap<NaiveDate, usize> {
+        let mut counts = BTreeMap::new();
+        for note in self.notes.iter().filter(|note| !inbox::is_archived(note)) {
+            *counts.entry(timezone::display(&note.timestamp).date_naive()).or_insert(0) += 1;
+        }
+        counts
+    }
+    
+    // Counts how many notes carry each tag, sorted by tag name
+    pub fn tag_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+        for note in &self.notes {
+            for tag in note.tags() {
+                *counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+        counts.into_iter().collect()
+    }
+    
+    // Every tag and the parents of nested ones, each with the number of notes
+    // tagged with it or anything under it, in tree order
+    pub fn tag_tree(&self) -> Vec<(String, usize)> {
+        // Keyed by path segments, so `work/a` sorts right after `work`
+        let mut counts: BTreeMap<Vec<String>, usize> = BTreeMap::new();
+        for note in &self.notes {
+            let tags = note.tags();
+            let mut paths: Vec<Vec<&str>> = Vec::new();
+            for tag in &tags {
+                let parts: Vec<&str> = tag.split('/').collect();
+                for depth in 1..=parts.len() {
+                    if !paths.iter().any(|path| path[..] == parts[..depth]) {
+                        paths.push(parts[..depth].to_vec());
+                    }
+                }
+            }
+            for path in paths {
+                *counts.entry(path.iter().map(|part| part.to_string()).collect()).or_insert(0) += 1;
+            }
+        }
+        counts.into_iter().map(|(path, count)| (path.join("/"), count)).collect()
+    }
+    
+    // Renames, merges (when `to` already exists) or removes (when `to` is None)
+    // a tag across all notes. Returns the number of notes changed.
+    pub fn retag_all(&mut self, from: &str, to: Option<&str>) -> Result<usize> {
+        let mut changed = 0;
+        for note in self.notes.iter_mut() {
+            if note.retag(from, to) {
+                changed += 1;
+            }
+        }
         
-        fs::write(file_path, content)
-            .context("Failed to write to output file")?;
+        if changed > 0 {
+            self.save_notes()?;
+        }
         
-        Ok(())
+        Ok(changed)
     }
     
-    pub fn import_from_file(&mut self, file_path: &str) -> Result<usize> {
-        let content = fs::read_to_string(file_path)
-            .context(format!("Failed to read file: {}", file_path))?;
+    // Adds or removes `tag` on the notes with the given IDs; returns how many
+    // of them changed
+    pub fn tag_notes(&mut self, ids: &[String], tag: &str, add: bool) -> Result<usize> {
+        let mut changed = 0;
+        for note in self.notes.iter_mut().filter(|note| ids.contains(&note.id)) {
+            if add && !note.has_tag(tag) {
+                let mut tags = note.tags();
+                tags.push(tag.to_string());
+                note.set_tags(&tags);
+                changed += 1;
+            } else if !add && note.retag(tag, None) {
+                changed += 1;
+            }
+        }
         
-        if content.trim().is_empty() {
-            return Ok(0);
+        if changed > 0 {
+            self.save_notes()?;
         }
         
-        // Parse the imported notes
-        let imported_notes = NoteParser::parse_notes_from_text(&content)
-            .context("Failed to parse imported notes")?;
+        Ok(changed)
+    }
+    
+    // Sets `key` on each note to its value, as (note ID, value) pairs, saving once
+    pub fn set_meta_values(&mut self, key: &str, values: &[(String, String)]) -> Result<()> {
+        for (id, value) in values {
+            if let Some(note) = self.notes.iter_mut().find(|note| &note.id == id) {
+                note.meta.insert(key.to_string(), value.clone());
+            }
+        }
+        self.save_notes()
+    }
+    
+    // Adds each note's tags to it, as (note ID, tags) pairs
+    pub fn add_tags(&mut self, additions: &[(String, Vec<String>)]) -> Result<()> {
+        for (id, added) in additions {
+            if let Some(note) = self.notes.iter_mut().find(|note| &note.id == id) {
+                note.set_tags(&[note.tags(), added.clone()].concat());
+            }
+        }
+        self.save_notes()
+    }
+    
+    // Stores the file in the blob store and references it from the note.
+    // Returns true when identical content was already stored.
+    pub fn attach_file(&mut self, note_id: &str, file_path: &str) -> Result<bool> {
+        let source = Path::new(file_path);
+        let name = source.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_path.to_string());
+        let (hash, deduplicated) = self.blobs.store(source)?;
         
-        if imported_notes.is_empty() {
-            return Ok(0);
+        let note = self.notes.iter_mut()
+            .find(|n| n.id == note_id)
+            .context("Note not found")?;
+        note.add_attachment(&Attachment { hash, name });
+        self.save_notes()?;
+        
+        Ok(deduplicated)
+    }
+    
+    pub fn attachment_path(&self, attachment: &Attachment) -> PathBuf {
+        self.blobs.path_for(&attachment.hash)
+    }
+    
+    // Removes stored files no note uses: attachments, and the text of long
+    // notes unless a backup still needs it to be restored whole
+    pub fn collect_garbage(&self) -> Result<usize> {
+        let mut referenced: HashSet<String> = self.notes.iter()
+            .flat_map(|note| note.attachments())
+            .map(|attachment| attachment.hash)
+            .chain(self.stored_bodies.values().cloned())
+            .collect();
+        for backup in self.backups.list()? {
+            let content = self.backups.read(&backup.name)?;
+            let notes = NoteParser::parse_notes_from_text(&content, self.clock.now())
+                .with_context(|| format!("Backup {} could not be parsed", backup.name))?;
+            referenced.extend(notes.into_iter().filter_map(|mut note| note.meta.remove(bodies::BODY_KEY)));
         }
         
-        // Get existing IDs to avoid conflicts
-        let existing_ids: Vec<String> = self.notes.iter().map(|n| n.id.clone()).collect();
+        self.blobs.gc(&referenced)
+    }
+    
+    pub fn get_notes(&self) -> &[Note] {
+        &self.notes
+    }
+    
+    pub fn file_size(&self) -> u64 {
+        fs::metadata(&self.notes_file).map(|m| m.len()).unwrap_or(0)
+    }
+    
+    // Snapshots the notes file as it is on disk
+    pub fn create_backup(&self) -> Result<String> {
+        // Loading the notes already warned about any bad bytes
+        let content = if self.notes_file.exists() {
+            encoding::decode(&fs::read(&self.notes_file).context("Failed to read notes file")?).0
+        } else {
+            String::new()
+        };
+        self.backups.create(&content, self.clock.now())
+    }
+    
+    pub fn list_backups(&self) -> Result<Vec<BackupInfo>> {
+        self.backups.list()
+    }
+    
+    pub fn find_backups(&self, name: &str) -> Result<Vec<String>> {
+        self.backups.find(name)
+    }
+    
+    pub fn latest_backup_by(&self, day: NaiveDate) -> Result<Option<String>> {
+        self.backups.latest_by(day)
+    }
+    
+    pub fn notes_in_backup(&self, name: &str) -> Result<Vec<Note>> {
+        let content = self.backups.read(name)?;
+        let mut notes = NoteParser::parse_notes_from_text(&content, self.clock.now())
+            .with_context(|| format!("Backup {} could not be parsed", name))?;
+        bodies::restore(&mut notes, &self.blobs);
+        Ok(notes)
+    }
+    
+    // The note as it was in a backup, if it existed then
+    pub fn note_in_backup(&self, name: &str, id: &str) -> Result<Option<Note>> {
+        Ok(self.notes_in_backup(name)?.into_iter().find(|note| note.id == id))
+    }
+    
+    pub fn prune_backups(&self, keep: usize) -> Result<usize> {
+        self.backups.prune(keep)
+    }
+    
+    pub fn file_format(&self) -> FileFormat {
+        self.format
+    }
+    
+    // Rewrites a format 1 file in format 2. Returns false when there was
+    // nothing to do.
+    pub fn migrate_format(&mut self) -> Result<bool> {
+        if self.format == FileFormat::V2 {
+            return Ok(false);
+        }
+        self.format = FileFormat::V2;
+        self.save_notes()?;
+        Ok(true)
+    }
+    
+    // Replaces the current notes with a snapshot. Returns the restored note count.
+    pub fn restore_backup(&mut self, name: &str) -> Result<usize> {
+        let content = self.backups.read(name)?;
+        // Make sure the snapshot parses before it replaces anything
+        NoteParser::parse_notes_from_text(&content, self.clock.now())
+            .context("Backup could not be parsed")?;
+        
+        let lock = self.lock_shared()?;
+        Journal::for_file(&self.notes_file).write(&self.notes_file, &content)?;
+        drop(lock);
+        let loaded = self.checksums.take();
+        self.load_notes()?;
+        self.update_checksums(loaded)?;
+        if let Some(audit) = &mut self.audit {
+            audit.record(&self.notes, self.clock.now())?;
+        }
+        self.refresh_index()?;
+        
+        Ok(self.notes.len())
+    }
+    
+    // The notes file as it is, or every note written out in `format`
+    pub fn output(&self, format: Option<&dyn Format>) -> Result<String> {
+        match format {
+            Some(format) => format.write(&self.notes.iter().collect::<Vec<_>>()),
+            None if !self.notes_file.exists() => Ok(String::new()),
+            // With the text of long notes in place of their stand-ins
+            None if !self.stored_bodies.is_empty() => Ok(NoteParser::format_notes(&self.notes.iter().collect::<Vec<_>>(), self.format)),
+            None => fs::read_to_string(&self.notes_file).context("Failed to read notes file"),
+        }
+    }
+    
+    // Writes one note with front matter to `path`, or to `<slug>.md` inside
+    // it when it is a directory. Existing files are kept unless `overwrite`.
+    pub fn export_note(&self, note_id: &str, path: Option<&str>, overwrite: bool) -> Result<PathBuf> {
+        let note = self.notes.iter()
+            .find(|n| n.id == note_id)
+            .context("Note not found")?;
         
-        // Add imported notes, regenerating IDs if there are conflicts
-        let mut imported_count = 0;
-        for imported_note in imported_notes {
-            let note_content = imported_note.content;
-            let mut note_id = imported_note.id;
+        let slug = note.slug();
+        let file_name = format!("{}.md", if slug.is_empty() { &note.id } else { &slug });
+        let target = match path.map(PathBuf::from) {
+            Some(path) if path.is_dir() => path.join(file_name),
+            Some(path) => path,
+            None => PathBuf::from(file_name),
+        };
+        self.write_markdown(note, &target, overwrite)?;
+        Ok(target)
+    }
+    
+    // Writes each note to its own `<slug>.md` in `dir`, every name different,
+    // and records them in the directory's manifest. Returns the files written,
+    // the manifest last.
+    pub fn export_notes(&self, note_ids: &[String], dir: &Path, overwrite: bool) -> Result<Vec<PathBuf>> {
+        let notes: Vec<&Note> = note_ids.iter()
+            .map(|id| self.notes.iter().find(|note| note.id == *id).context("Note not found"))
+            .collect::<Result<_>>()?;
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create directory {}", dir.display()))?;
+        
+        let targets: Vec<PathBuf> = slug::unique(&notes).into_iter()
+            .map(|stem| dir.join(format!("{}.md", stem)))
+            .collect();
+        if let Some(target) = targets.iter().find(|target| !overwrite && target.exists()) {
+            bail!("{} already exists (use --force to replace it)", target.display());
+        }
+        let mut manifest = BTreeMap::new();
+        for (note, target) in notes.iter().zip(&targets) {
+            self.write_markdown(note, target, true)?;
+            let file = target.file_name().unwrap_or_default().to_string_lossy().to_string();
+            manifest.insert(note.id.clone(), file);
+        }
+        slug::write_manifest(dir, &manifest)?;
+        let mut written = targets;
+        written.push(dir.join(slug::MANIFEST_FILE));
+        Ok(written)
+    }
+    
+    fn write_markdown(&self, note: &Note, target: &Path, overwrite: bool) -> Result<()> {
+        if target.exists() && !overwrite {
+            bail!("{} already exists (use --force to replace it)", target.display());
+        }
+        let content = format!("{}\n{}\n", NoteParser::format_front_matter(note), self.redact(&note.content));
+        fs::write(target, content)
+            .with_context(|| format!("Failed to write {}", target.display()))
+    }
+    
+    // The IDs of the notes an export of many notes includes: those matching
+    // the filter and having any of the tags (all when none are given),
+    // newest first
+    pub fn export_selection(&self, tags: &[String], filter: &NoteFilter) -> Vec<String> {
+        self.filtered_notes(filter).into_iter()
+            .filter(|note| tags.is_empty() || tags.iter().any(|tag| note.has_tag_under(tag)))
+            .map(|note| note.id)
+            .collect()
+    }
+    
+    // Seals an export of the notes with a manifest of what it wrote, next to
+    // `target`; returns where the manifest went
+    pub fn write_export_manifest(&self, note_ids: &[String], files: &[PathBuf], target: &Path) -> Result<PathBuf> {
+        let notes: Vec<(String, String)> = note_ids.iter()
+            .filter_map(|id| self.notes.iter().find(|note| note.id == *id))
+            .map(|note| (note.id.clone(), self.redact(&note.content).into_owned()))
+            .collect();
+        let path = seal::path_for(target);
+        seal::write(&path, &notes, files, self.clock.now())?;
+        Ok(path)
+    }
+    
+    pub fn export_site(&self, dir: &Path, note_ids: &[String], overwrite: bool) -> Result<usize> {
+        let mut notes: Vec<Note> = note_ids.iter()
+            .filter_map(|id| self.notes.iter().find(|note| note.id == *id))
+            .cloned()
+            .collect();
+        notes.iter_mut().for_each(|note| self.redact_note(note));
+        html::write_site(&notes, dir, overwrite)
+    }
+    
+    // Two-way sync with a file per note in `folder`, carrying edits, new files
+    // and deletions since the last sync across; `dry_run` only reports them
+    pub fn sync_folder(&mut self, folder: &Path, dry_run: bool) -> Result<SyncReport> {
+        if !dry_run {
+            fs::create_dir_all(folder)
+                .with_context(|| format!("Failed to create {}", folder.display()))?;
+        }
+        let folder = if folder.exists() {
+            folder.canonicalize().context("Failed to resolve the sync folder")?
+        } else {
+            std::path::absolute(folder).context("Failed to resolve the sync folder")?
+        };
+        let original = dry_run.then(|| self.notes.clone());
+        let state_path = self.notes_file.with_file_name("folder-sync.txt");
+        let mut state = SyncState::load(&state_path, &folder);
+        let mut report = SyncReport::default();
+        
+        // Pair each note ID known to either side with one file; other files,
+        // including extra copies claiming the same ID, are new notes
+        let known: BTreeSet<String> = self.notes.iter().map(|note| note.id.clone())
+            .chain(state.base.keys().cloned())
+            .collect();
+        let mut paired: BTreeMap<String, FolderFile> = BTreeMap::new();
+        let mut unpaired = Vec::new();
+        let files = if folder.exists() { folder::read_files(&folder)? } else { Vec::new() };
+        for file in files {
+            if known.contains(file.id()) && !paired.contains_key(file.id()) {
+                paired.insert(file.id().to_string(), file);
+            } else {
+                unpaired.push(file);
+            }
+        }
+        
+        let mut removed_ids = HashSet::new();
+        for id in &known {
+            let base = state.base.get(id);
+            let file = paired.remove(id);
+            let Some(note) = self.notes.iter_mut().find(|note| note.id == *id) else {
+                match file {
+                    // Deleted from the store and untouched in the folder
+                    Some(file) if base == Some(&index::content_hash(&file.content)) => {
+                        if !dry_run {
+                            fs::remove_file(&file.path)
+                                .with_context(|| format!("Failed to remove {}", file.path.display()))?;
+                        }
+                        report.removed_files += 1;
+                        report.changes.push((id.clone(), "removed from the folder"));
+                    }
+                    // Edited in the folder after the note was deleted
+                    Some(file) => unpaired.push(file),
+                    None => {}
+                }
+                continue;
+            };
             
-            // Check for ID conflicts and regenerate if needed
-            if existing_ids.contains(&note_id) {
-                // Generate a new unique ID
-                let all_existing_ids: Vec<String> = self.notes.iter()
-                    .map(|n| n.id.clone())
-                    .chain(std::iter::once(note_id.clone()))
-                    .collect();
-                
-                let new_note = crate::note::Note::new(note_content.clone(), &all_existing_ids);
-                note_id = new_note.id;
+            match file {
+                Some(file) => {
+                    if note.content != file.content {
+                        let note_changed = base != Some(&index::content_hash(&note.content));
+                        let file_changed = base != Some(&index::content_hash(&file.content));
+                        if !file_changed {
+                            report.written += 1;
+                            report.changes.push((id.clone(), "written to the folder"));
+                        } else if !note_changed {
+                            note.content = file.content.clone();
+                            if self.archive_done_checklists && !dry_run && checklist::archive_if_done(note, self.clock.now().date_naive()) {
+                                self.archived_checklists.push(id.clone());
+                            }
+                            report.updated += 1;
+                            report.changes.push((id.clone(), "updated from the folder"));
+                        } else {
+                            note.content = folder::conflict_text(&note.content, &file.content, &file.name);
+                            report.conflicts.push(id.clone());
+                            report.changes.push((id.clone(), "changed on both sides, kept with conflict markers"));
+                        }
+                    }
+                    paired.insert(id.clone(), file);
+                }
+                // Deleted from the folder and untouched in the store
+                None if base == Some(&index::content_hash(&note.content)) => {
+                    removed_ids.insert(id.clone());
+                    report.removed_notes += 1;
+                    report.changes.push((id.clone(), "removed from your notes"));
+                }
+                None => {
+                    report.written += 1;
+                    report.changes.push((id.clone(), "written to the folder"));
+                }
+            }
+        }
+        self.notes.retain(|note| !removed_ids.contains(&note.id));
+        
+        let first_new = self.notes.len();
+        for file in unpaired {
+            if file.content.trim().is_empty() {
+                continue;
+            }
+            let existing_ids: Vec<String> = self.notes.iter().map(|n| n.id.clone()).collect();
+            let note = Note::new(file.content.clone(), &existing_ids, self.clock.now());
+            report.added += 1;
+            report.changes.push((file.name.clone(), "added from the folder"));
+            paired.insert(note.id.clone(), file);
+            self.notes.push(note);
+        }
+        self.place_new_notes(first_new);
+        
+        if let Some(original) = original {
+            for note in &self.notes {
+                let path = folder.join(note.file_name());
+                if paired.get(&note.id).is_some_and(|file| file.path != path && file.id() == note.id && file.content == note.content) {
+                    report.renamed += 1;
+                    report.changes.push((note.id.clone(), "renamed in the folder"));
+                }
+            }
+            self.notes = original;
+            return Ok(report);
+        }
+        if report.updated + report.added + report.removed_notes + report.conflicts.len() > 0 {
+            self.save_notes()?;
+        }
+        
+        for note in &self.notes {
+            let path = folder.join(note.file_name());
+            let file = paired.remove(&note.id);
+            if file.as_ref().is_none_or(|file| file.content != note.content || file.path != path) {
+                folder::write_file(&path, &note.content)?;
+            }
+            if let Some(file) = file.filter(|file| file.path != path) {
+                fs::remove_file(&file.path)
+                    .with_context(|| format!("Failed to remove {}", file.path.display()))?;
+                if file.id() == note.id && file.content == note.content {
+                    report.renamed += 1;
+                    report.changes.push((note.id.clone(), "renamed in the folder"));
+                }
+            }
+        }
+        
+        state.base = self.notes.iter()
+            .map(|note| (note.id.clone(), index::content_hash(&note.content)))
+            .collect();
+        state.save(&state_path)?;
+        Ok(report)
+    }
+    
+    // Writes every note to a bundle for `note unpack` on another machine,
+    // with a tombstone for each note deleted so far. Returns how many.
+    pub fn pack(&self, path: &Path) -> Result<usize> {
+        let state_path = self.notes_file.with_file_name(pack::STATE_FILE);
+        let mut state = PackState::load(&state_path);
+        state.record(&self.notes);
+        pack::write(path, &self.notes, &state.deleted, self.clock.now())?;
+        state.save(&state_path)?;
+        Ok(state.deleted.len())
+    }
+    
+    // Merges a bundle from `note pack` on another machine. Notes only one
+    // side changed since the last exchange take that side's version, notes
+    // changed on both keep both with conflict markers, and the other
+    // machine's deletions of notes unchanged here are applied. Notes are told
+    // apart by their timestamp, so where each machine has a different note
+    // under the same ID, the older one keeps it on both.
+    pub fn unpack(&mut self, path: &Path) -> Result<UnpackReport> {
+        let bundle = pack::read(path, self.clock.now())?;
+        let label = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let state_path = self.notes_file.with_file_name(pack::STATE_FILE);
+        let mut state = PackState::load(&state_path);
+        state.bury(&self.notes);
+        let mut report = UnpackReport::default();
+        // Notes merged with their version, which count as in step with it
+        let mut merged = Vec::new();
+        
+        let before = self.notes.len();
+        self.notes.retain(|note| !bundle.deleted.contains_key(&pack::hash(note)));
+        report.removed = before - self.notes.len();
+        
+        let first_new = self.notes.len();
+        for theirs in &bundle.notes {
+            let mut theirs = theirs.clone();
+            let same_note = |note: &Note| note.timestamp == theirs.timestamp;
+            if !self.notes.iter().any(|note| note.id == theirs.id && same_note(note)) {
+                if let Some(ours) = self.notes.iter().find(|note| same_note(note)) {
+                    // Renamed here by an earlier unpack
+                    theirs.id = ours.id.clone();
+                } else if let Some(index) = self.notes.iter().position(|note| note.id == theirs.id) {
+                    if self.notes[index].timestamp < theirs.timestamp {
+                        theirs.id = pack::fresh_id(&theirs, &self.notes, &bundle.notes);
+                    } else {
+                        let id = pack::fresh_id(&self.notes[index], &self.notes, &bundle.notes);
+                        let old = std::mem::replace(&mut self.notes[index].id, id.clone());
+                        if let Some(hash) = state.base.remove(&old) {
+                            state.base.insert(id.clone(), hash);
+                        }
+                        report.renamed.push((old, id));
+                    }
+                }
             }
             
-            // Add the note with original timestamp but potentially new ID
-            self.notes.push(crate::note::Note {
-                id: note_id,
-                content: note_content,
-                timestamp: imported_note.timestamp,
-            });
+            let their_hash = pack::hash(&theirs);
+            let Some(ours) = self.notes.iter_mut().find(|note| note.id == theirs.id) else {
+                // Unless deleted here
+                if !state.deleted.contains_key(&their_hash) {
+                    self.notes.push(theirs);
+                    report.added += 1;
+                }
+                continue;
+            };
+            // The same, or a version already seen here
+            let our_hash = pack::hash(ours);
+            if our_hash == their_hash || state.has_seen(&ours.id, &their_hash) {
+                continue;
+            }
+            if state.latest(&ours.id) == Some(&our_hash) {
+                *ours = theirs;
+                report.updated += 1;
+                continue;
+            }
+            if ours.content != theirs.content {
+                ours.content = folder::conflict_text(&ours.content, &theirs.content, &label);
+                report.conflicts.push(ours.id.clone());
+            }
+            for (key, value) in theirs.meta {
+                ours.meta.entry(key).or_insert(value);
+            }
+            if pack::hash(ours) != our_hash && !report.conflicts.contains(&ours.id) {
+                report.updated += 1;
+            }
+            merged.push((ours.id.clone(), their_hash));
+        }
+        self.place_new_notes(first_new);
+        
+        if report.changed() {
+            self.save_notes()?;
+        }
+        state.deleted.extend(bundle.deleted);
+        state.record(&self.notes);
+        for (id, hash) in merged {
+            state.remember(&id, hash);
+        }
+        state.save(&state_path)?;
+        Ok(report)
+    }
+    
+    // Adds the notes `format` reads from `path`. Notes keep their ID from
+    // the file when it is free, else get one derived from their content and
+    // time, so importing the same file into the same store always gives the
+    // same result. With `duplicates`, a note whose content matches one
+    // already in the store, or earlier in the file, at the same moment give
+    // or take that tolerance is skipped.
+    pub fn import(&mut self, format: &dyn Format, path: &Path, options: &ReadOptions, duplicates: Option<chrono::Duration>) -> Result<ImportReport> {
+        let items = format.read(path, options)?;
+        
+        let mut report = ImportReport::default();
+        // IDs in use so far, including notes earlier in the same file
+        let mut taken: HashSet<String> = self.notes.iter().map(|n| n.id.clone()).collect();
+        let first_new = self.notes.len();
+        for item in items {
+            let Incoming { mut note, keeps_id, line, source } = match item {
+                Item::Note(incoming) => incoming,
+                Item::Skipped { line, source, reason } => {
+                    report.skipped(line, source, &reason);
+                    continue;
+                }
+            };
+            if let Some(tolerance) = duplicates {
+                let content = note.content.trim();
+                let existing = self.notes.iter().find(|existing| {
+                    existing.content.trim() == content && timezone::same_moment(&existing.timestamp, &note.timestamp, tolerance)
+                });
+                if let Some(existing) = existing {
+                    report.skipped(line, source, &format!("duplicate of {}", existing.id));
+                    continue;
+                }
+            }
+            if !keeps_id {
+                let existing_ids: Vec<String> = taken.iter().cloned().collect();
+                note.id = Note::new_id(&note.content, &self.clock.now(), &existing_ids);
+                report.created(&note.id, line, source);
+            } else if taken.contains(&note.id) {
+                let new_id = Note::derive_id(&note.content, &note.timestamp, &taken);
+                report.renamed.push((note.id.clone(), new_id.clone()));
+                report.ids.push(new_id.clone());
+                report.entries.push(ImportEntry {
+                    status: "renamed",
+                    id: Some(new_id.clone()),
+                    original_id: Some(note.id.clone()),
+                    line,
+                    source,
+                    ..ImportEntry::default()
+                });
+                note.id = new_id;
+            } else {
+                report.created(&note.id, line, source);
+            }
             
-            imported_count += 1;
+            // The note keeps its original timestamp and metadata
+            taken.insert(note.id.clone());
+            self.notes.push(note);
         }
+        self.place_new_notes(first_new);
+        report.entries.sort_by_key(|entry| entry.line);
         
-        // Save the updated notes
-        self.save_notes()?;
+        if !report.ids.is_empty() {
+            self.save_notes()?;
+        }
         
-        Ok(imported_count)
+        Ok(report)
+    }
+    
+    // Notes in a file of the notes.txt format, such as a backup snapshot
+    // (zstd-compressed ones included) or `note output` copy; "-" reads stdin.
+    // Long notes get their stored text back, as when the store is loaded.
+    pub fn read_external_notes(&self, path: &str) -> Result<Vec<Note>> {
+        let bytes = if path == "-" {
+            let mut bytes = Vec::new();
+            std::io::stdin().read_to_end(&mut bytes).context("Failed to read stdin")?;
+            bytes
+        } else {
+            fs::read(path).with_context(|| format!("Failed to read file: {}", path))?
+        };
+        // zstd frames start with this magic number, whatever the file is called
+        let bytes = if bytes.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            zstd::decode_all(bytes.as_slice()).with_context(|| format!("Failed to decompress {}", path))?
+        } else {
+            bytes
+        };
+        let content = encoding::decode_with_warning(&bytes, path);
+        let mut notes = NoteParser::parse_notes_from_text(&content, self.clock.now())
+            .with_context(|| format!("Failed to parse {}", path))?;
+        bodies::restore(&mut notes, &self.blobs);
+        Ok(notes)
+    }
+}
+
+// A session that ends early, e.g. with an error, still writes what autosave
+// held back
+impl Drop for NoteManager {
+    fn drop(&mut self) {
+        if let Err(error) = self.flush() {
+            eprintln!("{} Failed to save notes: {:#}", glyphs().error.red(), error);
+        }
     }
-}
\ No newline at end of file
+}
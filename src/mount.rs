@@ -0,0 +1,382 @@
+use anyhow::{Context, Result};
+use colored::*;
+use fuser::{
+    Config, Errno, FileAttr, FileHandle, FileType, Filesystem, FopenFlags, Generation, INodeNo,
+    LockOwner, MountOption, OpenFlags, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory,
+    ReplyEmpty, ReplyEntry, ReplyOpen, ReplyWrite, Request, TimeOrNow, WriteFlags,
+};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use crate::glyphs::glyphs;
+use crate::manager::NoteManager;
+
+// Attributes change whenever a note is saved, so the kernel should not cache them long
+const TTL: Duration = Duration::from_secs(1);
+
+// One note exposed as a text file. Edits are buffered in `data` and written
+// back to the store when the file is flushed or closed.
+struct NoteFile {
+    // None for a file created in the mount that has not been saved yet
+    id: Option<String>,
+    name: String,
+    data: Vec<u8>,
+    mtime: SystemTime,
+    dirty: bool,
+}
+
+struct MountState {
+    manager: NoteManager,
+    files: BTreeMap<u64, NoteFile>,
+    next_inode: u64,
+}
+
+// Exposes every note as `<id>-<slug>.txt` in a single flat directory.
+// File names are fixed for the lifetime of the mount so editors don't lose
+// track of a file whose first line changed.
+pub struct NoteFs {
+    state: Mutex<MountState>,
+}
+
+impl NoteFs {
+    pub fn new(manager: NoteManager) -> Self {
+        let mut files = BTreeMap::new();
+        let mut next_inode = INodeNo::ROOT.0 + 1;
+        for note in manager.get_notes() {
+            files.insert(next_inode, NoteFile {
+                id: Some(note.id.clone()),
+                name: note.file_name(),
+                data: format!("{}\n", note.content).into_bytes(),
+                mtime: note.timestamp.into(),
+                dirty: false,
+            });
+            next_inode += 1;
+        }
+        
+        Self {
+            state: Mutex::new(MountState { manager, files, next_inode }),
+        }
+    }
+    
+    fn attr(inode: u64, file: &NoteFile, req: &Request) -> FileAttr {
+        FileAttr {
+            ino: INodeNo(inode),
+            size: file.data.len() as u64,
+            blocks: (file.data.len() as u64).div_ceil(512),
+            atime: file.mtime,
+            mtime: file.mtime,
+            ctime: file.mtime,
+            crtime: file.mtime,
+            kind: FileType::RegularFile,
+            perm: 0o644,
+            nlink: 1,
+            uid: req.uid(),
+            gid: req.gid(),
+            rdev: 0,
+            blksize: 512,
+            flags: 0,
+        }
+    }
+    
+    fn root_attr(req: &Request) -> FileAttr {
+        FileAttr {
+            ino: INodeNo::ROOT,
+            size: 0,
+            blocks: 0,
+            atime: SystemTime::now(),
+            mtime: SystemTime::now(),
+            ctime: SystemTime::now(),
+            crtime: SystemTime::now(),
+            kind: FileType::Directory,
+            perm: 0o755,
+            nlink: 2,
+            uid: req.uid(),
+            gid: req.gid(),
+            rdev: 0,
+            blksize: 512,
+            flags: 0,
+        }
+    }
+    
+    fn find_by_name<'a>(state: &'a MountState, name: &OsStr) -> Option<(u64, &'a NoteFile)> {
+        let name = name.to_str()?;
+        state.files.iter()
+            .find(|(_, file)| file.name == name)
+            .map(|(inode, file)| (*inode, file))
+    }
+    
+    // Writes a modified file back to the store. New files become notes on
+    // their first non-empty save.
+    fn sync(state: &mut MountState, inode: u64) -> std::result::Result<(), Errno> {
+        let MountState { manager, files, .. } = state;
+        let file = files.get_mut(&inode).ok_or(Errno::ENOENT)?;
+        if !file.dirty {
+            return Ok(());
+        }
+        
+        let text = std::str::from_utf8(&file.data).map_err(|_| Errno::EINVAL)?;
+        let content = text.strip_suffix('\n').unwrap_or(text).to_string();
+        let saved = match &file.id {
+            Some(id) => manager.update_content(id, content).map(|_| ()),
+            None if content.trim().is_empty() => Ok(()),
+            None => manager.add_note(content, &[]).map(|id| file.id = Some(id)),
+        };
+        
+        if let Err(e) = saved {
+            eprintln!("{} Failed to save {}: {:#}", glyphs().error.red(), file.name, e);
+            return Err(Errno::EIO);
+        }
+        file.dirty = false;
+        Ok(())
+    }
+}
+
+impl Filesystem for NoteFs {
+    fn lookup(&self, req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEntry) {
+        if parent != INodeNo::ROOT {
+            reply.error(Errno::ENOENT);
+            return;
+        }
+        let state = self.state.lock().unwrap();
+        match Self::find_by_name(&state, name) {
+            Some((inode, file)) => reply.entry(&TTL, &Self::attr(inode, file, req), Generation(0)),
+            None => reply.error(Errno::ENOENT),
+        }
+    }
+    
+    fn getattr(&self, req: &Request, ino: INodeNo, _fh: Option<FileHandle>, reply: ReplyAttr) {
+        if ino == INodeNo::ROOT {
+            reply.attr(&TTL, &Self::root_attr(req));
+            return;
+        }
+        let state = self.state.lock().unwrap();
+        match state.files.get(&ino.0) {
+            Some(file) => reply.attr(&TTL, &Self::attr(ino.0, file, req)),
+            None => reply.error(Errno::ENOENT),
+        }
+    }
+    
+    fn setattr(
+        &self,
+        req: &Request,
+        ino: INodeNo,
+        _mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        _fh: Option<FileHandle>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<fuser::BsdFileFlags>,
+        reply: ReplyAttr,
+    ) {
+        if ino == INodeNo::ROOT {
+            reply.attr(&TTL, &Self::root_attr(req));
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let Some(file) = state.files.get_mut(&ino.0) else {
+            reply.error(Errno::ENOENT);
+            return;
+        };
+        
+        // Only truncation matters; ownership and mode are fixed
+        if let Some(size) = size {
+            file.data.resize(size as usize, 0);
+            file.mtime = SystemTime::now();
+            file.dirty = true;
+        }
+        reply.attr(&TTL, &Self::attr(ino.0, file, req));
+    }
+    
+    fn readdir(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, offset: u64, mut reply: ReplyDirectory) {
+        if ino != INodeNo::ROOT {
+            reply.error(Errno::ENOTDIR);
+            return;
+        }
+        let state = self.state.lock().unwrap();
+        let mut entries = vec![
+            (INodeNo::ROOT, FileType::Directory, ".".to_string()),
+            (INodeNo::ROOT, FileType::Directory, "..".to_string()),
+        ];
+        entries.extend(state.files.iter()
+            .map(|(inode, file)| (INodeNo(*inode), FileType::RegularFile, file.name.clone())));
+        
+        for (index, (inode, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            // The offset passed back in is the index of the next entry
+            if reply.add(inode, (index + 1) as u64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+    
+    fn open(&self, _req: &Request, ino: INodeNo, _flags: OpenFlags, reply: ReplyOpen) {
+        let state = self.state.lock().unwrap();
+        if state.files.contains_key(&ino.0) {
+            reply.opened(FileHandle(0), FopenFlags::empty());
+        } else {
+            reply.error(Errno::ENOENT);
+        }
+    }
+    
+    fn read(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _fh: FileHandle,
+        offset: u64,
+        size: u32,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyData,
+    ) {
+        let state = self.state.lock().unwrap();
+        let Some(file) = state.files.get(&ino.0) else {
+            reply.error(Errno::ENOENT);
+            return;
+        };
+        let start = (offset as usize).min(file.data.len());
+        let end = (start + size as usize).min(file.data.len());
+        reply.data(&file.data[start..end]);
+    }
+    
+    fn write(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _fh: FileHandle,
+        offset: u64,
+        data: &[u8],
+        _write_flags: WriteFlags,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        reply: ReplyWrite,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let Some(file) = state.files.get_mut(&ino.0) else {
+            reply.error(Errno::ENOENT);
+            return;
+        };
+        let start = offset as usize;
+        if file.data.len() < start + data.len() {
+            file.data.resize(start + data.len(), 0);
+        }
+        file.data[start..start + data.len()].copy_from_slice(data);
+        file.mtime = SystemTime::now();
+        file.dirty = true;
+        reply.written(data.len() as u32);
+    }
+    
+    fn flush(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, _lock_owner: LockOwner, reply: ReplyEmpty) {
+        let mut state = self.state.lock().unwrap();
+        match Self::sync(&mut state, ino.0) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+    
+    fn release(
+        &self,
+        _req: &Request,
+        ino: INodeNo,
+        _fh: FileHandle,
+        _flags: OpenFlags,
+        _lock_owner: Option<LockOwner>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        match Self::sync(&mut state, ino.0) {
+            Ok(()) | Err(Errno::ENOENT) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+    
+    fn fsync(&self, _req: &Request, ino: INodeNo, _fh: FileHandle, _datasync: bool, reply: ReplyEmpty) {
+        let mut state = self.state.lock().unwrap();
+        match Self::sync(&mut state, ino.0) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+    
+    // Creating a .txt file adds a note; anything else (editor swap and
+    // backup files) is refused so it can't end up in the store
+    fn create(
+        &self,
+        req: &Request,
+        parent: INodeNo,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let Some(name) = name.to_str().filter(|name| name.ends_with(".txt")) else {
+            reply.error(Errno::EACCES);
+            return;
+        };
+        if parent != INodeNo::ROOT {
+            reply.error(Errno::ENOENT);
+            return;
+        }
+        
+        let mut state = self.state.lock().unwrap();
+        if state.files.values().any(|file| file.name == name) {
+            reply.error(Errno::EEXIST);
+            return;
+        }
+        let inode = state.next_inode;
+        state.next_inode += 1;
+        let file = NoteFile {
+            id: None,
+            name: name.to_string(),
+            data: Vec::new(),
+            mtime: SystemTime::now(),
+            dirty: false,
+        };
+        reply.created(&TTL, &Self::attr(inode, &file, req), Generation(0), FileHandle(0), FopenFlags::empty());
+        state.files.insert(inode, file);
+    }
+    
+    // Deleting a file removes its note
+    fn unlink(&self, _req: &Request, parent: INodeNo, name: &OsStr, reply: ReplyEmpty) {
+        let mut state = self.state.lock().unwrap();
+        let Some((inode, _)) = (parent == INodeNo::ROOT)
+            .then(|| Self::find_by_name(&state, name))
+            .flatten() else {
+            reply.error(Errno::ENOENT);
+            return;
+        };
+        
+        let file = state.files.remove(&inode).expect("inode was just found");
+        if let Some(id) = file.id
+            && let Err(e) = state.manager.remove_note_by_id(&id) {
+            eprintln!("{} Failed to remove {}: {:#}", glyphs().error.red(), file.name, e);
+            reply.error(Errno::EIO);
+            return;
+        }
+        reply.ok();
+    }
+}
+
+// Serves the notes at `mountpoint` until the filesystem is unmounted
+pub fn mount(manager: NoteManager, mountpoint: &Path) -> Result<()> {
+    let mut config = Config::default();
+    config.mount_options.extend([
+        MountOption::FSName("note".to_string()),
+        MountOption::Subtype("note".to_string()),
+        MountOption::DefaultPermissions,
+        MountOption::NoExec,
+    ]);
+    fuser::mount(NoteFs::new(manager), mountpoint, &config)
+        .with_context(|| format!("Failed to mount notes at {}", mountpoint.display()))
+}
\ No newline at end of file
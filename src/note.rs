@@ -1,24 +1,158 @@
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
+use crate::slug;
+
+// Metadata keys managed by dedicated commands rather than `note meta`
+pub const RESERVED_META_KEYS: &[&str] = &[
+    "tags", "attach", "srs", "clock", "clock-label", "clock-pair", "clock-secs", "read",
+    "inbox", "snooze", "archived", "viewed", "recap", "journal",
+    "kind", "body",
+];
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Note {
     pub id: String,
     pub content: String,
     pub timestamp: DateTime<Local>,
+    pub meta: BTreeMap<String, String>,
 }
 
 impl Note {
-    pub fn new(content: String, existing_ids: &[String]) -> Self {
-        let timestamp = Local::now();
+    // A note written at `timestamp`, as given by a NoteManager's clock
+    pub fn new(content: String, existing_ids: &[String], timestamp: DateTime<Local>) -> Self {
         let id = Self::generate_unique_id(&content, &timestamp, existing_ids);
         Self {
             id,
             content,
             timestamp,
+            meta: BTreeMap::new(),
+        }
+    }
+    
+    // User-defined key-value pairs, excluding fields the tool manages itself
+    pub fn custom_meta(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.meta.iter().filter(|(key, _)| !RESERVED_META_KEYS.contains(&key.as_str()))
+    }
+    
+    pub fn tags(&self) -> Vec<String> {
+        self.meta_list("tags")
+    }
+    
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| t == tag)
+    }
+    
+    // The tag itself or one nested under it, so `work` matches `work/infra`
+    pub fn has_tag_under(&self, tag: &str) -> bool {
+        self.tags().iter().any(|t| Self::is_tag_under(t, tag))
+    }
+    
+    pub fn is_tag_under(tag: &str, parent: &str) -> bool {
+        tag.strip_prefix(parent).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+    
+    pub fn set_tags(&mut self, tags: &[String]) {
+        // Keep the first occurrence of each tag, preserving order
+        let mut unique: Vec<String> = Vec::new();
+        for tag in tags {
+            if !unique.contains(tag) {
+                unique.push(tag.clone());
+            }
+        }
+        self.set_meta_list("tags", &unique);
+    }
+    
+    // Replaces `from` with `to` (or drops it when `to` is None).
+    // Returns true if the note carried the tag.
+    pub fn retag(&mut self, from: &str, to: Option<&str>) -> bool {
+        if !self.has_tag(from) {
+            return false;
+        }
+        let tags: Vec<String> = self.tags().into_iter()
+            .filter_map(|t| if t == from { to.map(|t| t.to_string()) } else { Some(t) })
+            .collect();
+        self.set_tags(&tags);
+        true
+    }
+    
+    // The first line as a file name, see slug::from_line(). Empty when the
+    // line has no letters or digits.
+    pub fn slug(&self) -> String {
+        slug::from_line(self.content.lines().next().unwrap_or(""))
+    }
+    
+    // `<id>-<slug>.txt`, or `<id>.txt` when the first line has no slug
+    pub fn file_name(&self) -> String {
+        let slug = self.slug();
+        if slug.is_empty() {
+            format!("{}.txt", self.id)
+        } else {
+            format!("{}-{}.txt", self.id, slug)
+        }
+    }
+    
+    // Tags nest with '/', as in `work/projectx/infra`; stray slashes are dropped
+    pub fn normalize_tag(tag: &str) -> String {
+        tag.trim().trim_start_matches('#').replace([',', ' '], "-")
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+    
+    pub fn attachments(&self) -> Vec<Attachment> {
+        self.meta_list("attach")
+            .iter()
+            .filter_map(|entry| Attachment::parse(entry))
+            .collect()
+    }
+    
+    pub fn add_attachment(&mut self, attachment: &Attachment) {
+        let mut entries = self.meta_list("attach");
+        let entry = attachment.to_string();
+        if !entries.contains(&entry) {
+            entries.push(entry);
+            self.set_meta_list("attach", &entries);
+        }
+    }
+    
+    // List-valued metadata is stored comma separated in a single header field
+    pub fn meta_list(&self, key: &str) -> Vec<String> {
+        self.meta.get(key)
+            .map(|value| value.split(',').filter(|s| !s.is_empty()).map(|s| s.to_string()).collect())
+            .unwrap_or_default()
+    }
+    
+    pub fn set_meta_list(&mut self, key: &str, values: &[String]) {
+        if values.is_empty() {
+            self.meta.remove(key);
+        } else {
+            self.meta.insert(key.to_string(), values.join(","));
+        }
+    }
+    
+    // An ID for a note written at `timestamp`, as Note::new gives it
+    pub fn new_id(content: &str, timestamp: &DateTime<Local>, existing_ids: &[String]) -> String {
+        Self::generate_unique_id(content, timestamp, existing_ids)
+    }
+    
+    // Replacement ID for an imported note whose own ID is taken. Derived from
+    // the content and original timestamp rather than the current time, so
+    // importing the same file into the same store always gives the same IDs.
+    pub fn derive_id(content: &str, timestamp: &DateTime<Local>, taken: &HashSet<String>) -> String {
+        let hash = |counter: u32| blake3::hash(format!("{}\n{}\n{}", timestamp.to_rfc3339(), counter, content).as_bytes()).to_hex();
+        for counter in 0..=65536u32 {
+            let id = hash(counter)[..4].to_string();
+            if !taken.contains(&id) {
+                return id;
+            }
         }
+        // Every short ID is taken; fall back to a longer one
+        hash(0)[..8].to_string()
     }
     
     fn generate_unique_id(content: &str, timestamp: &DateTime<Local>, existing_ids: &[String]) -> String {
@@ -53,4 +187,35 @@ pub enum RemoveResult {
     Removed(String),
     NotFound,
     Ambiguous(Vec<String>),
+}
+
+#[derive(Debug)]
+pub enum LookupResult {
+    Found(String),
+    NotFound,
+    Ambiguous(Vec<String>),
+}
+
+// A file attached to a note, referenced by the hash of its content
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub hash: String,
+    pub name: String,
+}
+
+impl Attachment {
+    fn parse(entry: &str) -> Option<Self> {
+        let (hash, name) = entry.split_once(':')?;
+        Some(Self {
+            hash: hash.to_string(),
+            name: name.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Attachment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Names may not contain the list separator used by the header
+        write!(f, "{}:{}", self.hash, self.name.replace(',', "_"))
+    }
 }
\ No newline at end of file
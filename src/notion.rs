@@ -0,0 +1,189 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::encoding;
+
+// A page flattened out of a Notion "Markdown & CSV" export
+pub struct NotionPage {
+    pub title: String,
+    pub content: String,
+    // Title of the page or database this page was nested under
+    pub parent: Option<String>,
+}
+
+// Reads an export either as the downloaded zip or as an extracted directory
+pub fn read_export(path: &Path) -> Result<Vec<NotionPage>> {
+    let files = if path.is_dir() {
+        read_dir_files(path)?
+    } else {
+        read_zip_files(path)?
+    };
+    
+    let mut pages: Vec<NotionPage> = files.iter()
+        .filter(|(path, _)| has_extension(path, "md"))
+        .map(|(path, text)| markdown_page(path, text))
+        .collect();
+    
+    // Database rows are usually exported as pages too; only rows without a
+    // page of their own are taken from the CSV
+    for (path, text) in files.iter().filter(|(path, _)| has_extension(path, "csv")) {
+        let database = file_title(path);
+        for row in csv_rows(text, &database) {
+            let exists = pages.iter().any(|p| p.title == row.title && p.parent.as_deref() == Some(&database));
+            if !exists {
+                pages.push(row);
+            }
+        }
+    }
+    
+    Ok(pages)
+}
+
+fn read_zip_files(path: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let file = fs::File::open(path)
+        .context(format!("Failed to open file: {}", path.display()))?;
+    let mut archive = zip::ZipArchive::new(file)
+        .context("Failed to read Notion export archive")?;
+    
+    let mut files = Vec::new();
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        if entry.is_dir() || !(has_extension(&name, "md") || has_extension(&name, "csv")) {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes)?;
+        let text = encoding::decode_with_warning(&bytes, &name.display().to_string());
+        files.push((name, text));
+    }
+    Ok(files)
+}
+
+fn read_dir_files(root: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).context(format!("Failed to read directory: {}", dir.display()))? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if has_extension(&path, "md") || has_extension(&path, "csv") {
+                let text = encoding::read(&path)?;
+                let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+                files.push((relative, text));
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn markdown_page(path: &Path, text: &str) -> NotionPage {
+    let title = file_title(path);
+    let text = text.trim_start_matches('\u{feff}').trim();
+    
+    // Pages start with "# Title"; turn that into a plain first line
+    let body = match text.lines().next() {
+        Some(first) if first.trim_start_matches('#').trim() == title => {
+            text.lines().skip(1).collect::<Vec<_>>().join("\n")
+        }
+        _ => text.to_string(),
+    };
+    let body = body.trim();
+    
+    NotionPage {
+        content: if body.is_empty() { title.clone() } else { format!("{}\n{}", title, body) },
+        title,
+        parent: parent_title(path),
+    }
+}
+
+fn csv_rows(text: &str, database: &str) -> Vec<NotionPage> {
+    let mut records = parse_csv(text.trim_start_matches('\u{feff}')).into_iter();
+    let Some(headers) = records.next() else {
+        return Vec::new();
+    };
+    
+    records
+        .filter_map(|record| {
+            let title = record.first()?.trim().to_string();
+            if title.is_empty() {
+                return None;
+            }
+            let mut lines = vec![title.clone()];
+            for (header, value) in headers.iter().zip(&record).skip(1) {
+                if !value.trim().is_empty() {
+                    lines.push(format!("{}: {}", header, value.trim()));
+                }
+            }
+            Some(NotionPage {
+                title,
+                content: lines.join("\n"),
+                parent: Some(database.to_string()),
+            })
+        })
+        .collect()
+}
+
+// Minimal RFC 4180 parsing: quoted fields may contain commas, quotes and newlines
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+fn has_extension(path: &Path, extension: &str) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
+
+fn file_title(path: &Path) -> String {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    strip_notion_id(&stem)
+}
+
+fn parent_title(path: &Path) -> Option<String> {
+    let parent = path.parent()?.file_name()?.to_string_lossy().to_string();
+    let title = strip_notion_id(&parent);
+    // Top-level export folders carry no page information
+    let top_level = path.parent()?.parent().is_none_or(|p| p.as_os_str().is_empty());
+    if title.is_empty() || (parent == title && top_level) {
+        return None;
+    }
+    Some(title)
+}
+
+// Notion appends a 32 character hex ID to every exported file and folder name
+fn strip_notion_id(name: &str) -> String {
+    match name.rsplit_once(' ') {
+        Some((title, id)) if id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()) => title.to_string(),
+        _ => name.to_string(),
+    }
+}
\ No newline at end of file
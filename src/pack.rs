@@ -0,0 +1,204 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::index;
+use crate::note::Note;
+use crate::parser::{FileFormat, NoteParser};
+use crate::resurface;
+use crate::timezone;
+
+// `note pack` and `note unpack` carry the notes between two machines that
+// never see each other, e.g. on a USB stick. A bundle is every note plus a
+// tombstone for each note deleted, zstd-compressed:
+//
+//   %note-bundle 1
+//   packed <timestamp>
+//   deleted <note id> <hash>
+//
+//   <the notes, as in notes.txt>
+//
+// pack-state.txt next to notes.txt remembers the hash of every version of
+// each note seen in a pack or unpack, the last one as of the latest, which
+// tells which side changed a note since and keeps an old bundle unpacked
+// again from undoing newer edits. It also keeps the tombstones so far, so a
+// machine that missed a bundle still hears of its deletions from the next.
+const BUNDLE_HEADER: &str = "%note-bundle 1";
+const STATE_HEADER: &str = "%note-pack";
+pub const STATE_FILE: &str = "pack-state.txt";
+
+// Covers everything but the ID, which may differ after a clash, and the
+// view time, which changes on every `note show` and would keep a note viewed
+// on one machine from matching the other's tombstone or version
+pub fn hash(note: &Note) -> String {
+    let meta: Vec<String> = note.meta.iter()
+        .filter(|(key, _)| key.as_str() != resurface::VIEWED_KEY)
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect();
+    index::content_hash(&format!("{}\n{}\n{}", timezone::storage(&note.timestamp), meta.join("\n"), note.content))
+}
+
+#[derive(Default)]
+pub struct PackState {
+    // Hashes of the versions of each note by ID, the latest last
+    pub base: BTreeMap<String, Vec<String>>,
+    // IDs of deleted notes by their hash
+    pub deleted: BTreeMap<String, String>,
+}
+
+impl PackState {
+    pub fn load(path: &Path) -> Self {
+        let mut state = Self::default();
+        let Ok(content) = fs::read_to_string(path) else {
+            return state;
+        };
+        let mut lines = content.lines();
+        if lines.next() != Some(STATE_HEADER) {
+            return state;
+        }
+        for line in lines {
+            let mut parts = line.split(' ');
+            match (parts.next(), parts.next()) {
+                (Some("note"), Some(id)) => {
+                    state.base.insert(id.to_string(), parts.map(str::to_string).collect());
+                }
+                (Some("deleted"), Some(id)) => {
+                    if let Some(hash) = parts.next() {
+                        state.deleted.insert(hash.to_string(), id.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        state
+    }
+    
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut content = format!("{}\n", STATE_HEADER);
+        for (id, hashes) in &self.base {
+            content.push_str(&format!("note {} {}\n", id, hashes.join(" ")));
+        }
+        for (hash, id) in &self.deleted {
+            content.push_str(&format!("deleted {} {}\n", id, hash));
+        }
+        fs::write(path, content).context("Failed to save pack state")
+    }
+    
+    pub fn latest(&self, id: &str) -> Option<&String> {
+        self.base.get(id).and_then(|hashes| hashes.last())
+    }
+    
+    pub fn has_seen(&self, id: &str, hash: &str) -> bool {
+        self.base.get(id).is_some_and(|hashes| hashes.iter().any(|seen| seen == hash))
+    }
+    
+    // Notes deleted since the last pack or unpack become tombstones, for
+    // every version of them seen
+    pub fn bury(&mut self, notes: &[Note]) {
+        let ids: HashSet<&str> = notes.iter().map(|note| note.id.as_str()).collect();
+        for (id, hashes) in &self.base {
+            if !ids.contains(id.as_str()) {
+                for hash in hashes {
+                    self.deleted.insert(hash.clone(), id.clone());
+                }
+            }
+        }
+        self.base.retain(|id, _| ids.contains(id.as_str()));
+    }
+    
+    // Takes the notes as they are now as the latest versions
+    pub fn record(&mut self, notes: &[Note]) {
+        self.bury(notes);
+        for note in notes {
+            let hash = hash(note);
+            let hashes = self.base.entry(note.id.clone()).or_default();
+            hashes.retain(|seen| *seen != hash);
+            hashes.push(hash);
+        }
+    }
+    
+    // A version seen but not kept as it is, e.g. merged with ours
+    pub fn remember(&mut self, id: &str, hash: String) {
+        let hashes = self.base.entry(id.to_string()).or_default();
+        if !hashes.contains(&hash) {
+            hashes.insert(hashes.len().saturating_sub(1), hash);
+        }
+    }
+}
+
+pub struct Bundle {
+    pub packed: String,
+    pub notes: Vec<Note>,
+    pub deleted: BTreeMap<String, String>,
+}
+
+pub fn write(path: &Path, notes: &[Note], deleted: &BTreeMap<String, String>, packed: DateTime<Local>) -> Result<()> {
+    let mut content = format!("{}\npacked {}\n", BUNDLE_HEADER, timezone::storage(&packed));
+    for (hash, id) in deleted {
+        content.push_str(&format!("deleted {} {}\n", id, hash));
+    }
+    content.push('\n');
+    let notes: Vec<&Note> = notes.iter().collect();
+    content.push_str(&NoteParser::format_notes(&notes, FileFormat::V2));
+    let compressed = zstd::encode_all(content.as_bytes(), 0).context("Failed to compress the bundle")?;
+    fs::write(path, compressed).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+pub fn read(path: &Path, now: DateTime<Local>) -> Result<Bundle> {
+    let compressed = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let content = zstd::decode_all(compressed.as_slice()).ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .filter(|content| content.starts_with(BUNDLE_HEADER));
+    let Some(content) = content else {
+        bail!("{} isn't a bundle from `note pack`", path.display());
+    };
+    let (header, notes) = content.split_once("\n\n").unwrap_or((&content, ""));
+    let mut bundle = Bundle { packed: String::new(), notes: Vec::new(), deleted: BTreeMap::new() };
+    for line in header.lines().skip(1) {
+        let mut parts = line.split(' ');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("packed"), Some(packed), None) => bundle.packed = packed.to_string(),
+            (Some("deleted"), Some(id), Some(hash)) => {
+                bundle.deleted.insert(hash.to_string(), id.to_string());
+            }
+            _ => {}
+        }
+    }
+    if !notes.trim().is_empty() {
+        bundle.notes = NoteParser::parse_notes_from_text(notes, now)
+            .with_context(|| format!("Failed to parse the notes in {}", path.display()))?;
+    }
+    Ok(bundle)
+}
+
+// An ID for a note that clashes with a different note on the other machine.
+// Both machines work out the same one, since it only depends on the note and
+// the IDs in use on either, leaving out copies of the note itself.
+pub fn fresh_id(note: &Note, ours: &[Note], theirs: &[Note]) -> String {
+    let taken: HashSet<String> = ours.iter().chain(theirs)
+        .filter(|other| other.timestamp != note.timestamp)
+        .map(|other| other.id.clone())
+        .collect();
+    Note::derive_id(&note.content, &note.timestamp, &taken)
+}
+
+#[derive(Default)]
+pub struct UnpackReport {
+    pub added: usize,
+    pub updated: usize,
+    // Deleted on the other machine and unchanged here
+    pub removed: usize,
+    // Notes of ours given a new ID because an older note on the other
+    // machine has theirs: (old, new)
+    pub renamed: Vec<(String, String)>,
+    // Edited on both machines, kept with conflict markers
+    pub conflicts: Vec<String>,
+}
+
+impl UnpackReport {
+    pub fn changed(&self) -> bool {
+        self.added + self.updated + self.removed + self.renamed.len() + self.conflicts.len() > 0
+    }
+}
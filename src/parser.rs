@@ -1,11 +1,188 @@
 use anyhow::Result;
 use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
 use crate::note::Note;
+use crate::timezone;
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One note as Markdown with YAML front matter
+    Markdown,
+    /// Todo notes and checkboxes as JSON for `task import`
+    Taskwarrior,
+    /// A static site with an index page and one page per note
+    Html,
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum SplitBy {
+    /// Each paragraph separated by blank lines becomes a note
+    BlankLine,
+    /// Each non-empty line becomes a note
+    Line,
+    /// The whole file becomes a single note
+    None,
+}
+
+// How note content is told apart from headers in notes.txt.
+//
+// Format 1 treats any line starting with '#' after leading whitespace as a
+// header and escapes such content lines as `\#`, which loses content that
+// itself starts with `\#`.
+//
+// Format 2 files start with FORMAT_2_MARKER. Only lines with '#' in the first
+// column are headers, and content lines of the form `\...\#...` (any number
+// of backslashes, including none, then '#') get one more backslash, which
+// reading strips again, so every line round-trips exactly.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FileFormat {
+    V1,
+    V2,
+}
+
+pub const FORMAT_2_MARKER: &str = "%note-format 2";
 
 pub struct NoteParser;
 
 impl NoteParser {
-    pub fn parse_notes_from_text(content: &str) -> Result<Vec<Note>> {
+    pub fn detect_format(content: &str) -> FileFormat {
+        if content.lines().next().is_some_and(|line| line.trim_end() == FORMAT_2_MARKER) {
+            FileFormat::V2
+        } else {
+            FileFormat::V1
+        }
+    }
+    
+    // Reads either format, going by the marker line. Notes whose date can't be
+    // read are dated `now`.
+    pub fn parse_notes_from_text(content: &str, now: DateTime<Local>) -> Result<Vec<Note>> {
+        match Self::detect_format(content) {
+            FileFormat::V1 => Self::parse_v1(content, now),
+            FileFormat::V2 => Ok(Self::parse_v2(content, now)),
+        }
+    }
+    
+    // The 1-based line each of `notes`, as parsed from `content`, starts on,
+    // plus the header lines that gave no note and why
+    pub fn header_lines(content: &str, notes: &[Note]) -> (Vec<usize>, Vec<(usize, &'static str)>) {
+        let format = Self::detect_format(content);
+        let mut starts = Vec::new();
+        let mut dropped = Vec::new();
+        let mut remaining = notes.iter().peekable();
+        for (index, line) in content.lines().enumerate() {
+            let is_header = match format {
+                FileFormat::V1 => line.trim().starts_with('#'),
+                FileFormat::V2 => index > 0 && line.starts_with('#'),
+            };
+            if !is_header {
+                continue;
+            }
+            // Only the ID is compared, so the fallback date doesn't matter
+            match Self::parse_header(line.trim(), DateTime::default()) {
+                Some(header) if remaining.peek().is_some_and(|note| note.id == header.id) => {
+                    starts.push(index + 1);
+                    remaining.next();
+                }
+                Some(_) => dropped.push((index + 1, "empty note")),
+                None => dropped.push((index + 1, "malformed header")),
+            }
+        }
+        (starts, dropped)
+    }
+    
+    // The whole notes file in `format`
+    pub fn format_notes(notes: &[&Note], format: FileFormat) -> String {
+        let mut content = String::new();
+        if format == FileFormat::V2 {
+            content.push_str(FORMAT_2_MARKER);
+            content.push('\n');
+        }
+        
+        for (index, note) in notes.iter().enumerate() {
+            if index > 0 {
+                content.push('\n');
+            }
+            content.push_str(&Self::format_note(note, format));
+        }
+        content
+    }
+    
+    // One note's header line and content, as it appears in the file
+    pub fn format_note(note: &Note, format: FileFormat) -> String {
+        let escaped_content = match format {
+            FileFormat::V1 => Self::escape_content(&note.content),
+            FileFormat::V2 => Self::escape_content_v2(&note.content),
+        };
+        // Header line: #id timestamp [key=value ...]
+        format!("{}\n{}\n", Self::format_header(note, format), escaped_content)
+    }
+    
+    // The IDs in the file's header lines, without parsing the notes
+    pub fn scan_ids(content: &str) -> Vec<String> {
+        let format = Self::detect_format(content);
+        content.lines()
+            .filter(|line| match format {
+                FileFormat::V1 => line.trim_start().starts_with('#'),
+                FileFormat::V2 => line.starts_with('#'),
+            })
+            .filter_map(|line| line.trim_start()[1..].split_whitespace().next())
+            .map(str::to_string)
+            .collect()
+    }
+    
+    fn parse_v2(content: &str, now: DateTime<Local>) -> Vec<Note> {
+        let mut notes = Vec::new();
+        let mut current: Option<(Note, Vec<&str>)> = None;
+        let mut finish = |current: Option<(Note, Vec<&str>)>| {
+            if let Some((mut note, lines)) = current {
+                note.content = Self::unescape_content_v2(&lines.join("\n")).trim().to_string();
+                if !note.content.is_empty() {
+                    notes.push(note);
+                }
+            }
+        };
+        
+        for line in content.lines().skip(1) {
+            if line.starts_with('#') {
+                finish(current.take());
+                // Malformed headers are dropped along with their content, as in format 1
+                current = Self::parse_header(line, now).map(|note| (note, Vec::new()));
+            } else if let Some((_, lines)) = &mut current {
+                lines.push(line);
+            }
+        }
+        finish(current);
+        notes
+    }
+    
+    // `#id date [key=value ...]` as a note without content
+    fn parse_header(line: &str, now: DateTime<Local>) -> Option<Note> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 2 {
+            return None;
+        }
+        let id = parts[0][1..].to_string(); // Remove the # prefix
+        let (meta_parts, date_parts): (Vec<&str>, Vec<&str>) = parts[1..].iter()
+            .partition(|part| Self::is_meta_field(part));
+        let date_str = date_parts.join(" ");
+        let meta = Self::parse_meta_fields(&meta_parts);
+        
+        // Parse the timestamp
+        let timestamp = DateTime::parse_from_rfc3339(&date_str)
+            .or_else(|_| DateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S %z"))
+            .or_else(|_| DateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S"))
+            .map(|dt| dt.with_timezone(&Local))
+            .or_else(|_| {
+                // Try parsing simple date formats and assume current time
+                Self::parse_simple_date(&date_str)
+                    .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_local_timezone(Local).single().unwrap())
+            })
+            .unwrap_or(now);
+        
+        Some(Note { id, content: String::new(), timestamp, meta })
+    }
+    
+    fn parse_v1(content: &str, now: DateTime<Local>) -> Result<Vec<Note>> {
         let mut notes = Vec::new();
         let lines: Vec<&str> = content.lines().collect();
         let mut i = 0;
@@ -20,49 +197,24 @@ impl NoteParser {
             }
             
             // Look for lines starting with #
-            if line.starts_with('#') {
-                // Parse the header line: #id date
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                if parts.len() >= 2 {
-                    let id = parts[0][1..].to_string(); // Remove the # prefix
-                    let date_str = parts[1..].join(" ");
-                    
-                    // Parse the timestamp
-                    let timestamp = DateTime::parse_from_rfc3339(&date_str)
-                        .or_else(|_| DateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S %z"))
-                        .or_else(|_| DateTime::parse_from_str(&date_str, "%Y-%m-%d %H:%M:%S"))
-                        .map(|dt| dt.with_timezone(&Local))
-                        .or_else(|_| {
-                            // Try parsing simple date formats and assume current time
-                            Self::parse_simple_date(&date_str)
-                                .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_local_timezone(Local).single().unwrap())
-                        })
-                        .unwrap_or_else(|_| Local::now());
-                    
-                    // Collect content lines until next note or end of file
-                    let mut content_lines = Vec::new();
-                    i += 1;
-                    
-                    while i < lines.len() {
-                        let content_line = lines[i];
-                        if content_line.trim().starts_with('#') && !content_line.trim_start().starts_with("\\#") {
-                            break;
-                        }
-                        content_lines.push(content_line);
-                        i += 1;
-                    }
-                    
-                    let content = Self::unescape_content(&content_lines.join("\n")).trim().to_string();
-                    if !content.is_empty() {
-                        notes.push(Note {
-                            id,
-                            content,
-                            timestamp,
-                        });
+            if line.starts_with('#') && let Some(header) = Self::parse_header(line, now) {
+                // Collect content lines until next note or end of file
+                let mut content_lines = Vec::new();
+                i += 1;
+                
+                while i < lines.len() {
+                    let content_line = lines[i];
+                    if content_line.trim().starts_with('#') && !content_line.trim_start().starts_with("\\#") {
+                        break;
                     }
-                } else {
+                    content_lines.push(content_line);
                     i += 1;
                 }
+                
+                let content = Self::unescape_content(&content_lines.join("\n")).trim().to_string();
+                if !content.is_empty() {
+                    notes.push(Note { content, ..header });
+                }
             } else {
                 i += 1;
             }
@@ -71,6 +223,135 @@ impl NoteParser {
         Ok(notes)
     }
     
+    // Date formats never contain '=', so any key=value token is metadata
+    fn is_meta_field(part: &str) -> bool {
+        match part.split_once('=') {
+            Some((key, _)) => Self::is_valid_meta_key(key),
+            None => false,
+        }
+    }
+    
+    pub fn is_valid_meta_key(key: &str) -> bool {
+        !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+    
+    fn parse_meta_fields(parts: &[&str]) -> BTreeMap<String, String> {
+        parts.iter()
+            .filter_map(|part| part.split_once('='))
+            .map(|(key, value)| (key.to_string(), Self::decode_meta_value(value)))
+            .collect()
+    }
+    
+    // Format 1 keeps the local offsets it always had, so saving an old file
+    // doesn't rewrite every header; `note migrate` moves it to UTC
+    pub fn format_header(note: &Note, format: FileFormat) -> String {
+        let timestamp = match format {
+            FileFormat::V1 => note.timestamp.to_rfc3339(),
+            FileFormat::V2 => timezone::storage(&note.timestamp),
+        };
+        let mut header = format!("#{} {}", note.id, timestamp);
+        for (key, value) in &note.meta {
+            header.push_str(&format!(" {}={}", key, Self::encode_meta_value(value)));
+        }
+        header
+    }
+    
+    // YAML front matter for a note exported on its own. Only what is useful
+    // outside the store is included; scheduling and clock fields are left out.
+    pub fn format_front_matter(note: &Note) -> String {
+        let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"));
+        let list = |values: Vec<String>| format!("[{}]", values.iter().map(|v| quote(v)).collect::<Vec<_>>().join(", "));
+        
+        let mut front_matter = String::from("---\n");
+        front_matter.push_str(&format!("id: {}\n", quote(&note.id)));
+        front_matter.push_str(&format!("date: {}\n", note.timestamp.to_rfc3339()));
+        let tags = note.tags();
+        if !tags.is_empty() {
+            front_matter.push_str(&format!("tags: {}\n", list(tags)));
+        }
+        let attachments = note.attachments();
+        if !attachments.is_empty() {
+            front_matter.push_str(&format!("attachments: {}\n", list(attachments.into_iter().map(|a| a.name).collect())));
+        }
+        for (key, value) in note.custom_meta() {
+            front_matter.push_str(&format!("{}: {}\n", key, quote(value)));
+        }
+        front_matter.push_str("---\n");
+        front_matter
+    }
+    
+    // Percent-encode characters that would break the whitespace separated header
+    fn encode_meta_value(value: &str) -> String {
+        let mut encoded = String::new();
+        for c in value.chars() {
+            if c == '%' || c == '=' || c.is_whitespace() || c.is_control() {
+                let mut buf = [0u8; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    encoded.push_str(&format!("%{:02X}", byte));
+                }
+            } else {
+                encoded.push(c);
+            }
+        }
+        encoded
+    }
+    
+    fn decode_meta_value(value: &str) -> String {
+        let bytes = value.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                if let Some(byte) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    decoded.push(byte);
+                    i += 3;
+                    continue;
+                }
+            }
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+    
+    // Splits headerless text into note contents, each with the 1-based line
+    // it starts on
+    pub fn split_plain_text(content: &str, split_by: SplitBy) -> Vec<(usize, String)> {
+        let lines: Vec<(usize, &str)> = content.lines().enumerate().map(|(index, line)| (index + 1, line)).collect();
+        let chunks: Vec<(usize, String)> = match split_by {
+            SplitBy::None => vec![(lines.iter().find(|(_, line)| !line.trim().is_empty()).map_or(1, |(number, _)| *number), content.to_string())],
+            SplitBy::Line => lines.iter().map(|(number, line)| (*number, line.to_string())).collect(),
+            SplitBy::BlankLine => {
+                let mut chunks = Vec::new();
+                let mut current: Vec<&str> = Vec::new();
+                let mut start = 0;
+                for (number, line) in &lines {
+                    if line.trim().is_empty() {
+                        if !current.is_empty() {
+                            chunks.push((start, current.join("\n")));
+                            current.clear();
+                        }
+                    } else {
+                        if current.is_empty() {
+                            start = *number;
+                        }
+                        current.push(line);
+                    }
+                }
+                if !current.is_empty() {
+                    chunks.push((start, current.join("\n")));
+                }
+                chunks
+            }
+        };
+        
+        chunks.into_iter()
+            .map(|(number, chunk)| (number, chunk.trim().to_string()))
+            .filter(|(_, chunk)| !chunk.is_empty())
+            .collect()
+    }
+    
     fn parse_simple_date(date_str: &str) -> Result<chrono::NaiveDate> {
         // Try parsing various date formats
         let formats = [
@@ -128,6 +409,29 @@ impl NoteParser {
             .join("\n")
     }
     
+    fn escape_content_v2(content: &str) -> String {
+        content.lines()
+            .map(|line| {
+                if line.trim_start_matches('\\').starts_with('#') {
+                    format!("\\{}", line)
+                } else {
+                    line.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    
+    fn unescape_content_v2(content: &str) -> String {
+        content.lines()
+            .map(|line| {
+                let unescaped = line.strip_prefix('\\').unwrap_or(line);
+                if unescaped.trim_start_matches('\\').starts_with('#') { unescaped } else { line }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+    
     fn unescape_content(content: &str) -> String {
         content.lines()
             .map(|line| {
@@ -140,4 +444,60 @@ impl NoteParser {
             .collect::<Vec<_>>()
             .join("\n")
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // The note's text after a save and load in format 2
+    fn round_trip(content: &str) -> String {
+        let note = Note::new(content.to_string(), &[], Local::now());
+        let text = NoteParser::format_notes(&[&note], FileFormat::V2);
+        let notes = NoteParser::parse_notes_from_text(&text, Local::now()).unwrap();
+        assert_eq!(notes.len(), 1, "{}", text);
+        notes[0].content.clone()
+    }
+    
+    #[test]
+    fn escapes_lines_that_look_like_headers() {
+        assert_eq!(NoteParser::escape_content_v2("#a1b2 2025-01-01"), "\\#a1b2 2025-01-01");
+        assert_eq!(NoteParser::escape_content_v2("\\#tag"), "\\\\#tag");
+        assert_eq!(NoteParser::escape_content_v2("\\\\#tag"), "\\\\\\#tag");
+        assert_eq!(NoteParser::escape_content_v2("  #indented"), "  #indented");
+        assert_eq!(NoteParser::escape_content_v2("plain \\# text"), "plain \\# text");
+    }
+    
+    #[test]
+    fn round_trips_hashes_and_backslashes() {
+        for content in [
+            "intro\n#heading",
+            "intro\n\\#escaped",
+            "intro\n\\\\#double",
+            "intro\n   #indented",
+            "intro\n#a1b2 2025-07-09T10:00:00Z tags=x",
+            "intro\n\\",
+            "#first line",
+        ] {
+            assert_eq!(round_trip(content), content);
+        }
+    }
+    
+    #[test]
+    fn keeps_v1_escapes_when_migrating() {
+        let v1 = "#a1b2 2025-07-09T10:00:00+02:00\nintro\n\\#heading\n  indented\n\n#c3d4 2025-07-08T09:00:00+02:00\nplain\n";
+        assert!(NoteParser::detect_format(v1) == FileFormat::V1);
+        let notes = NoteParser::parse_notes_from_text(v1, Local::now()).unwrap();
+        assert_eq!(notes[0].content, "intro\n#heading\n  indented");
+        
+        let v2 = NoteParser::format_notes(&notes.iter().collect::<Vec<_>>(), FileFormat::V2);
+        assert!(NoteParser::detect_format(&v2) == FileFormat::V2);
+        let migrated = NoteParser::parse_notes_from_text(&v2, Local::now()).unwrap();
+        assert_eq!(migrated.len(), 2);
+        for (before, after) in notes.iter().zip(&migrated) {
+            assert_eq!(before.id, after.id);
+            assert_eq!(before.content, after.content);
+            assert_eq!(before.timestamp, after.timestamp);
+        }
+    }
 }
\ No newline at end of file
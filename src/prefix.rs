@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+
+// Generated IDs are hexadecimal
+const ID_DIGITS: usize = 16;
+
+// Note IDs by character, so the notes a typed prefix means and the shortest
+// prefix naming each note alone, as git abbreviates commit hashes, are both
+// found by walking down from the first character
+#[derive(Default)]
+pub struct PrefixTrie {
+    root: TrieNode,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: BTreeMap<char, TrieNode>,
+    // IDs ending here or further down
+    count: usize,
+    id: Option<String>,
+}
+
+impl PrefixTrie {
+    pub fn new<'a>(ids: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut trie = Self::default();
+        for id in ids {
+            trie.insert(id);
+        }
+        trie
+    }
+    
+    pub fn insert(&mut self, id: &str) {
+        let mut node = &mut self.root;
+        node.count += 1;
+        for c in id.chars() {
+            node = node.children.entry(c).or_default();
+            node.count += 1;
+        }
+        node.id = Some(id.to_string());
+    }
+    
+    pub fn contains(&self, id: &str) -> bool {
+        self.node(id).is_some_and(|node| node.id.is_some())
+    }
+    
+    // Every ID starting with the prefix, in order
+    pub fn matching(&self, prefix: &str) -> Vec<String> {
+        let mut ids = Vec::new();
+        if let Some(node) = self.node(prefix) {
+            collect(node, &mut ids);
+        }
+        ids
+    }
+    
+    // Characters of the ID needed to tell it from every other one; all of
+    // them when it is itself the start of a longer ID
+    pub fn unique_len(&self, id: &str) -> usize {
+        let mut node = &self.root;
+        for (index, c) in id.chars().enumerate() {
+            match node.children.get(&c) {
+                Some(child) if child.count == 1 => return index + 1,
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+        id.chars().count()
+    }
+    
+    // IDs sharing their first `len` characters with another
+    pub fn shared(&self, len: usize) -> usize {
+        let mut ids = Vec::new();
+        collect(&self.root, &mut ids);
+        ids.iter().filter(|id| self.unique_len(id) > len).count()
+    }
+    
+    // True once as many IDs share their first `len` characters as there are
+    // half as many prefixes that long, when typing that many often matches
+    // several notes. Adding notes never makes prefixes unique again, so this
+    // turns true once as a store grows.
+    pub fn crowded(&self, len: usize) -> bool {
+        ID_DIGITS.checked_pow(len as u32).is_some_and(|prefixes| self.shared(len) >= prefixes / 2)
+    }
+    
+    fn node(&self, prefix: &str) -> Option<&TrieNode> {
+        prefix.chars().try_fold(&self.root, |node, c| node.children.get(&c))
+    }
+}
+
+fn collect(node: &TrieNode, ids: &mut Vec<String>) {
+    if let Some(id) = &node.id {
+        ids.push(id.clone());
+    }
+    for child in node.children.values() {
+        collect(child, ids);
+    }
+}
@@ -0,0 +1,26 @@
+use anyhow::{Context, Result};
+use qrcode::QrCode;
+use qrcode::render::unicode::Dense1x2;
+
+// Renders text as a QR code for the terminal, two modules per character with
+// half blocks. Terminals draw blocks in a light color on a dark background,
+// so the code's light modules are the ones drawn. The ASCII version uses `##`
+// for each light module.
+pub fn render(text: &str, ascii: bool) -> Result<String> {
+    let code = QrCode::new(text.as_bytes())
+        .context("Note is too long to fit in a QR code")?;
+    
+    let rendered = if ascii {
+        code.render::<char>()
+            .dark_color(' ')
+            .light_color('#')
+            .module_dimensions(2, 1)
+            .build()
+    } else {
+        code.render::<Dense1x2>()
+            .dark_color(Dense1x2::Light)
+            .light_color(Dense1x2::Dark)
+            .build()
+    };
+    Ok(rendered)
+}
@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::io::Read;
+use std::time::Duration;
+
+use crate::note::Note;
+
+// Reading-list entries are notes tagged `reading` whose content is
+// "title — url". Marking one as read records the date in `read`.
+pub const READING_TAG: &str = "reading";
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The title sits in the page head, so large pages are cut off here
+const MAX_PAGE_BYTES: u64 = 512 * 1024;
+
+pub fn is_url(text: &str) -> bool {
+    let text = text.trim();
+    let rest = text.strip_prefix("https://").or_else(|| text.strip_prefix("http://"));
+    rest.is_some_and(|rest| !rest.is_empty() && !rest.contains(char::is_whitespace))
+}
+
+// The page's <title>, or None when it has none
+pub fn fetch_title(url: &str) -> Result<Option<String>> {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(FETCH_TIMEOUT))
+        .build()
+        .into();
+    let mut response = agent.get(url).call()
+        .with_context(|| format!("Failed to fetch {}", url))?;
+    
+    let mut page = Vec::new();
+    response.body_mut().as_reader().take(MAX_PAGE_BYTES).read_to_end(&mut page)
+        .with_context(|| format!("Failed to read {}", url))?;
+    
+    Ok(extract_title(&String::from_utf8_lossy(&page)))
+}
+
+fn extract_title(html: &str) -> Option<String> {
+    let pattern = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").expect("title pattern is valid");
+    let raw = pattern.captures(html)?.get(1)?.as_str();
+    let title = decode_entities(&raw.split_whitespace().collect::<Vec<_>>().join(" "));
+    if title.is_empty() { None } else { Some(title) }
+}
+
+// Handles the entities that commonly show up in titles
+fn decode_entities(text: &str) -> String {
+    let numeric = Regex::new(r"&#(x[0-9a-fA-F]+|[0-9]+);").expect("entity pattern is valid");
+    let text = numeric.replace_all(text, |caps: &regex::Captures| {
+        let code = &caps[1];
+        let value = match code.strip_prefix('x') {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => code.parse().ok(),
+        };
+        value.and_then(char::from_u32).map(String::from).unwrap_or_else(|| caps[0].to_string())
+    });
+    
+    text.replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&mdash;", "—")
+        .replace("&ndash;", "–")
+        .replace("&amp;", "&")
+}
+
+pub fn note_content(title: Option<&str>, url: &str) -> String {
+    match title {
+        Some(title) => format!("{} — {}", title, url),
+        None => url.to_string(),
+    }
+}
+
+// The link of a reading-list note: the last URL in its first line
+pub fn url(note: &Note) -> Option<&str> {
+    note.content.lines().next()?
+        .split_whitespace()
+        .rev()
+        .find(|word| is_url(word))
+}
+
+pub fn is_unread(note: &Note) -> bool {
+    note.has_tag(READING_TAG) && !note.meta.contains_key("read")
+}
\ No newline at end of file
@@ -0,0 +1,107 @@
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use crate::note::Note;
+
+// `note recap` sums up the notes captured on a day, for the end of it: on the
+// terminal, as a desktop notification (--notify) or as a digest note (--save).
+// The digest is tagged `recap` and remembers its day in the `recap` field, so
+// running it again the same day updates it instead of adding another.
+pub const RECAP_TAG: &str = "recap";
+pub const RECAP_KEY: &str = "recap";
+
+// A notification lists this many notes and counts the rest
+const LISTED: usize = 5;
+
+// The notifier each platform ships or commonly has; `{title}` and `{body}`
+// are replaced by the notification's text
+pub fn default_command() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "terminal-notifier -title {title} -message {body}"
+    } else if cfg!(windows) {
+        ""
+    } else {
+        "notify-send {title} {body}"
+    }
+}
+
+// Digests from earlier runs don't count as captured notes
+pub fn is_recap(note: &Note) -> bool {
+    note.has_tag(RECAP_TAG) && note.meta.contains_key(RECAP_KEY)
+}
+
+pub fn title(count: usize, day: NaiveDate, today: NaiveDate) -> String {
+    let notes = match count {
+        0 => "No notes".to_string(),
+        1 => "1 note".to_string(),
+        _ => format!("{} notes", count),
+    };
+    if day == today {
+        format!("{} captured today", notes)
+    } else {
+        format!("{} captured on {}", notes, day.format("%b %-d"))
+    }
+}
+
+// First lines of the notes, oldest first, then the tags they used
+pub fn lines(notes: &[&Note]) -> Vec<String> {
+    let mut lines: Vec<String> = notes.iter()
+        .take(LISTED)
+        .map(|note| format!("- {}", first_line(note)))
+        .collect();
+    if notes.len() > LISTED {
+        lines.push(format!("and {} more", notes.len() - LISTED));
+    }
+    let tags = tag_summary(notes);
+    if !tags.is_empty() {
+        lines.push(tags);
+    }
+    lines
+}
+
+// Every note by ID, for a digest note kept next to them
+pub fn digest(notes: &[&Note], day: NaiveDate) -> String {
+    let mut content = format!("Recap of {}\n", day.format("%A, %b %-d %Y"));
+    for note in notes {
+        content.push_str(&format!("\n- [{}] {}", note.id, first_line(note)));
+    }
+    let tags = tag_summary(notes);
+    if !tags.is_empty() {
+        content.push_str(&format!("\n\n{}", tags));
+    }
+    content
+}
+
+pub fn notify(command: &str, title: &str, body: &str) -> Result<()> {
+    let mut words = command.split_whitespace().map(|word| word.replace("{title}", title).replace("{body}", body));
+    let program = words.next().context("Set `notify_command` in the [recap] config section to use --notify")?;
+    let status = Command::new(&program)
+        .args(words)
+        .status()
+        .with_context(|| format!("Failed to run notification command '{}'", program))?;
+    if !status.success() {
+        bail!("Notification command '{}' exited with {}", program, status);
+    }
+    Ok(())
+}
+
+fn first_line(note: &Note) -> &str {
+    note.content.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("")
+}
+
+// "Tags: work (3), home (1)", most used first
+fn tag_summary(notes: &[&Note]) -> String {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for tag in notes.iter().flat_map(|note| note.tags()) {
+        *counts.entry(tag).or_insert(0) += 1;
+    }
+    if counts.is_empty() {
+        return String::new();
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    let tags: Vec<String> = counts.iter().map(|(tag, count)| format!("{} ({})", tag, count)).collect();
+    format!("Tags: {}", tags.join(", "))
+}
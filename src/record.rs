@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fs;
+use std::process::Command;
+
+// `note record` runs a shell under script(1), which gives it a terminal of its
+// own and copies everything shown there to a file. Once the shell exits, the
+// transcript is cleaned of terminal control sequences and saved as a note.
+pub const RECORD_TAG: &str = "session";
+
+// Masked in every transcript on top of the `[redact]` patterns, since secrets
+// typed or printed during a session end up in the note: private keys, tokens
+// from well-known services, bearer headers and `password=...`-style settings
+pub const SECRET_PATTERNS: &[&str] = &[
+    r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    r"\bAKIA[0-9A-Z]{16}\b",
+    r"\bgh[pousr]_[A-Za-z0-9]{36,}\b",
+    r"\bgithub_pat_[A-Za-z0-9_]{22,}",
+    r"\bxox[abprs]-[A-Za-z0-9-]{10,}",
+    r"\bsk-[A-Za-z0-9_-]{20,}",
+    r"\beyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+    r"(?i)\bbearer\s+[A-Za-z0-9._~+/-]+=*",
+    r#"(?i)\b[a-z0-9_]*(?:password|passwd|secret|token|api_?key)[a-z0-9_]*\s*[:=]\s*\S+"#,
+];
+
+// `{shell}` is replaced by $SHELL and `{file}` by the transcript path
+pub fn default_command() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "script -q {file} {shell}"
+    } else if cfg!(windows) {
+        ""
+    } else {
+        "script -q -f -c {shell} {file}"
+    }
+}
+
+// The raw transcript, or None when the session wrote nothing
+pub fn capture(command: &str) -> Result<Option<String>> {
+    let dir = std::env::temp_dir().join(format!("note-record-{}", std::process::id()));
+    fs::create_dir_all(&dir).context("Failed to create a temporary directory")?;
+    let path = dir.join("transcript.txt");
+    let file = path.to_string_lossy();
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "sh".to_string());
+    
+    let mut words = command.split_whitespace().map(|word| word.replace("{file}", &file).replace("{shell}", &shell));
+    let program = words.next().context("Set `command` in the [record] config section to use note record")?;
+    // The shell's exit status is whatever its last command left, so only the
+    // transcript counts
+    let status = Command::new(&program)
+        .args(words)
+        .status()
+        .with_context(|| format!("Failed to run recording command '{}'", program));
+    // Carriage returns are kept for clean() to overwrite lines as the
+    // terminal did
+    let transcript = fs::read(&path).ok().map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    let _ = fs::remove_dir_all(&dir);
+    status?;
+    Ok(transcript.filter(|text| !text.trim().is_empty()))
+}
+
+// The transcript as it read on screen: escape sequences for colours, cursor
+// movement and window titles removed, backspaces and carriage returns applied,
+// and script's own start and end lines dropped
+pub fn clean(transcript: &str) -> String {
+    let escapes = Regex::new(r"\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b\[[0-?]*[ -/]*[@-~]|\x1b[P^_][^\x1b]*\x1b\\|\x1b[ -/]*[0-~]")
+        .expect("escape pattern is valid");
+    let text = escapes.replace_all(transcript, "");
+    
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        if raw.starts_with("Script started on ") || raw.starts_with("Script done on ") {
+            continue;
+        }
+        let mut line: Vec<char> = Vec::new();
+        let mut column: usize = 0;
+        for c in raw.chars() {
+            match c {
+                '\r' => column = 0,
+                '\u{8}' => column = column.saturating_sub(1),
+                '\t' => {
+                    line.truncate(column);
+                    line.push('\t');
+                    column = line.len();
+                }
+                c if c.is_control() => {}
+                c => {
+                    if column < line.len() {
+                        line[column] = c;
+                    } else {
+                        line.push(c);
+                    }
+                    column += 1;
+                }
+            }
+        }
+        let line: String = line.into_iter().collect();
+        lines.push(line.trim_end().to_string());
+    }
+    
+    // No runs of blank lines, and none at either end
+    lines.dedup_by(|line, previous| line.is_empty() && previous.is_empty());
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+// At most `max_bytes` of the transcript, keeping its beginning and end, where
+// the session's setup and outcome are
+pub fn truncate(transcript: &str, max_bytes: usize) -> String {
+    if max_bytes == 0 || transcript.len() <= max_bytes {
+        return transcript.to_string();
+    }
+    let lines: Vec<&str> = transcript.lines().collect();
+    let (mut head, mut head_len) = (0, 0);
+    while head < lines.len() && head_len + lines[head].len() < max_bytes / 2 {
+        head_len += lines[head].len() + 1;
+        head += 1;
+    }
+    let (mut tail, mut tail_len) = (lines.len(), 0);
+    while tail > head && tail_len + lines[tail - 1].len() < max_bytes / 2 {
+        tail_len += lines[tail - 1].len() + 1;
+        tail -= 1;
+    }
+    format!("{}\n[... {} lines left out ...]\n{}",
+        lines[..head].join("\n"),
+        tail - head,
+        lines[tail..].join("\n")
+    )
+}
+
+// The note: a title line, then the transcript fenced as a code block, with
+// a fence longer than any run of backticks it contains
+pub fn note_content(title: &str, transcript: &str) -> String {
+    let mut fence = "```".to_string();
+    while transcript.contains(&fence) {
+        fence.push('`');
+    }
+    format!("{}\n\n{}\n{}\n{}", title, fence, transcript, fence)
+}
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::borrow::Cow;
+
+use crate::note::Note;
+
+// Masks text matching the configured patterns when notes are shown or
+// exported, so secrets stay out of screen shares. The stored notes are never
+// changed.
+pub struct Redactor {
+    patterns: Vec<Regex>,
+    mask: String,
+}
+
+impl Redactor {
+    pub fn new(patterns: &[String], mask: &str) -> Result<Self> {
+        let patterns = patterns.iter()
+            .map(|pattern| Regex::new(pattern).with_context(|| format!("Invalid redaction pattern: {}", pattern)))
+            .collect::<Result<_>>()?;
+        Ok(Self { patterns, mask: mask.to_string() })
+    }
+    
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for pattern in &self.patterns {
+            if let Cow::Owned(replaced) = pattern.replace_all(&text, regex::NoExpand(&self.mask)) {
+                text = Cow::Owned(replaced);
+            }
+        }
+        text
+    }
+    
+    pub fn redact_note(&self, note: &mut Note) {
+        if let Cow::Owned(content) = self.redact(&note.content) {
+            note.content = content;
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+use crate::config::RemoteConfig;
+
+// `note remote <host> ...` runs note on another machine over SSH, to capture
+// into the store on a workstation while logged in to a server. The remote
+// note is run with --porcelain or a fixed --format, so its output can be read
+// back here whatever its config says.
+const LIST_FORMAT: &str = "{id}\\t{date:%Y-%m-%d %H:%M}\\t{first_line}";
+
+pub struct RemoteNote {
+    pub id: String,
+    pub date: String,
+    pub first_line: String,
+}
+
+// Adds a note on `host` and returns its ID
+pub fn add(remote_config: &RemoteConfig, host: &str, text: &str, tags: &[String]) -> Result<String> {
+    let mut args = vec!["--porcelain".to_string()];
+    for tag in tags {
+        args.extend(["--tag".to_string(), tag.clone()]);
+    }
+    // After "--" the text can't be taken for an option or a subcommand
+    args.extend(["--".to_string(), text.to_string()]);
+    let output = run(remote_config, host, &args)?;
+    match output.lines().last().map(str::trim) {
+        Some(id) if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric()) => Ok(id.to_string()),
+        // Refused, e.g. by the remote similar-note check, which explains why
+        _ => bail!("Not saved on {}:\n{}", host, output.trim_end()),
+    }
+}
+
+pub fn list(remote_config: &RemoteConfig, host: &str, tag: Option<&str>) -> Result<Vec<RemoteNote>> {
+    let mut args = vec!["list".to_string(), "--format".to_string(), LIST_FORMAT.to_string()];
+    if let Some(tag) = tag {
+        args.extend(["--tag".to_string(), tag.to_string()]);
+    }
+    let output = run(remote_config, host, &args)?;
+    Ok(output.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            Some(RemoteNote {
+                id: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                first_line: fields.next().unwrap_or("").to_string(),
+            })
+        })
+        .collect())
+}
+
+// The remote note's stdout. SSH hands the command to the remote shell as one
+// string, so each argument is quoted for it; the program is left as configured
+// so a path such as ~/.cargo/bin/note still expands.
+fn run(remote_config: &RemoteConfig, host: &str, args: &[String]) -> Result<String> {
+    let mut ssh = remote_config.ssh.split_whitespace();
+    let program = ssh.next().context("Set `ssh` in the [remote] config section to use note remote")?;
+    let remote_command: Vec<String> = std::iter::once(remote_config.program.clone())
+        .chain(args.iter().map(|arg| shell_quote(arg)))
+        .collect();
+    let output = Command::new(program)
+        .args(ssh)
+        .arg(host)
+        .arg(remote_command.join(" "))
+        .output()
+        .with_context(|| format!("Failed to run '{}'", program))?;
+    
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.trim() {
+            "" => bail!("note on {} failed ({})", host, output.status),
+            message => bail!("note on {} failed: {}", host, message),
+        }
+    }
+    Ok(stdout)
+}
+
+fn shell_quote(word: &str) -> String {
+    if !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c)) {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\\''"))
+    }
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Local};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::inbox;
+use crate::note::Note;
+
+// `note show` and `note resurface` record when a note was last looked at in
+// `viewed=<rfc3339>`. Notes never viewed count from when they were written.
+pub const VIEWED_KEY: &str = "viewed";
+
+pub fn last_viewed(note: &Note) -> DateTime<Local> {
+    note.meta.get(VIEWED_KEY)
+        .and_then(|viewed| DateTime::parse_from_rfc3339(viewed).ok())
+        .map(|viewed| viewed.with_timezone(&Local))
+        .unwrap_or(note.timestamp)
+}
+
+// Up to `count` notes that haven't been looked at the longest, or a random
+// sample of them. Archived notes stay buried.
+pub fn pick(notes: &[Note], count: usize, random: bool) -> Vec<&Note> {
+    let mut candidates: Vec<&Note> = notes.iter()
+        .filter(|note| !inbox::is_archived(note))
+        .collect();
+    
+    if random {
+        shuffle(&mut candidates);
+    } else {
+        candidates.sort_by_key(|note| (last_viewed(note), note.timestamp));
+    }
+    candidates.truncate(count);
+    candidates
+}
+
+// Fisher-Yates with a xorshift generator seeded from the clock; good enough
+// for choosing notes to look at
+fn shuffle<T>(items: &mut [T]) {
+    let mut state = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0) | 1;
+    for i in (1..items.len()).rev() {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        items.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+}
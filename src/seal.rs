@@ -0,0 +1,168 @@
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::timezone;
+
+// `note export --manifest` seals an export the way a release lists its
+// checksums: how many notes went out and when, a hash of each note's exported
+// text and of every file written, and a digest over all of that, so `note
+// import --verify-manifest` can tell a complete, untouched export from one
+// that lost files or was edited on the way.
+const HEADER: &str = "%note-export 1";
+
+// Alongside a directory export, or as <file>.manifest next to a file
+pub const DIR_FILE: &str = "export.manifest";
+
+pub fn path_for(target: &Path) -> PathBuf {
+    if target.is_dir() {
+        return target.join(DIR_FILE);
+    }
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".manifest");
+    target.with_file_name(name)
+}
+
+pub fn hash(bytes: &[u8]) -> String {
+    blake3::hash(bytes).to_hex().to_string()
+}
+
+// `notes` are (ID, exported text) pairs; `files` are the files written, which
+// are recorded relative to the manifest's directory
+pub fn write(path: &Path, notes: &[(String, String)], files: &[PathBuf], exported: DateTime<Local>) -> Result<()> {
+    let base = base_dir(path)?;
+    let own = base.join(path.file_name().unwrap_or_default());
+    let mut body = format!("exported {}\ncount {}\n", timezone::storage(&exported), notes.len());
+    for (id, text) in notes {
+        body.push_str(&format!("note {} {}\n", id, hash(text.as_bytes())));
+    }
+    let mut names = Vec::new();
+    for file in files {
+        let file = file.canonicalize().with_context(|| format!("Failed to resolve {}", file.display()))?;
+        // A manifest left by an earlier export into the same place
+        if file == own {
+            continue;
+        }
+        let Ok(name) = file.strip_prefix(&base) else {
+            bail!("{} is outside {}, where the manifest goes", file.display(), base.display());
+        };
+        let bytes = fs::read(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+        names.push((relative_name(name), hash(&bytes)));
+    }
+    names.sort();
+    for (name, hash) in names {
+        body.push_str(&format!("file {} {}\n", hash, name));
+    }
+    let content = format!("{}\ndigest {}\n{}", HEADER, hash(body.as_bytes()), body);
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[derive(Default)]
+pub struct Verification {
+    pub exported: String,
+    pub count: usize,
+    // False when the digest doesn't match, i.e. the manifest itself changed
+    pub intact: bool,
+    pub missing: Vec<String>,
+    pub changed: Vec<String>,
+    // Files in the directory being imported that the export didn't write
+    pub unlisted: Vec<String>,
+}
+
+impl Verification {
+    pub fn is_ok(&self) -> bool {
+        self.intact && self.missing.is_empty() && self.changed.is_empty() && self.unlisted.is_empty()
+    }
+}
+
+// Checks the export at `import` (a file the manifest lists, or the directory
+// it was written to) against the manifest at `path`
+pub fn verify(path: &Path, import: &Path) -> Result<Verification> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        bail!("{} isn't an export manifest", path.display());
+    }
+    let digest = lines.next().and_then(|line| line.strip_prefix("digest ")).unwrap_or_default();
+    let body: String = lines.clone().map(|line| format!("{}\n", line)).collect();
+    
+    let base = base_dir(path)?;
+    let mut verification = Verification { intact: digest == hash(body.as_bytes()), ..Verification::default() };
+    let mut notes = 0;
+    let mut listed = Vec::new();
+    for line in lines {
+        let Some((kind, rest)) = line.split_once(' ') else {
+            continue;
+        };
+        match kind {
+            "exported" => verification.exported = rest.to_string(),
+            "count" => verification.count = rest.parse().unwrap_or_default(),
+            "note" => notes += 1,
+            "file" => {
+                let Some((expected, name)) = rest.split_once(' ') else {
+                    continue;
+                };
+                let file = base.join(name);
+                match fs::read(&file) {
+                    Ok(bytes) if hash(&bytes) == expected => {}
+                    Ok(_) => verification.changed.push(name.to_string()),
+                    Err(_) => verification.missing.push(name.to_string()),
+                }
+                listed.push(file);
+            }
+            _ => {}
+        }
+    }
+    if notes != verification.count {
+        verification.intact = false;
+    }
+    
+    let import = import.canonicalize().with_context(|| format!("Failed to resolve {}", import.display()))?;
+    if import.is_dir() {
+        for file in files_in(&import)? {
+            if file != base.join(path.file_name().unwrap_or_default()) && !listed.contains(&file) {
+                let name = file.strip_prefix(&base).map(relative_name).unwrap_or_else(|_| file.display().to_string());
+                verification.unlisted.push(name);
+            }
+        }
+    } else if !listed.contains(&import) {
+        bail!("{} isn't one of the files {} lists", import.display(), path.display());
+    }
+    Ok(verification)
+}
+
+fn base_dir(manifest: &Path) -> Result<PathBuf> {
+    let dir = match manifest.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    dir.canonicalize().with_context(|| format!("Failed to resolve {}", dir.display()))
+}
+
+fn relative_name(path: &Path) -> String {
+    path.components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Every file under `dir`, for exports that fill a directory of their own
+pub fn files_in(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    Ok(files)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use anyhow::{Context, Result};
+use colored::*;
+use regex::Regex;
+use rust_stemmers::{Algorithm, Stemmer};
+use std::borrow::Cow;
+use std::ops::Range;
+
+use crate::language;
+
+#[derive(Clone)]
+enum MatchKind {
+    Pattern(Regex),
+    // Every query word must appear in the note in some inflected form
+    Stemmed {
+        algorithm: Algorithm,
+        stems: Vec<String>,
+        words: Regex,
+        // The query stemmed for each language a note may be detected in
+        by_language: Vec<(&'static str, Algorithm, Vec<String>)>,
+    },
+    // Every query word must appear in the note, give or take the typos it is
+    // allowed: (lowercased word, edits allowed)
+    Fuzzy {
+        query: Vec<(Vec<char>, usize)>,
+        words: Regex,
+    },
+}
+
+// A compiled search query shared by filtering and highlighting
+#[derive(Clone)]
+pub struct Matcher {
+    kind: MatchKind,
+    // The query text of a plain (non-regex) search, for index lookups
+    literal: Option<String>,
+}
+
+impl Matcher {
+    // Plain queries match literally and case-insensitively; regex queries are
+    // used as written (prefix with (?i) for case-insensitivity)
+    pub fn new(query: &str, is_regex: bool) -> Result<Self> {
+        let pattern = if is_regex {
+            query.to_string()
+        } else {
+            format!("(?i){}", regex::escape(query))
+        };
+        let regex = Regex::new(&pattern)
+            .context(format!("Invalid search pattern: {}", query))?;
+        Ok(Self {
+            kind: MatchKind::Pattern(regex),
+            literal: (!is_regex).then(|| query.to_string()),
+        })
+    }
+    
+    // Matches words by their stem, so "deploying" also finds "deployed"
+    pub fn stemmed(query: &str, language: &str) -> Result<Self> {
+        let algorithm = stem_algorithm(language)
+            .context(format!("Unsupported stemming language: {}", language))?;
+        let words = Regex::new(r"\w+").expect("valid word pattern");
+        let stem_query = |algorithm: Algorithm| -> Vec<String> {
+            let stemmer = Stemmer::create(algorithm);
+            words.find_iter(query)
+                .map(|word| stemmer.stem(&word.as_str().to_lowercase()).into_owned())
+                .collect()
+        };
+        let stems = stem_query(algorithm);
+        let by_language = language::STEMMING.iter()
+            .filter_map(|(code, name)| stem_algorithm(name).map(|algorithm| (*code, algorithm, stem_query(algorithm))))
+            .collect();
+        Ok(Self { kind: MatchKind::Stemmed { algorithm, stems, words, by_language }, literal: None })
+    }
+    
+    // Matches words a few typos away, so "kubernets" finds "Kubernetes".
+    // Short words allow fewer edits, as any two-letter word is two edits
+    // from any other: one per four letters, up to `max_distance`.
+    pub fn fuzzy(query: &str, max_distance: usize) -> Self {
+        let words = Regex::new(r"\w+").expect("valid word pattern");
+        let query = words.find_iter(query)
+            .map(|word| {
+                let word: Vec<char> = word.as_str().to_lowercase().chars().collect();
+                let allowed = max_distance.min(word.len() / 4);
+                (word, allowed)
+            })
+            .collect();
+        Self { kind: MatchKind::Fuzzy { query, words }, literal: None }
+    }
+    
+    // The matcher for a note written in `lang`: a stemmed search stems the
+    // note in its own language, when that has a stemmer, rather than the
+    // configured one
+    pub fn for_language(&self, lang: Option<&str>) -> Cow<'_, Matcher> {
+        let MatchKind::Stemmed { algorithm, words, by_language, .. } = &self.kind else {
+            return Cow::Borrowed(self);
+        };
+        match lang.and_then(|lang| by_language.iter().find(|(code, _, _)| *code == lang)) {
+            Some((_, other, stems)) if other != algorithm => Cow::Owned(Self {
+                kind: MatchKind::Stemmed {
+                    algorithm: *other,
+                    stems: stems.clone(),
+                    words: words.clone(),
+                    by_language: by_language.clone(),
+                },
+                literal: None,
+            }),
+            _ => Cow::Borrowed(self),
+        }
+    }
+    
+    pub fn literal(&self) -> Option<&str> {
+        self.literal.as_deref()
+    }
+    
+    pub fn is_match(&self, text: &str) -> bool {
+        match &self.kind {
+            MatchKind::Pattern(regex) => regex.is_match(text),
+            MatchKind::Stemmed { algorithm, stems, words, .. } => {
+                let stemmer = Stemmer::create(*algorithm);
+                let text_stems: Vec<String> = words.find_iter(text)
+                    .map(|word| stemmer.stem(&word.as_str().to_lowercase()).into_owned())
+                    .collect();
+                !stems.is_empty() && stems.iter().all(|stem| text_stems.contains(stem))
+            }
+            MatchKind::Fuzzy { query, words } => {
+                let text_words: Vec<Vec<char>> = words.find_iter(text)
+                    .map(|word| word.as_str().to_lowercase().chars().collect())
+                    .collect();
+                !query.is_empty() && query.iter().all(|(wanted, allowed)| {
+                    text_words.iter().any(|word| within_distance(wanted, word, *allowed))
+                })
+            }
+        }
+    }
+    
+    // Byte ranges of every non-empty match within the line
+    pub fn match_ranges(&self, line: &str) -> Vec<Range<usize>> {
+        match &self.kind {
+            MatchKind::Pattern(regex) => regex.find_iter(line)
+                .filter(|found| !found.as_str().is_empty())
+                .map(|found| found.range())
+                .collect(),
+            MatchKind::Stemmed { algorithm, stems, words, .. } => {
+                let stemmer = Stemmer::create(*algorithm);
+                words.find_iter(line)
+                    .filter(|word| stems.contains(&stemmer.stem(&word.as_str().to_lowercase()).into_owned()))
+                    .map(|word| word.range())
+                    .collect()
+            }
+            MatchKind::Fuzzy { query, words } => words.find_iter(line)
+                .filter(|word| {
+                    let word: Vec<char> = word.as_str().to_lowercase().chars().collect();
+                    query.iter().any(|(wanted, allowed)| within_distance(wanted, &word, *allowed))
+                })
+                .map(|word| word.range())
+                .collect(),
+        }
+    }
+    
+    // Wraps every match in the line with bold color, leaving the rest untouched
+    pub fn highlight(&self, line: &str) -> String {
+        let mut highlighted = String::new();
+        let mut last = 0;
+        for range in self.match_ranges(line) {
+            highlighted.push_str(&line[last..range.start]);
+            highlighted.push_str(&line[range.clone()].bold().bright_magenta().to_string());
+            last = range.end;
+        }
+        highlighted.push_str(&line[last..]);
+        highlighted
+    }
+}
+
+// Whether the Levenshtein distance between the words is at most `max`,
+// giving up on a row once every cell in it is past that
+fn within_distance(a: &[char], b: &[char], max: usize) -> bool {
+    if a.len().abs_diff(b.len()) > max {
+        return false;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().all(|distance| *distance > max) {
+            return false;
+        }
+        previous = current;
+    }
+    previous[b.len()] <= max
+}
+
+fn stem_algorithm(language: &str) -> Option<Algorithm> {
+    let algorithm = match language.to_lowercase().as_str() {
+        "arabic" => Algorithm::Arabic,
+        "danish" => Algorithm::Danish,
+        "dutch" => Algorithm::Dutch,
+        "english" => Algorithm::English,
+        "finnish" => Algorithm::Finnish,
+        "french" => Algorithm::French,
+        "german" => Algorithm::German,
+        "greek" => Algorithm::Greek,
+        "hungarian" => Algorithm::Hungarian,
+        "italian" => Algorithm::Italian,
+        "norwegian" => Algorithm::Norwegian,
+        "portuguese" => Algorithm::Portuguese,
+        "romanian" => Algorithm::Romanian,
+        "russian" => Algorithm::Russian,
+        "spanish" => Algorithm::Spanish,
+        "swedish" => Algorithm::Swedish,
+        "tamil" => Algorithm::Tamil,
+        "turkish" => Algorithm::Turkish,
+        _ => return None,
+    };
+    Some(algorithm)
+}
\ No newline at end of file
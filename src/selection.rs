@@ -0,0 +1,39 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+// Named sets of note IDs saved by `note select`, written as `@name` where
+// commands take IDs, e.g. `note tag add review @sel`. They are kept in
+// selections.txt next to notes.txt, one per line: the name, then the IDs.
+pub const DEFAULT_NAME: &str = "sel";
+
+// "@name" as the selection it refers to
+pub fn reference(arg: &str) -> Option<&str> {
+    arg.strip_prefix('@').filter(|name| is_valid_name(name))
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+pub fn load(path: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(path).context("Failed to read selections")?;
+    Ok(content.lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            let name = words.next()?;
+            Some((name.to_string(), words.map(str::to_string).collect()))
+        })
+        .collect())
+}
+
+pub fn save(path: &Path, selections: &BTreeMap<String, Vec<String>>) -> Result<()> {
+    let content: String = selections.iter()
+        .map(|(name, ids)| format!("{} {}\n", name, ids.join(" ")))
+        .collect();
+    fs::write(path, content).context("Failed to write selections")
+}
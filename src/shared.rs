@@ -0,0 +1,142 @@
+use anyhow::{bail, Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::note::Note;
+
+// Shared mode, for one notes file used from several hosts over NFS or SMB.
+// Every save takes a lock file next to notes.txt, re-reads the file and
+// applies only this process's own changes to it, note by note, so a note
+// another host added or edited in the meantime is kept. Where both changed
+// the same note, the later save wins.
+
+// Saves hold the lock for milliseconds, so one this old was left behind by a
+// process that crashed
+const STALE_AFTER: Duration = Duration::from_secs(30);
+
+pub struct StoreLock {
+    path: PathBuf,
+}
+
+impl StoreLock {
+    pub fn path_for(target: &Path) -> PathBuf {
+        let mut name = target.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        target.with_file_name(name)
+    }
+    
+    // Creating the file with O_EXCL is atomic on NFSv3 and later as well as
+    // SMB, unlike flock(), which network filesystems often ignore. Waits up
+    // to `timeout` for another process's lock.
+    pub fn acquire(target: &Path, timeout: Duration) -> Result<Self> {
+        let path = Self::path_for(target);
+        let started = SystemTime::now();
+        // Spread out retries so hosts that collided once don't keep colliding
+        let mut delay = Duration::from_millis(20 + u64::from(std::process::id() % 20));
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    let owner = format!("{} {}\n", host_name(), std::process::id());
+                    file.write_all(owner.as_bytes()).context("Failed to write lock file")?;
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e).context("Failed to create lock file"),
+            }
+            
+            if lock_age(&path).is_some_and(|age| age > STALE_AFTER) {
+                // Whoever wins the race to remove it takes the next turn
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+            if started.elapsed().unwrap_or_default() > timeout {
+                let owner = fs::read_to_string(&path).unwrap_or_default();
+                bail!("Timed out waiting for {}, held by {}; remove it if that process is gone",
+                    path.display(),
+                    owner.trim().split_once(' ').map_or("another process".to_string(), |(host, pid)| format!("process {} on {}", pid, host))
+                );
+            }
+            thread::sleep(delay);
+            delay = (delay * 2).min(Duration::from_millis(500));
+        }
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// True while another process holds a lock that isn't stale, e.g. so a journal
+// it is in the middle of writing isn't taken for a crashed save
+pub fn is_locked(target: &Path) -> bool {
+    lock_age(&StoreLock::path_for(target)).is_some_and(|age| age <= STALE_AFTER)
+}
+
+fn lock_age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    // Clocks of different hosts disagree; a lock from the "future" counts as fresh
+    Some(SystemTime::now().duration_since(modified).unwrap_or_default())
+}
+
+fn host_name() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown host".to_string())
+}
+
+// Applies the changes from `base` to `ours` onto `theirs`, the file as another
+// host left it: our new notes go on top, our edits replace their version (or
+// bring back a note they removed) and our removals are removed. Everything
+// else stays as they wrote it.
+pub fn merge(base: &[Note], ours: &[Note], theirs: Vec<Note>) -> Vec<Note> {
+    let base: HashMap<&str, &Note> = base.iter().map(|note| (note.id.as_str(), note)).collect();
+    let ours_by_id: HashMap<&str, &Note> = ours.iter().map(|note| (note.id.as_str(), note)).collect();
+    
+    let their_ids: HashSet<String> = theirs.iter().map(|note| note.id.clone()).collect();
+    let mut merged: Vec<Note> = Vec::new();
+    for note in theirs {
+        let id = note.id.as_str();
+        match (base.get(id), ours_by_id.get(id)) {
+            // Removed here
+            (Some(_), None) => {}
+            (Some(before), Some(after)) if !same(before, after) => merged.push((*after).clone()),
+            _ => merged.push(note),
+        }
+    }
+    
+    let mut taken: HashSet<String> = merged.iter().map(|note| note.id.clone()).collect();
+    let mut added = Vec::new();
+    // Our new notes, and our edits to notes they removed
+    let kept = |note: &Note| match base.get(note.id.as_str()) {
+        None => true,
+        Some(before) => !same(before, note) && !their_ids.contains(&note.id),
+    };
+    for note in ours.iter().filter(|note| kept(note)) {
+        if merged.iter().any(|other| other.id == note.id && same(other, note)) {
+            continue;
+        }
+        let mut note = note.clone();
+        // Another host gave a note of its own the same short ID
+        if taken.contains(&note.id) {
+            note.id = Note::derive_id(&note.content, &note.timestamp, &taken);
+        }
+        taken.insert(note.id.clone());
+        added.push(note);
+    }
+    added.append(&mut merged);
+    added
+}
+
+fn same(a: &Note, b: &Note) -> bool {
+    a.content == b.content && a.timestamp == b.timestamp && a.meta == b.meta
+}
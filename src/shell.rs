@@ -0,0 +1,48 @@
+// Shell integration printed by `note shell-init`, meant to be evaluated from
+// the shell's rc file. Alt-N saves the command line being typed as a note and
+// leaves it in place to run or keep editing.
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+const BASH_INIT: &str = r#"# note shell integration: Alt-N saves the current command line as a note
+__note_capture_line() {
+    [ -z "$READLINE_LINE" ] && return
+    local id
+    id=$(command note --porcelain add -- "$READLINE_LINE" 2>/dev/null) && printf 'note: saved [%s]\n' "$id"
+}
+bind -x '"\en": __note_capture_line'
+"#;
+
+const ZSH_INIT: &str = r#"# note shell integration: Alt-N saves the current command line as a note
+__note_capture_line() {
+    [[ -z $BUFFER ]] && return
+    local id
+    id=$(command note --porcelain add -- "$BUFFER" 2>/dev/null) && zle -M "note: saved [$id]"
+}
+zle -N __note_capture_line
+bindkey '\en' __note_capture_line
+"#;
+
+const FISH_INIT: &str = r#"# note shell integration: Alt-N saves the current command line as a note
+function __note_capture_line
+    set -l line (commandline | string collect)
+    test -z "$line"; and return
+    set -l id (command note --porcelain add -- "$line" 2>/dev/null)
+    and echo; and echo "note: saved [$id]"
+    commandline -f repaint
+end
+bind \en __note_capture_line
+"#;
+
+pub fn init_script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => BASH_INIT,
+        Shell::Zsh => ZSH_INIT,
+        Shell::Fish => FISH_INIT,
+    }
+}
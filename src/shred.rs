@@ -0,0 +1,105 @@
+use anyhow::{Context, Result};
+use chrono::DateTime;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::encoding;
+use crate::parser::NoteParser;
+
+// `note rm --shred` opens the files it is about to replace before the save,
+// and once the new ones have been renamed into place, overwrites the removed
+// notes' bytes in the old ones through those handles. The save itself stays
+// as safe as any other: a crash before the rename still finds the old file
+// whole and the journal to replay. Filesystems that copy on write or keep
+// snapshots may still hold the old blocks elsewhere.
+pub struct Shredder {
+    targets: Vec<(File, Vec<Range<u64>>)>,
+}
+
+impl Shredder {
+    // Where the notes with `ids` sit in the notes file
+    pub fn notes(path: &Path, ids: &HashSet<String>) -> Result<Self> {
+        let mut shredder = Self { targets: Vec::new() };
+        // Read raw, since a file that isn't UTF-8 loads fine and its removed
+        // notes must be found at their offsets on disk
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(shredder),
+            Err(error) => return Err(error).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let ranges = note_ranges(&bytes, ids);
+        if !ranges.is_empty() {
+            shredder.targets.push((open(path)?, ranges));
+        }
+        Ok(shredder)
+    }
+    
+    // All of a file derived from the notes, such as the search index
+    pub fn add_file(&mut self, path: &Path) -> Result<()> {
+        let Ok(len) = fs::metadata(path).map(|metadata| metadata.len()) else {
+            return Ok(());
+        };
+        self.targets.push((open(path)?, std::iter::once(0..len).collect()));
+        Ok(())
+    }
+    
+    pub fn overwrite(self) -> Result<()> {
+        for (mut file, ranges) in self.targets {
+            for range in ranges {
+                file.seek(SeekFrom::Start(range.start))?;
+                let mut left = range.end - range.start;
+                let zeros = [0u8; 4096];
+                while left > 0 {
+                    let chunk = left.min(zeros.len() as u64) as usize;
+                    file.write_all(&zeros[..chunk]).context("Failed to overwrite removed notes")?;
+                    left -= chunk as u64;
+                }
+            }
+            file.sync_all().context("Failed to overwrite removed notes")?;
+        }
+        Ok(())
+    }
+}
+
+fn open(path: &Path) -> Result<File> {
+    OpenOptions::new().write(true).open(path)
+        .with_context(|| format!("Failed to open {} to overwrite", path.display()))
+}
+
+// Byte ranges from each matching note's header up to the next header. The
+// notes are found in the decoded text and mapped back by line number, which
+// decoding keeps.
+fn note_ranges(bytes: &[u8], ids: &HashSet<String>) -> Vec<Range<u64>> {
+    let (content, _) = encoding::decode(bytes);
+    // Only the IDs are needed, so the date given to unreadable headers doesn't matter
+    let Ok(notes) = NoteParser::parse_notes_from_text(&content, DateTime::default()) else {
+        return Vec::new();
+    };
+    let (starts, _) = NoteParser::header_lines(&content, &notes);
+    let offsets = line_offsets(bytes);
+    let len = bytes.len() as u64;
+    let line_start = |line: usize| offsets.get(line - 1).copied().unwrap_or(len);
+    let mut ranges = Vec::new();
+    for (index, (note, start)) in notes.iter().zip(&starts).enumerate() {
+        if !ids.contains(&note.id) {
+            continue;
+        }
+        let end = starts.get(index + 1).map(|next| line_start(*next)).unwrap_or(len);
+        ranges.push(line_start(*start)..end);
+    }
+    ranges
+}
+
+// Where each line starts, ending lines at "\r\n", "\r" or "\n" as decoding does
+fn line_offsets(bytes: &[u8]) -> Vec<u64> {
+    let mut offsets = vec![0];
+    for (index, &byte) in bytes.iter().enumerate() {
+        if byte == b'\n' || (byte == b'\r' && bytes.get(index + 1) != Some(&b'\n')) {
+            offsets.push(index as u64 + 1);
+        }
+    }
+    offsets
+}
@@ -0,0 +1,32 @@
+use std::collections::HashSet;
+
+use crate::inbox;
+use crate::note::Note;
+
+// Near-duplicate detection for new notes: the share of distinct lowercase
+// words two texts have in common (Jaccard similarity), from 0.0 to 1.0
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(&b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+// The existing note most like `text`, if any reaches `threshold`. Archived
+// notes are left out since capturing an idea again usually means reviving it.
+pub fn most_similar<'a>(notes: &'a [Note], text: &str, threshold: f64) -> Option<(&'a Note, f64)> {
+    notes.iter()
+        .filter(|note| !inbox::is_archived(note))
+        .map(|note| (note, similarity(&note.content, text)))
+        .filter(|(_, score)| *score >= threshold)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
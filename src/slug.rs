@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::note::Note;
+
+// File names made from the first line of a note: lowercased, accented Latin
+// letters spelled out in ASCII, and anything that isn't a letter or digit
+// collapsed to '-'. Exports of many notes give each one a different name and
+// list which note went where in a manifest.
+pub const MANIFEST_FILE: &str = "manifest.tsv";
+
+// Longest slug taken from a note's first line
+const MAX_LEN: usize = 40;
+
+pub fn from_line(line: &str) -> String {
+    let mut slug = String::new();
+    let mut truncated = false;
+    for c in line.chars().flat_map(char::to_lowercase) {
+        match transliterate(c) {
+            Some(ascii) => slug.push_str(ascii),
+            None if c.is_alphanumeric() => slug.push(c),
+            None if !slug.is_empty() && !slug.ends_with('-') => slug.push('-'),
+            None => {}
+        }
+        if slug.chars().count() >= MAX_LEN {
+            truncated = true;
+            break;
+        }
+    }
+    // Cut back to the last whole word rather than stop mid-word
+    if let Some(end) = slug.rfind('-').filter(|end| truncated && *end > 0) {
+        slug.truncate(end);
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+// A different file stem for each note, in the order given: its slug, or its
+// ID when it has none. Slugs shared by several notes get "-2", "-3" and so on
+// in the order the notes were written, so exporting again after adding a note
+// doesn't rename the files of older ones.
+pub fn unique(notes: &[&Note]) -> Vec<String> {
+    let mut by_age: Vec<usize> = (0..notes.len()).collect();
+    by_age.sort_by_key(|index| (notes[*index].timestamp, &notes[*index].id));
+    
+    let mut taken = HashSet::new();
+    let mut stems = vec![String::new(); notes.len()];
+    for index in by_age {
+        let note = notes[index];
+        let slug = note.slug();
+        let base = if slug.is_empty() { note.id.clone() } else { slug };
+        let mut stem = base.clone();
+        let mut suffix = 2;
+        while !taken.insert(stem.clone()) {
+            stem = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        stems[index] = stem;
+    }
+    stems
+}
+
+// "<id>\t<path>" for each exported note, paths relative to `dir`
+pub fn write_manifest(dir: &Path, files: &BTreeMap<String, String>) -> Result<()> {
+    let mut content = String::from("id\tfile\n");
+    for (id, file) in files {
+        content.push_str(&format!("{}\t{}\n", id, file));
+    }
+    let path = dir.join(MANIFEST_FILE);
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => "s",
+        'ß' => "ss",
+        'ţ' | 'ť' | 'ŧ' | 'ț' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
@@ -0,0 +1,52 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// `note snap` runs a screenshot tool and attaches the image it writes to a
+// new note. The command is split on spaces and `{file}` in it is replaced by
+// the path the image should be written to.
+pub const SNAP_TAG: &str = "screenshot";
+
+// Interactive region capture with the tool each platform ships
+pub fn default_command() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "screencapture -i {file}"
+    } else if cfg!(windows) {
+        ""
+    } else {
+        "gnome-screenshot -a -f {file}"
+    }
+}
+
+// The image written by the command, or None when nothing was captured, as
+// when the selection is cancelled. The caller removes it once attached.
+pub fn capture(command: &str, now: DateTime<Local>) -> Result<Option<PathBuf>> {
+    let dir = std::env::temp_dir().join(format!("note-snap-{}", std::process::id()));
+    fs::create_dir_all(&dir).context("Failed to create a temporary directory")?;
+    let path = dir.join(format!("screenshot-{}.png", now.format("%Y%m%d-%H%M%S")));
+    let file = path.to_string_lossy();
+    
+    let mut words = command.split_whitespace().map(|word| word.replace("{file}", &file));
+    let program = words.next().context("Set `command` in the [snap] config section to use note snap")?;
+    // Tools differ in how they exit when cancelled, so only the file counts;
+    // their own error messages still reach the terminal
+    Command::new(&program)
+        .args(words)
+        .status()
+        .with_context(|| format!("Failed to run screenshot command '{}'", program))?;
+    
+    if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > 0) {
+        Ok(Some(path))
+    } else {
+        let _ = fs::remove_dir_all(&dir);
+        Ok(None)
+    }
+}
+
+pub fn clean_up(path: &Path) {
+    if let Some(dir) = path.parent() {
+        let _ = fs::remove_dir_all(dir);
+    }
+}
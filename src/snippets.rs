@@ -0,0 +1,70 @@
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+
+use crate::timezone;
+
+// `:name:` in the text of a new or edited note is replaced by the snippet of
+// that name from the [snippets] config table, or by one of the built-in ones
+// below. Unknown names, as in `10:30:00`, and anything inside ``` fences are
+// left as typed.
+pub fn expand(text: &str, snippets: &BTreeMap<String, String>, keep: &str, now: DateTime<Local>) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut fenced = false;
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            expanded.push('\n');
+        }
+        let fence = line.trim_start().starts_with("```");
+        if fence {
+            fenced = !fenced;
+        }
+        if fence || fenced {
+            expanded.push_str(line);
+        } else {
+            expand_line(line, snippets, keep, now, &mut expanded);
+        }
+    }
+    expanded
+}
+
+// `keep` is the note as it was before an edit: snippets already in it were
+// kept on purpose, so only newly typed ones are expanded
+fn expand_line(line: &str, snippets: &BTreeMap<String, String>, keep: &str, now: DateTime<Local>, expanded: &mut String) {
+    let mut rest = line;
+    while let Some(start) = rest.find(':') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name_len = after.find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '+'))).unwrap_or(after.len());
+        let value = if name_len > 0 && after[name_len..].starts_with(':') {
+            let token = &rest[start..start + name_len + 2];
+            if keep.contains(token) { None } else { lookup(&after[..name_len], snippets, now) }
+        } else {
+            None
+        };
+        match value {
+            Some(value) => {
+                expanded.push_str(&value);
+                rest = &after[name_len + 1..];
+            }
+            // The colon may close this name and open the next one
+            None => {
+                expanded.push(':');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+}
+
+fn lookup(name: &str, snippets: &BTreeMap<String, String>, now: DateTime<Local>) -> Option<String> {
+    if let Some(value) = snippets.get(name) {
+        return Some(value.clone());
+    }
+    let now = timezone::display(&now);
+    match name {
+        "date" => Some(now.format("%Y-%m-%d").to_string()),
+        "time" => Some(now.format("%H:%M").to_string()),
+        "now" => Some(now.format("%Y-%m-%d %H:%M").to_string()),
+        _ => None,
+    }
+}
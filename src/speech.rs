@@ -0,0 +1,62 @@
+use anyhow::{bail, Context, Result};
+use std::process::Command;
+
+// `note say` reads a note aloud with a text-to-speech command. The command is
+// split on spaces and `{text}` in it is replaced by what to say, as a single
+// argument, so no shell quoting is involved.
+pub fn default_command() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "say {text}"
+    } else if cfg!(windows) {
+        ""
+    } else {
+        "espeak-ng {text}"
+    }
+}
+
+// The note as it should sound: Markdown markers, code blocks and link
+// addresses are left out, as a voice reading them out only gets in the way
+pub fn spoken_text(content: &str) -> String {
+    let mut sentences = Vec::new();
+    let mut fenced = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            fenced = !fenced;
+            if fenced {
+                sentences.push("Code block.".to_string());
+            }
+            continue;
+        }
+        if fenced || line.is_empty() {
+            continue;
+        }
+        let line = line.trim_start_matches('#').trim_start();
+        let line = ["- [ ] ", "- [x] ", "- [X] ", "- ", "* ", "> "].iter()
+            .find_map(|marker| line.strip_prefix(marker))
+            .unwrap_or(line);
+        let words: Vec<&str> = line.split_whitespace()
+            .map(|word| if word.starts_with("http://") || word.starts_with("https://") { "link" } else { word })
+            .collect();
+        let mut sentence = words.join(" ").replace(['*', '`', '_'], "");
+        // A pause between lines, which are often separate thoughts
+        if !sentence.ends_with(['.', '!', '?', ':', ';']) {
+            sentence.push('.');
+        }
+        sentences.push(sentence);
+    }
+    sentences.join(" ")
+}
+
+pub fn say(command: &str, text: &str) -> Result<()> {
+    let mut words = command.split_whitespace().map(|word| word.replace("{text}", text));
+    let program = words.next().context("Set `command` in the [say] config section to use note say")?;
+    let status = Command::new(&program)
+        .args(words)
+        .status()
+        .with_context(|| format!("Failed to run speech command '{}'", program))?;
+    if !status.success() {
+        bail!("Speech command '{}' exited with {}", program, status);
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+use chrono::{Duration, NaiveDate};
+
+use crate::note::Note;
+
+// Spaced-repetition state for a note, scheduled with the SM-2 algorithm.
+// Stored in the note header as `srs=due,interval,repetitions,easiness`.
+#[derive(Debug, Clone)]
+pub struct SrsCard {
+    pub due: NaiveDate,
+    pub interval: u32,
+    pub repetitions: u32,
+    pub easiness: f64,
+}
+
+impl SrsCard {
+    pub fn new(today: NaiveDate) -> Self {
+        Self {
+            due: today,
+            interval: 0,
+            repetitions: 0,
+            easiness: 2.5,
+        }
+    }
+    
+    pub fn from_note(note: &Note) -> Option<Self> {
+        let fields = note.meta_list("srs");
+        if fields.len() != 4 {
+            return None;
+        }
+        Some(Self {
+            due: NaiveDate::parse_from_str(&fields[0], "%Y-%m-%d").ok()?,
+            interval: fields[1].parse().ok()?,
+            repetitions: fields[2].parse().ok()?,
+            easiness: fields[3].parse().ok()?,
+        })
+    }
+    
+    pub fn store(&self, note: &mut Note) {
+        note.set_meta_list("srs", &[
+            self.due.format("%Y-%m-%d").to_string(),
+            self.interval.to_string(),
+            self.repetitions.to_string(),
+            format!("{:.2}", self.easiness),
+        ]);
+    }
+    
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        self.due <= today
+    }
+    
+    // Applies a recall grade from 0 (blackout) to 5 (perfect) and schedules the next review
+    pub fn review(&mut self, grade: u8, today: NaiveDate) {
+        let grade = grade.min(5);
+        if grade >= 3 {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.easiness).round() as u32,
+            };
+            self.repetitions += 1;
+        } else {
+            self.repetitions = 0;
+            self.interval = 1;
+        }
+        
+        let penalty = (5 - grade) as f64;
+        self.easiness = (self.easiness + 0.1 - penalty * (0.08 + penalty * 0.02)).max(1.3);
+        self.due = today + Duration::days(self.interval as i64);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,68 @@
+use chrono::NaiveDate;
+use colored::*;
+
+use crate::config::{SummaryConfig, SummarySection};
+use crate::glyphs::glyphs;
+use crate::inbox;
+use crate::layout::Layout;
+use crate::note::Note;
+use crate::srs::SrsCard;
+
+// `note summary` prints a short digest meant for a shell startup file: the
+// configured sections in order, each note once, and nothing at all when no
+// section has anything to show.
+pub const DUE_KEY: &str = "due";
+
+// Notes with a `due=YYYY-MM-DD` of today or earlier, such as imported tasks,
+// and spaced repetition cards due for review
+pub fn due_date(note: &Note, today: NaiveDate) -> Option<NaiveDate> {
+    let due = note.meta.get(DUE_KEY)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        .or_else(|| SrsCard::from_note(note).map(|card| card.due))?;
+    (due <= today).then_some(due)
+}
+
+pub fn render(notes: &[Note], summary_config: &SummaryConfig, layout: &Layout, today: NaiveDate) -> String {
+    let mut newest: Vec<&Note> = notes.iter().filter(|note| !inbox::is_archived(note)).collect();
+    newest.sort_by_key(|note| std::cmp::Reverse(note.timestamp));
+    
+    let mut shown: Vec<&str> = Vec::new();
+    let mut lines = Vec::new();
+    for section in &summary_config.sections {
+        let (title, mut picked, limit): (&str, Vec<&Note>, usize) = match section {
+            SummarySection::Pinned => ("Pinned", newest.iter()
+                .filter(|note| note.has_tag(&summary_config.pinned_tag))
+                .copied()
+                .collect(), summary_config.limit),
+            SummarySection::Due => {
+                let mut due: Vec<&Note> = newest.iter()
+                    .filter(|note| due_date(note, today).is_some())
+                    .copied()
+                    .collect();
+                due.sort_by_key(|note| due_date(note, today));
+                ("Due", due, summary_config.limit)
+            }
+            SummarySection::Inbox => ("Inbox", newest.iter()
+                .filter(|note| inbox::is_pending(note, today))
+                .copied()
+                .collect(), summary_config.limit),
+            SummarySection::Recent => ("Newest", newest.clone(), summary_config.recent),
+        };
+        picked.retain(|note| !shown.contains(&note.id.as_str()));
+        if picked.is_empty() || limit == 0 {
+            continue;
+        }
+        
+        lines.push(title.bold().to_string());
+        for note in picked.iter().take(limit) {
+            lines.push(format!("  {}", layout.render(note)));
+            shown.push(&note.id);
+        }
+        // Newest is a window onto everything, so only the others say what's left
+        let hidden = picked.len().saturating_sub(limit);
+        if hidden > 0 && *section != SummarySection::Recent {
+            lines.push(format!("  {}", format!("{} {} more", glyphs().more, hidden).bright_black()));
+        }
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,129 @@
+// Pasted spreadsheet rows: tab-separated (as copied from a spreadsheet) or
+// comma-separated text. `--table` stores them as an aligned Markdown pipe
+// table, and tab-separated rows already in a note are aligned when shown.
+
+// Rows of cells when every non-empty line has the same number of cells (at
+// least two) and there are at least two lines
+pub fn parse(text: &str) -> Option<Vec<Vec<String>>> {
+    let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+    if lines.len() < 2 {
+        return None;
+    }
+    
+    let tabs = text.contains('\t');
+    let rows: Vec<Vec<String>> = lines.iter()
+        .map(|line| if tabs { split_tsv(line) } else { split_csv(line) })
+        .collect();
+    let columns = rows[0].len();
+    if columns < 2 || rows.iter().any(|row| row.len() != columns) {
+        return None;
+    }
+    Some(rows)
+}
+
+// A Markdown pipe table with the first row as the header. Columns holding
+// only numbers below the header are right-aligned.
+pub fn format_markdown(rows: &[Vec<String>]) -> String {
+    let rows: Vec<Vec<String>> = rows.iter()
+        .map(|row| row.iter().map(|cell| cell.replace('|', "\\|")).collect())
+        .collect();
+    // Separator rows need at least three dashes
+    let widths: Vec<usize> = column_widths(&rows).into_iter().map(|width| width.max(3)).collect();
+    let numeric: Vec<bool> = (0..widths.len())
+        .map(|column| rows[1..].iter().all(|row| is_number(&row[column])))
+        .collect();
+    
+    let format_row = |row: &[String]| {
+        let cells: Vec<String> = row.iter().enumerate()
+            .map(|(column, cell)| pad(cell, widths[column], numeric[column]))
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let separator: Vec<String> = widths.iter().zip(&numeric)
+        .map(|(width, numeric)| {
+            let dashes = "-".repeat(*width);
+            if *numeric { format!("{}:", &dashes[1..]) } else { dashes }
+        })
+        .collect();
+    
+    let mut lines = vec![format_row(&rows[0]), format!("| {} |", separator.join(" | "))];
+    lines.extend(rows[1..].iter().map(|row| format_row(row)));
+    lines.join("\n")
+}
+
+// Lines with runs of two or more tab-separated rows of equal width replaced
+// by space-aligned columns; other lines are unchanged
+pub fn align_tab_rows(lines: &[&str]) -> Vec<String> {
+    let mut aligned: Vec<String> = Vec::with_capacity(lines.len());
+    let mut start = 0;
+    while start < lines.len() {
+        let columns = lines[start].split('\t').count();
+        let mut end = start;
+        while columns > 1 && end < lines.len() && lines[end].split('\t').count() == columns {
+            end += 1;
+        }
+        
+        if end - start >= 2 {
+            let rows: Vec<Vec<String>> = lines[start..end].iter().map(|line| split_tsv(line)).collect();
+            let widths = column_widths(&rows);
+            for row in &rows {
+                let cells: Vec<String> = row.iter().enumerate()
+                    .map(|(column, cell)| pad(cell, widths[column], is_number(cell)))
+                    .collect();
+                aligned.push(cells.join("  ").trim_end().to_string());
+            }
+            start = end;
+        } else {
+            aligned.push(lines[start].to_string());
+            start += 1;
+        }
+    }
+    aligned
+}
+
+fn split_tsv(line: &str) -> Vec<String> {
+    line.split('\t').map(|cell| cell.trim().to_string()).collect()
+}
+
+// Handles quoted cells with embedded commas and doubled quotes
+fn split_csv(line: &str) -> Vec<String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                cell.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            c => cell.push(c),
+        }
+    }
+    cells.push(cell.trim().to_string());
+    cells
+}
+
+fn column_widths(rows: &[Vec<String>]) -> Vec<usize> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    (0..columns)
+        .map(|column| rows.iter()
+            .filter_map(|row| row.get(column))
+            .map(|cell| cell.chars().count())
+            .max()
+            .unwrap_or(0))
+        .collect()
+}
+
+fn pad(cell: &str, width: usize, right: bool) -> String {
+    if right { format!("{:>width$}", cell) } else { format!("{:<width$}", cell) }
+}
+
+// Numbers as they appear in spreadsheets: 1,200.50 or -3% or $4
+fn is_number(cell: &str) -> bool {
+    let digits = cell.trim().trim_start_matches(['-', '+', '$', '€', '£']).trim_end_matches('%');
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+        && digits.chars().any(|c| c.is_ascii_digit())
+}
@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::checklist;
+use crate::note::Note;
+
+// Notes tagged `todo` are tasks on their own; checkbox lines (`- [ ] ...`)
+// in any note are tasks too. A todo note with checkboxes exports just the
+// checkboxes.
+pub const TODO_TAG: &str = "todo";
+
+// Taskwarrior's compact ISO 8601 timestamps, always in UTC
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+// The subset of the taskwarrior JSON format that maps onto notes
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Task {
+    pub uuid: String,
+    pub description: String,
+    pub status: String,
+    pub entry: String,
+    // When a completed task was finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Annotation {
+    pub entry: String,
+    pub description: String,
+}
+
+// A task read back from taskwarrior, ready to become a note
+pub struct ImportedTask {
+    pub uuid: String,
+    pub status: String,
+    pub content: String,
+    pub entry: Option<DateTime<Local>>,
+    pub tags: Vec<String>,
+    pub due: Option<NaiveDate>,
+    pub priority: Option<String>,
+}
+
+impl ImportedTask {
+    // Completed and deleted tasks aren't imported
+    pub fn is_open(&self) -> bool {
+        self.status == "pending" || self.status == "waiting"
+    }
+}
+
+pub fn tasks_for_note(note: &Note) -> Vec<Task> {
+    let tags: Vec<String> = note.tags().into_iter().filter(|tag| tag != TODO_TAG).collect();
+    let due = note.meta.get("due").and_then(|due| format_due(due));
+    let priority = note.meta.get("priority").and_then(|p| parse_priority(p));
+    let annotation = Annotation {
+        entry: format_timestamp(&note.timestamp),
+        description: format!("note:{}", note.id),
+    };
+    
+    let checkboxes: Vec<(bool, &str)> = note.content.lines().filter_map(checklist::parse_item).collect();
+    if checkboxes.is_empty() {
+        if !note.has_tag(TODO_TAG) {
+            return Vec::new();
+        }
+        let description = note.content.lines().next().unwrap_or("").trim().to_string();
+        return vec![Task {
+            uuid: task_uuid(&note.id, 0),
+            description,
+            status: "pending".to_string(),
+            entry: format_timestamp(&note.timestamp),
+            end: None,
+            due,
+            priority,
+            tags,
+            annotations: vec![annotation],
+        }];
+    }
+    
+    checkboxes.into_iter().enumerate().map(|(index, (done, text))| Task {
+        uuid: task_uuid(&note.id, index),
+        description: text.to_string(),
+        status: if done { "completed" } else { "pending" }.to_string(),
+        entry: format_timestamp(&note.timestamp),
+        // The note doesn't record when a box was ticked
+        end: done.then(|| format_timestamp(&note.timestamp)),
+        due: due.clone(),
+        priority: priority.clone(),
+        tags: tags.clone(),
+        annotations: vec![annotation.clone()],
+    }).collect()
+}
+
+// Reads `task export` output: a JSON array, or one task object per line
+pub fn parse_export(content: &str) -> Result<Vec<ImportedTask>> {
+    let trimmed = content.trim();
+    let tasks: Vec<Task> = if trimmed.starts_with('[') {
+        serde_json::from_str(trimmed).context("Failed to parse taskwarrior JSON")?
+    } else {
+        trimmed.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).context("Failed to parse taskwarrior JSON"))
+            .collect::<Result<_>>()?
+    };
+    
+    Ok(tasks.into_iter()
+        .map(|task| {
+            // Annotations become extra lines, except the link back to a note
+            let mut lines = vec![task.description];
+            lines.extend(task.annotations.into_iter()
+                .map(|a| a.description)
+                .filter(|text| !text.starts_with("note:")));
+            
+            let mut tags = vec![TODO_TAG.to_string()];
+            tags.extend(task.tags.iter().map(|t| Note::normalize_tag(t)).filter(|t| !t.is_empty()));
+            ImportedTask {
+                uuid: task.uuid,
+                status: task.status,
+                content: lines.join("\n"),
+                entry: parse_timestamp(&task.entry),
+                tags,
+                due: task.due.as_deref().and_then(parse_timestamp).map(|due| due.date_naive()),
+                priority: task.priority,
+            }
+        })
+        .collect())
+}
+
+// Taskwarrior priorities are H, M or L
+fn parse_priority(value: &str) -> Option<String> {
+    match value.to_lowercase().as_str() {
+        "h" | "high" => Some("H".to_string()),
+        "m" | "medium" => Some("M".to_string()),
+        "l" | "low" => Some("L".to_string()),
+        _ => None,
+    }
+}
+
+// A `due=YYYY-MM-DD` date means local midnight, like `task add due:...`
+fn format_due(value: &str) -> Option<String> {
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let midnight = Local.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).earliest()?;
+    Some(format_timestamp(&midnight))
+}
+
+fn format_timestamp(timestamp: &DateTime<Local>) -> String {
+    timestamp.with_timezone(&Utc).format(TIMESTAMP_FORMAT).to_string()
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Local>> {
+    let naive = NaiveDateTime::parse_from_str(value, TIMESTAMP_FORMAT).ok()?;
+    Some(Utc.from_utc_datetime(&naive).with_timezone(&Local))
+}
+
+// Stable per note and checkbox position, so exporting again updates the
+// same tasks instead of duplicating them
+fn task_uuid(note_id: &str, index: usize) -> String {
+    let hash = blake3::hash(format!("note-task:{}:{}", note_id, index).as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&hash.as_bytes()[..16]);
+    // Mark as a version 4 (random) UUID with the RFC 4122 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
\ No newline at end of file
@@ -0,0 +1,51 @@
+// With `tidy` under [storage], note text is cleaned up whenever the store is
+// saved, for text pasted from web pages and word processors: typographic
+// quotes, dashes and ellipses become their ASCII forms, invisible and
+// non-breaking spaces are dropped or made plain, trailing whitespace goes and
+// runs of blank lines shrink to one. Fenced code blocks keep their text and
+// blank lines, and only lose trailing whitespace.
+pub fn content(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut fenced = false;
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.trim_start().starts_with("```") {
+            fenced = !fenced;
+            lines.push(line.to_string());
+            continue;
+        }
+        if fenced {
+            lines.push(line.to_string());
+            continue;
+        }
+        let line = punctuation(line);
+        let line = line.trim_end();
+        if line.is_empty() && lines.last().is_some_and(|previous| previous.is_empty()) {
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+fn punctuation(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    for c in line.chars() {
+        match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' => plain.push('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' => plain.push('"'),
+            '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2212}' => plain.push('-'),
+            '\u{2014}' | '\u{2015}' => plain.push_str("--"),
+            '\u{2026}' => plain.push_str("..."),
+            '\u{00A0}' | '\u{2002}'..='\u{200A}' | '\u{202F}' | '\u{205F}' => plain.push(' '),
+            // Zero-width spaces and soft hyphens; the joiners emoji and some
+            // scripts need are kept
+            '\u{00AD}' | '\u{200B}' | '\u{2060}' | '\u{FEFF}' => {}
+            c => plain.push(c),
+        }
+    }
+    plain
+}
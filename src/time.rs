@@ -0,0 +1,52 @@
+use chrono::{DateTime, Duration, Local};
+use std::sync::Mutex;
+
+// Where a NoteManager gets the current time: note timestamps and the IDs
+// derived from them, view times, "today" for triage, reviews, summaries and
+// the journal, audit entries and backup names. The system clock unless the
+// manager was opened with another, so library users and tests can build
+// reproducible stores.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Local>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+// Always the same time, or moving on by `step` each time it is read so notes
+// added one after another still get distinct timestamps
+pub struct FixedClock {
+    next: Mutex<DateTime<Local>>,
+    step: Duration,
+}
+
+impl FixedClock {
+    pub fn at(time: DateTime<Local>) -> Self {
+        Self { next: Mutex::new(time), step: Duration::zero() }
+    }
+    
+    pub fn with_step(mut self, step: Duration) -> Self {
+        self.step = step;
+        self
+    }
+    
+    // Jumps ahead, e.g. to a day when reviews fall due
+    pub fn advance(&self, by: Duration) {
+        let mut next = self.next.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *next += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        let mut next = self.next.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = *next;
+        *next += self.step;
+        now
+    }
+}
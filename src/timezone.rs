@@ -0,0 +1,67 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, FixedOffset, Local, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use std::sync::OnceLock;
+
+// Timestamps are stored in UTC so a file shared between machines in
+// different timezones reads the same everywhere, and shown in the display
+// timezone: the machine's own unless configured or given with --tz.
+#[derive(Clone, Copy)]
+pub enum DisplayZone {
+    Local,
+    Named(Tz),
+    Fixed(FixedOffset),
+}
+
+impl DisplayZone {
+    // "local", an IANA name such as "UTC" or "Europe/Berlin", or an offset
+    // such as "+05:30"
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("local") {
+            return Ok(Self::Local);
+        }
+        if let Ok(offset) = name.parse::<FixedOffset>() {
+            return Ok(Self::Fixed(offset));
+        }
+        match name.parse::<Tz>().or_else(|_| name.to_uppercase().parse::<Tz>()) {
+            Ok(tz) => Ok(Self::Named(tz)),
+            Err(_) => bail!("Unknown timezone '{}': use an IANA name such as Europe/Berlin, or an offset such as +05:30", name),
+        }
+    }
+}
+
+static ZONE: OnceLock<DisplayZone> = OnceLock::new();
+
+pub fn set_display_zone(zone: DisplayZone) {
+    let _ = ZONE.set(zone);
+}
+
+// A timestamp as the user should see it
+pub fn display(timestamp: &DateTime<Local>) -> DateTime<FixedOffset> {
+    match ZONE.get().copied().unwrap_or(DisplayZone::Local) {
+        DisplayZone::Local => timestamp.fixed_offset(),
+        DisplayZone::Named(tz) => timestamp.with_timezone(&tz).fixed_offset(),
+        DisplayZone::Fixed(offset) => timestamp.with_timezone(&offset),
+    }
+}
+
+// A timestamp as written to notes.txt, e.g. 2025-03-21T08:30:00.123Z
+pub fn storage(timestamp: &DateTime<Local>) -> String {
+    timestamp.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+// Whether two timestamps could be one moment written down differently: apart
+// by no more than `tolerance` (fractions of a second lost in a copy), once any
+// whole quarter-hour difference of up to 14 hours is taken out, as when a
+// copy had its local time read back in another timezone
+pub fn same_moment(a: &DateTime<Local>, b: &DateTime<Local>, tolerance: Duration) -> bool {
+    let quarter = Duration::minutes(15).num_milliseconds();
+    let tolerance = tolerance.num_milliseconds();
+    let apart = (*a - *b).num_milliseconds().abs();
+    if apart > Duration::hours(14).num_milliseconds() + tolerance {
+        return false;
+    }
+    let offset = (apart + quarter / 2) / quarter * quarter;
+    (apart - offset).abs() <= tolerance
+}
@@ -0,0 +1,150 @@
+use anyhow::{bail, Context, Result};
+use std::collections::{HashSet, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// `note watch` follows a file or named pipe and turns what other programs
+// write to it into notes. A file is followed from its end like `tail -f`,
+// starting over when it is truncated; a pipe is reopened each time a writer
+// closes it, so one `note watch` serves any number of writers in turn.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+pub struct Tail {
+    path: PathBuf,
+    reader: BufReader<File>,
+    fifo: bool,
+    position: u64,
+    // A line still being written, until its newline arrives
+    pending: String,
+}
+
+impl Tail {
+    pub fn open(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            bail!("{} does not exist", path.display());
+        }
+        let fifo = is_fifo(path);
+        // Opening a pipe waits for its first writer
+        let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let position = if fifo { 0 } else { file.seek(SeekFrom::End(0))? };
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader: BufReader::new(file),
+            fifo,
+            position,
+            pending: String::new(),
+        })
+    }
+    
+    // The next line written, without its line ending. None means a writer
+    // closed the pipe, which ends its last block; files never return None.
+    pub fn next_line(&mut self) -> Result<Option<String>> {
+        loop {
+            let read = self.reader.read_line(&mut self.pending)
+                .with_context(|| format!("Failed to read {}", self.path.display()))?;
+            self.position += read as u64;
+            if self.pending.ends_with('\n') {
+                let line = self.pending.trim_end_matches(['\n', '\r']).to_string();
+                self.pending.clear();
+                return Ok(Some(line));
+            }
+            if read > 0 {
+                continue;
+            }
+            if self.fifo {
+                // A last line without a newline is returned before waiting
+                // for the next writer
+                if !self.pending.is_empty() {
+                    return Ok(Some(std::mem::take(&mut self.pending)));
+                }
+                self.reader = BufReader::new(File::open(&self.path)
+                    .with_context(|| format!("Failed to reopen {}", self.path.display()))?);
+                return Ok(None);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+            let len = std::fs::metadata(&self.path).map(|metadata| metadata.len()).unwrap_or(0);
+            if len < self.position {
+                self.reader.seek(SeekFrom::Start(0))?;
+                self.position = 0;
+                self.pending.clear();
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_fifo(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_fifo())
+}
+
+#[cfg(not(unix))]
+fn is_fifo(_path: &Path) -> bool {
+    false
+}
+
+// At most `max` notes in any minute, so a runaway writer can't flood the
+// store; 0 lets everything through
+pub struct RateLimit {
+    max: usize,
+    recent: VecDeque<Instant>,
+}
+
+impl RateLimit {
+    pub fn per_minute(max: usize) -> Self {
+        Self { max, recent: VecDeque::new() }
+    }
+    
+    pub fn allow(&mut self) -> bool {
+        if self.max == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        while self.recent.front().is_some_and(|at| now.duration_since(*at) >= Duration::from_secs(60)) {
+            self.recent.pop_front();
+        }
+        if self.recent.len() >= self.max {
+            return false;
+        }
+        self.recent.push_back(now);
+        true
+    }
+}
+
+// Text saved within the last `window`, so a writer repeating itself, or
+// writing the same line to a file and a pipe, doesn't add the same note twice.
+// Older text is forgotten, both to keep memory bounded and because a line
+// written again much later is usually meant.
+pub const REPEAT_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+pub struct RecentText {
+    window: Duration,
+    seen: HashSet<String>,
+    recent: VecDeque<(Instant, String)>,
+}
+
+impl RecentText {
+    pub fn within(window: Duration) -> Self {
+        Self { window, seen: HashSet::new(), recent: VecDeque::new() }
+    }
+    
+    pub fn contains(&mut self, text: &str) -> bool {
+        let now = Instant::now();
+        while let Some((at, _)) = self.recent.front() && now.duration_since(*at) >= self.window {
+            if let Some((_, text)) = self.recent.pop_front() {
+                self.seen.remove(&text);
+            }
+        }
+        self.seen.contains(text)
+    }
+    
+    // Only text actually saved, so a line dropped by the rate limit still
+    // counts as new when it is written again
+    pub fn insert(&mut self, text: &str) {
+        if self.seen.insert(text.to_string()) {
+            self.recent.push_back((Instant::now(), text.to_string()));
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use colored::*;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+use crate::glyphs::glyphs;
+
+// Where note keeps its configuration and data: under $XDG_CONFIG_HOME and
+// $XDG_DATA_HOME when they are set, else ~/.config and ~/.local/share. The
+// spec says to ignore relative paths in them, so those fall back too. A store
+// or config file that still only exists in the old place is used from there,
+// with a notice on how to move it, rather than seeming to have disappeared.
+static CONFIG_NOTICE: Once = Once::new();
+static DATA_NOTICE: Once = Once::new();
+
+pub fn config_dir() -> Option<PathBuf> {
+    app_dir("XDG_CONFIG_HOME", &[".config"], "config.toml", &CONFIG_NOTICE)
+}
+
+pub fn data_dir() -> Option<PathBuf> {
+    app_dir("XDG_DATA_HOME", &[".local", "share"], "notes.txt", &DATA_NOTICE)
+}
+
+fn app_dir(var: &str, default: &[&str], file: &str, notice: &Once) -> Option<PathBuf> {
+    let fallback = dirs::home_dir().map(|home| default.iter().fold(home, |dir, part| dir.join(part)).join("note"));
+    let preferred = std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|base| base.is_absolute())
+        .map(|base| base.join("note"));
+    match (preferred, fallback) {
+        (Some(preferred), Some(fallback)) if preferred != fallback && !preferred.join(file).exists() && fallback.join(file).exists() => {
+            notice.call_once(|| print_notice(var, &fallback, &preferred));
+            Some(fallback)
+        }
+        (Some(preferred), _) => Some(preferred),
+        (None, fallback) => fallback,
+    }
+}
+
+fn print_notice(var: &str, old: &Path, new: &Path) {
+    eprintln!("{} Still using {}: ${} is set, but nothing is in {} yet",
+        glyphs().warning.yellow(),
+        old.display(),
+        var,
+        new.display()
+    );
+    eprintln!("  Move it there to follow ${}: {}",
+        var,
+        format!("mv {} {}", old.display(), new.display()).bright_cyan()
+    );
+}
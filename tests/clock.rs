@@ -0,0 +1,116 @@
+use chrono::{DateTime, Duration, Local, TimeZone};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use note::config::SummaryConfig;
+use note::layout::Layout;
+use note::manager::NoteManager;
+use note::resurface;
+use note::summary;
+use note::time::FixedClock;
+
+// 9:00 on a Monday, in whatever timezone the tests run in
+fn start() -> DateTime<Local> {
+    Local.with_ymd_and_hms(2025, 3, 10, 9, 0, 0).single().unwrap()
+}
+
+// An empty store of its own for each test, under the system temp directory
+fn store_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("note-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn notes_are_dated_by_the_clock() {
+    let dir = store_dir("dates");
+    let clock = Arc::new(FixedClock::at(start()).with_step(Duration::seconds(1)));
+    let mut manager = NoteManager::open_with_clock(dir.clone(), clock.clone()).unwrap();
+    let first = manager.add_note("first".to_string(), &[]).unwrap();
+    clock.advance(Duration::days(1));
+    let second = manager.add_note("second".to_string(), &[]).unwrap();
+    
+    // Read back from the file, as a later run would see them
+    let reopened = NoteManager::open_with_clock(dir.clone(), clock).unwrap();
+    let timestamp = |id: &str| reopened.get_notes().iter().find(|note| note.id == id).unwrap().timestamp;
+    assert_eq!(timestamp(&first), start());
+    assert_eq!(timestamp(&second), start() + Duration::days(1) + Duration::seconds(1));
+    
+    // The same notes at the same times get the same IDs in another store
+    let other_dir = store_dir("dates-again");
+    let clock = Arc::new(FixedClock::at(start()).with_step(Duration::seconds(1)));
+    let mut other = NoteManager::open_with_clock(other_dir.clone(), clock.clone()).unwrap();
+    assert_eq!(other.add_note("first".to_string(), &[]).unwrap(), first);
+    clock.advance(Duration::days(1));
+    assert_eq!(other.add_note("second".to_string(), &[]).unwrap(), second);
+    
+    let _ = std::fs::remove_dir_all(&dir);
+    let _ = std::fs::remove_dir_all(&other_dir);
+}
+
+#[test]
+fn view_times_come_from_the_clock() {
+    let dir = store_dir("viewed");
+    let clock = Arc::new(FixedClock::at(start()));
+    let mut manager = NoteManager::open_with_clock(dir.clone(), clock.clone()).unwrap();
+    let id = manager.add_note("look at me later".to_string(), &[]).unwrap();
+    let viewed = |manager: &NoteManager| resurface::last_viewed(manager.get_notes().iter().find(|note| note.id == id).unwrap());
+    // Never viewed counts from when it was written
+    assert_eq!(viewed(&manager), start());
+    
+    clock.advance(Duration::hours(3));
+    manager.mark_viewed(&id).unwrap();
+    assert_eq!(viewed(&manager), start() + Duration::hours(3));
+    
+    clock.advance(Duration::days(30));
+    let picked = manager.resurface_notes(1, false).unwrap();
+    assert_eq!(picked.len(), 1);
+    assert_eq!(viewed(&manager), start() + Duration::hours(3) + Duration::days(30));
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn summary_shows_what_is_due_on_the_clock_day() {
+    colored::control::set_override(false);
+    let dir = store_dir("summary");
+    let clock = Arc::new(FixedClock::at(start()));
+    let mut manager = NoteManager::open_with_clock(dir.clone(), clock.clone()).unwrap();
+    let id = manager.add_note("renew the passport".to_string(), &[]).unwrap();
+    manager.update_meta(&id, summary::DUE_KEY, Some("2025-03-11")).unwrap();
+    
+    let config = SummaryConfig::default();
+    let layout = Layout::parse(&config.line).unwrap();
+    let render = |manager: &NoteManager| summary::render(manager.get_notes(), &config, &layout, manager.now().date_naive());
+    // Only listed as the newest note until its day comes
+    assert!(!render(&manager).contains("Due"));
+    clock.advance(Duration::days(1));
+    let rendered = render(&manager);
+    assert!(rendered.starts_with(&format!("Due\n  [{}] renew the passport", id)), "{}", rendered);
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn journal_entries_are_timed_by_the_clock() {
+    colored::control::set_override(false);
+    let dir = store_dir("journal");
+    let clock = Arc::new(FixedClock::at(start()));
+    let mut manager = NoteManager::open_with_clock(dir.clone(), clock.clone()).unwrap();
+    let today = manager.now().date_naive();
+    let id = manager.write_journal(today, None, "Standup").unwrap();
+    clock.advance(Duration::minutes(95));
+    assert_eq!(manager.write_journal(today, Some(4), "Shipped it").unwrap(), id);
+    
+    let journal = manager.journal_for(today).unwrap();
+    assert_eq!(journal.content, "Journal, Monday Mar 10 2025\n\n09:00  Standup\n10:35  [mood: good] Shipped it");
+    assert!(manager.journal_for(today + Duration::days(1)).is_none());
+    
+    // Tomorrow starts a journal of its own
+    clock.advance(Duration::days(1));
+    let tomorrow = manager.now().date_naive();
+    assert_ne!(manager.write_journal(tomorrow, None, "Retro").unwrap(), id);
+    assert_eq!(manager.journal_for(tomorrow).unwrap().timestamp, start() + Duration::days(1) + Duration::minutes(95));
+    
+    let _ = std::fs::remove_dir_all(&dir);
+}
\ No newline at end of file